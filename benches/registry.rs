@@ -0,0 +1,103 @@
+use message_io::network::{self, Transport, NetworkController, Endpoint, ResourceType};
+use message_io::network::adapter::{Resource};
+use message_io::network::internal::{Poll, ResourceRegistry};
+
+use mio::{Interest, Registry, Token};
+use mio::event::{Source};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use std::net::{UdpSocket};
+use std::io::{self};
+use std::time::{Duration, Instant};
+
+const RESOURCES: usize = 1024;
+const THREADS: usize = 4;
+
+fn init_endpoints(controller: &NetworkController, receiver: &UdpSocket) -> Vec<Endpoint> {
+    let addr = receiver.local_addr().unwrap();
+    (0..RESOURCES).map(|_| controller.connect(Transport::Udp, addr).unwrap().0).collect()
+}
+
+fn concurrent_lookups(c: &mut Criterion) {
+    let (controller, _processor) = network::split();
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let endpoints = init_endpoints(&controller, &receiver);
+
+    c.bench_function(&format!("registry lookups by {} threads", THREADS), |b| {
+        b.iter_custom(|iters| {
+            let start = Instant::now();
+            crossbeam_utils::thread::scope(|scope| {
+                for index in 0..THREADS {
+                    let (controller, endpoints) = (&controller, &endpoints);
+                    scope.spawn(move |_| {
+                        for iter in 0..iters as usize {
+                            let endpoint = endpoints[(iter + index) % RESOURCES];
+                            controller.is_ready(endpoint.resource_id()).unwrap();
+                        }
+                    });
+                }
+            })
+            .unwrap();
+            start.elapsed()
+        });
+    });
+}
+
+// Resource without OS socket, so the registry is measured without the cost of the syscalls.
+struct DummyResource;
+
+impl Source for DummyResource {
+    fn register(&mut self, _: &Registry, _: Token, _: Interest) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reregister(&mut self, _: &Registry, _: Token, _: Interest) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn deregister(&mut self, _: &Registry) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Resource for DummyResource {
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+}
+
+fn concurrent_churn(c: &mut Criterion) {
+    let mut poll = Poll::default();
+    let registry = ResourceRegistry::<DummyResource, ()>::new(
+        poll.create_registry(0, ResourceType::Remote),
+    );
+    let _ids: Vec<_> =
+        (0..RESOURCES).map(|_| registry.register(DummyResource, (), false).unwrap()).collect();
+
+    c.bench_function(&format!("registry churn by {} threads", THREADS), |b| {
+        b.iter_custom(|iters| {
+            let start = Instant::now();
+            crossbeam_utils::thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    let registry = &registry;
+                    scope.spawn(move |_| {
+                        for _ in 0..iters {
+                            let id = registry.register(DummyResource, (), false).unwrap();
+                            registry.deregister(id);
+                        }
+                    });
+                }
+            })
+            .unwrap();
+            start.elapsed()
+        });
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(5));
+    targets = concurrent_lookups, concurrent_churn
+}
+criterion_main!(benches);
@@ -56,6 +56,7 @@ pub fn run(file_path: String) {
                 println!("\nReceiver disconnected");
             }
         },
+        NodeEvent::Source(..) => unreachable!(),
         NodeEvent::Signal(signal) => match signal {
             Signal::SendChunk => {
                 let mut data = [0; CHUNK_SIZE];
@@ -43,6 +43,7 @@ pub fn run(transport: Transport, remote_addr: RemoteAddr) {
                 handler.stop();
             }
         },
+        NodeEvent::Source(..) => unreachable!(),
         NodeEvent::Signal(signal) => match signal {
             Signal::Greet => {
                 let message = FromClientMessage::Ping;
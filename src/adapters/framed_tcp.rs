@@ -4,8 +4,10 @@ use crate::network::adapter::{
     Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
     ListeningInfo, PendingStatus,
 };
-use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen};
+use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen, SocketOption};
+use crate::network::{borrow_socket, set_socket_options, adopt_socket, inet_addr};
 use crate::util::encoding::{self, Decoder, MAX_ENCODED_SIZE};
+use crate::adapters::tcp::{self};
 
 use mio::net::{TcpListener, TcpStream};
 use mio::event::{Source};
@@ -26,6 +28,7 @@ const INPUT_BUFFER_SIZE: usize = u16::MAX as usize; // 2^16 - 1
 #[derive(Clone, Debug, Default)]
 pub struct FramedTcpConnectConfig {
     keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
 }
 
 impl FramedTcpConnectConfig {
@@ -34,11 +37,20 @@ impl FramedTcpConnectConfig {
         self.keepalive = Some(keepalive);
         self
     }
+
+    /// Sets an option in the socket before connecting.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct FramedTcpListenConfig {
     keepalive: Option<TcpKeepalive>,
+    reuse_port: bool,
+    socket_options: Vec<SocketOption>,
 }
 
 impl FramedTcpListenConfig {
@@ -47,6 +59,22 @@ impl FramedTcpListenConfig {
         self.keepalive = Some(keepalive);
         self
     }
+
+    /// Set value for the `SO_REUSEPORT` option on this socket (only available in unix).
+    /// This allows to have several listeners in the same port (each one set with this option),
+    /// the incoming connections will be distributed among them by the OS.
+    /// See also [`crate::network::NetworkController::listen_group()`].
+    pub fn with_reuse_port(mut self) -> Self {
+        self.reuse_port = true;
+        self
+    }
+
+    /// Sets an option in the sockets of the accepted connections.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
 }
 
 pub(crate) struct FramedTcpAdapter;
@@ -76,6 +104,10 @@ impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.stream
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.stream))
+    }
 }
 
 impl Remote for RemoteResource {
@@ -89,6 +121,7 @@ impl Remote for RemoteResource {
         };
         let peer_addr = *remote_addr.socket_addr();
         let stream = TcpStream::connect(peer_addr)?;
+        set_socket_options(&borrow_socket(&stream), &config.socket_options)?;
         let local_addr = stream.local_addr()?;
         Ok(ConnectionInfo {
             remote: RemoteResource::new(stream, config.keepalive),
@@ -97,6 +130,20 @@ impl Remote for RemoteResource {
         })
     }
 
+    fn adopt_with(config: TransportConnect, socket: Socket) -> io::Result<ConnectionInfo<Self>> {
+        let config = match config {
+            TransportConnect::FramedTcp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &config.socket_options)?;
+        let peer_addr = inet_addr(socket.peer_addr()?)?;
+        Ok(ConnectionInfo {
+            remote: RemoteResource::new(TcpStream::from_std(socket.into()), config.keepalive),
+            local_addr,
+            peer_addr,
+        })
+    }
+
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
         let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
@@ -182,12 +229,17 @@ impl Remote for RemoteResource {
 pub(crate) struct LocalResource {
     listener: TcpListener,
     keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.listener
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.listener))
+    }
 }
 
 impl Local for LocalResource {
@@ -198,10 +250,33 @@ impl Local for LocalResource {
             TransportListen::FramedTcp(config) => config,
             _ => panic!("Internal error: Got wrong config"),
         };
-        let listener = TcpListener::bind(addr)?;
+        let socket = tcp::listener_socket(addr, config.reuse_port)?;
+        let listener = tcp::bind_listener(socket, addr)?;
         let local_addr = listener.local_addr().unwrap();
         Ok(ListeningInfo {
-            local: { LocalResource { listener, keepalive: config.keepalive } },
+            local: {
+                LocalResource {
+                    listener,
+                    keepalive: config.keepalive,
+                    socket_options: config.socket_options,
+                }
+            },
+            local_addr,
+        })
+    }
+
+    fn adopt_with(config: TransportListen, socket: Socket) -> io::Result<ListeningInfo<Self>> {
+        let config = match config {
+            TransportListen::FramedTcp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &[])?;
+        Ok(ListeningInfo {
+            local: LocalResource {
+                listener: TcpListener::from_std(socket.into()),
+                keepalive: config.keepalive,
+                socket_options: config.socket_options,
+            },
             local_addr,
         })
     }
@@ -209,10 +284,16 @@ impl Local for LocalResource {
     fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
         loop {
             match self.listener.accept() {
-                Ok((stream, addr)) => accept_remote(AcceptedType::Remote(
-                    addr,
-                    RemoteResource::new(stream, self.keepalive.clone()),
-                )),
+                Ok((stream, addr)) => {
+                    let socket = borrow_socket(&stream);
+                    if let Err(err) = set_socket_options(&socket, &self.socket_options) {
+                        log::warn!("TCP set socket options error: {}", err);
+                    }
+                    accept_remote(AcceptedType::Remote(
+                        addr,
+                        RemoteResource::new(stream, self.keepalive.clone()),
+                    ))
+                }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
                 Err(err) => break log::error!("TCP accept error: {}", err), // Should not happen
@@ -4,7 +4,8 @@ use crate::network::adapter::{
     Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
     ListeningInfo, PendingStatus,
 };
-use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen};
+use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen, SocketOption};
+use crate::network::{borrow_socket, set_socket_options, adopt_socket, inet_addr};
 
 use mio::net::{TcpListener, TcpStream};
 use mio::event::{Source};
@@ -17,12 +18,8 @@ use std::ffi::{CString};
 use std::io::{self, ErrorKind, Read, Write};
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 use std::num::NonZeroU32;
-use std::mem::{forget, MaybeUninit};
+use std::mem::{MaybeUninit};
 use std::os::raw::c_int;
-#[cfg(target_os = "windows")]
-use std::os::windows::io::{FromRawSocket, AsRawSocket};
-#[cfg(not(target_os = "windows"))]
-use std::os::{fd::AsRawFd, unix::io::FromRawFd};
 
 /// Size of the internal reading buffer.
 /// It implies that at most the generated [`crate::network::NetEvent::Message`]
@@ -37,6 +34,7 @@ pub struct TcpConnectConfig {
     bind_device: Option<String>,
     source_address: Option<SocketAddr>,
     keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
 }
 
 impl TcpConnectConfig {
@@ -58,12 +56,21 @@ impl TcpConnectConfig {
         self.source_address = Some(source_address);
         self
     }
+
+    /// Sets an option in the socket before connecting.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct TcpListenConfig {
     bind_device: Option<String>,
     keepalive: Option<TcpKeepalive>,
+    reuse_port: bool,
+    socket_options: Vec<SocketOption>,
 }
 
 impl TcpListenConfig {
@@ -79,6 +86,121 @@ impl TcpListenConfig {
         self.keepalive = Some(keepalive);
         self
     }
+
+    /// Set value for the `SO_REUSEPORT` option on this socket (only available in unix).
+    /// This allows to have several listeners in the same port (each one set with this option),
+    /// the incoming connections will be distributed among them by the OS.
+    /// See also [`crate::network::NetworkController::listen_group()`].
+    pub fn with_reuse_port(mut self) -> Self {
+        self.reuse_port = true;
+        self
+    }
+
+    /// Sets an option in the sockets of the accepted connections.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
+}
+
+/// Creates a non-blocking socket to be used as a TCP listener.
+pub(crate) fn listener_socket(addr: SocketAddr, reuse_port: bool) -> io::Result<Socket> {
+    let socket = Socket::new(
+        match addr {
+            SocketAddr::V4 { .. } => Domain::IPV4,
+            SocketAddr::V6 { .. } => Domain::IPV6,
+        },
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.set_nonblocking(true)?;
+    socket.set_reuse_address(true)?;
+
+    #[cfg(unix)]
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    #[cfg(not(unix))]
+    let _ = reuse_port;
+
+    Ok(socket)
+}
+
+/// Binds a socket created by [`listener_socket()`] and starts listening.
+pub(crate) fn bind_listener(socket: Socket, addr: SocketAddr) -> io::Result<TcpListener> {
+    socket.bind(&addr.into())?;
+    socket.listen(LISTENER_BACKLOG)?;
+    Ok(TcpListener::from_std(socket.into()))
+}
+
+/// Binds the socket to a specific interface, identified by its name.
+/// This option works in Unix, on other systems, it will be ignored.
+pub(crate) fn bind_device(socket: &Socket, device: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let device = CString::new(device)?;
+
+        #[cfg(all(not(target_os = "macos"), not(target_os = "ios")))]
+        socket.bind_device(Some(device.as_bytes()))?;
+
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        match NonZeroU32::new(unsafe { libc::if_nametoindex(device.as_ptr()) }) {
+            Some(index) => socket.bind_device_by_index_v4(Some(index))?,
+            None => {
+                return Err(io::Error::new(ErrorKind::NotFound, "Bind device interface not found"))
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (socket, device);
+
+    Ok(())
+}
+
+/// Creates a non-blocking TCP stream connecting to `peer_addr`.
+/// The connection is in progress when the stream is returned.
+pub(crate) fn connect_stream(
+    peer_addr: SocketAddr,
+    source_address: Option<SocketAddr>,
+    device: Option<&str>,
+    socket_options: &[SocketOption],
+) -> io::Result<TcpStream> {
+    let socket = Socket::new(
+        match peer_addr {
+            SocketAddr::V4 { .. } => Domain::IPV4,
+            SocketAddr::V6 { .. } => Domain::IPV6,
+        },
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.set_nonblocking(true)?;
+    set_socket_options(&socket, socket_options)?;
+
+    if let Some(source_address) = source_address {
+        socket.bind(&source_address.into())?;
+    }
+
+    if let Some(device) = device {
+        bind_device(&socket, device)?;
+    }
+
+    match socket.connect(&peer_addr.into()) {
+        #[cfg(unix)]
+        Err(e) if e.raw_os_error() != Some(libc::EINPROGRESS) => return Err(e),
+        #[cfg(windows)]
+        Err(e) if e.kind() != io::ErrorKind::WouldBlock => return Err(e),
+        _ => {}
+    }
+
+    Ok(TcpStream::from_std(socket.into()))
+}
+
+/// Enables the TCP keepalive in the stream, logging if it fails.
+pub(crate) fn set_keepalive(stream: &TcpStream, keepalive: &TcpKeepalive) {
+    if let Err(e) = borrow_socket(stream).set_tcp_keepalive(keepalive) {
+        log::warn!("TCP set keepalive error: {}", e);
+    }
 }
 
 pub(crate) struct TcpAdapter;
@@ -96,6 +218,10 @@ impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.stream
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.stream))
+    }
 }
 
 impl Remote for RemoteResource {
@@ -108,49 +234,12 @@ impl Remote for RemoteResource {
             _ => panic!("Internal error: Got wrong config"),
         };
         let peer_addr = *remote_addr.socket_addr();
-
-        let socket = Socket::new(
-            match peer_addr {
-                SocketAddr::V4 { .. } => Domain::IPV4,
-                SocketAddr::V6 { .. } => Domain::IPV6,
-            },
-            Type::STREAM,
-            Some(Protocol::TCP),
+        let stream = connect_stream(
+            peer_addr,
+            config.source_address,
+            config.bind_device.as_deref(),
+            &config.socket_options,
         )?;
-        socket.set_nonblocking(true)?;
-
-        if let Some(source_address) = config.source_address {
-            socket.bind(&source_address.into())?;
-        }
-
-        #[cfg(unix)]
-        if let Some(bind_device) = config.bind_device {
-            let device = CString::new(bind_device)?;
-
-            #[cfg(all(not(target_os = "macos"), not(target_os = "ios")))]
-            socket.bind_device(Some(device.as_bytes()))?;
-
-            #[cfg(any(target_os = "macos", target_os = "ios"))]
-            match NonZeroU32::new(unsafe { libc::if_nametoindex(device.as_ptr()) }) {
-                Some(index) => socket.bind_device_by_index_v4(Some(index))?,
-                None => {
-                    return Err(io::Error::new(
-                        ErrorKind::NotFound,
-                        "Bind device interface not found",
-                    ))
-                }
-            }
-        }
-
-        match socket.connect(&peer_addr.into()) {
-            #[cfg(unix)]
-            Err(e) if e.raw_os_error() != Some(libc::EINPROGRESS) => return Err(e),
-            #[cfg(windows)]
-            Err(e) if e.kind() != io::ErrorKind::WouldBlock => return Err(e),
-            _ => {}
-        }
-
-        let stream = TcpStream::from_std(socket.into());
         let local_addr = stream.local_addr()?;
         Ok(ConnectionInfo {
             remote: Self { stream, keepalive: config.keepalive },
@@ -159,6 +248,23 @@ impl Remote for RemoteResource {
         })
     }
 
+    fn adopt_with(config: TransportConnect, socket: Socket) -> io::Result<ConnectionInfo<Self>> {
+        let config = match config {
+            TransportConnect::Tcp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &config.socket_options)?;
+        let peer_addr = inet_addr(socket.peer_addr()?)?;
+        Ok(ConnectionInfo {
+            remote: Self {
+                stream: TcpStream::from_std(socket.into()),
+                keepalive: config.keepalive,
+            },
+            local_addr,
+            peer_addr,
+        })
+    }
+
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
         let buffer: MaybeUninit<[u8; INPUT_BUFFER_SIZE]> = MaybeUninit::uninit();
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
@@ -215,17 +321,7 @@ impl Remote for RemoteResource {
 
         if status == PendingStatus::Ready {
             if let Some(keepalive) = &self.keepalive {
-                #[cfg(target_os = "windows")]
-                let socket = unsafe { Socket::from_raw_socket(self.stream.as_raw_socket()) };
-                #[cfg(not(target_os = "windows"))]
-                let socket = unsafe { Socket::from_raw_fd(self.stream.as_raw_fd()) };
-
-                if let Err(e) = socket.set_tcp_keepalive(keepalive) {
-                    log::warn!("TCP set keepalive error: {}", e);
-                }
-
-                // Don't drop so the underlying socket is not closed.
-                forget(socket);
+                set_keepalive(&self.stream, keepalive);
             }
         }
 
@@ -251,12 +347,17 @@ pub fn check_stream_ready(stream: &TcpStream) -> PendingStatus {
 pub(crate) struct LocalResource {
     listener: TcpListener,
     keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.listener
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.listener))
+    }
 }
 
 impl Local for LocalResource {
@@ -268,44 +369,37 @@ impl Local for LocalResource {
             _ => panic!("Internal error: Got wrong config"),
         };
 
-        let socket = Socket::new(
-            match addr {
-                SocketAddr::V4 { .. } => Domain::IPV4,
-                SocketAddr::V6 { .. } => Domain::IPV6,
-            },
-            Type::STREAM,
-            Some(Protocol::TCP),
-        )?;
-        socket.set_nonblocking(true)?;
-        socket.set_reuse_address(true)?;
-
-        #[cfg(unix)]
-        if let Some(bind_device) = config.bind_device {
-            let device = CString::new(bind_device)?;
-
-            #[cfg(all(not(target_os = "macos"), not(target_os = "ios")))]
-            socket.bind_device(Some(device.as_bytes()))?;
-
-            #[cfg(any(target_os = "macos", target_os = "ios"))]
-            match NonZeroU32::new(unsafe { libc::if_nametoindex(device.as_ptr()) }) {
-                Some(index) => socket.bind_device_by_index_v4(Some(index))?,
-                None => {
-                    return Err(io::Error::new(
-                        ErrorKind::NotFound,
-                        "Bind device interface not found",
-                    ))
-                }
-            }
+        let socket = listener_socket(addr, config.reuse_port)?;
+        if let Some(device) = &config.bind_device {
+            bind_device(&socket, device)?;
         }
 
-        socket.bind(&addr.into())?;
-        socket.listen(LISTENER_BACKLOG)?;
-
-        let listener = TcpListener::from_std(socket.into());
-
+        let listener = bind_listener(socket, addr)?;
         let local_addr = listener.local_addr().unwrap();
         Ok(ListeningInfo {
-            local: { LocalResource { listener, keepalive: config.keepalive } },
+            local: {
+                LocalResource {
+                    listener,
+                    keepalive: config.keepalive,
+                    socket_options: config.socket_options,
+                }
+            },
+            local_addr,
+        })
+    }
+
+    fn adopt_with(config: TransportListen, socket: Socket) -> io::Result<ListeningInfo<Self>> {
+        let config = match config {
+            TransportListen::Tcp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &[])?;
+        Ok(ListeningInfo {
+            local: LocalResource {
+                listener: TcpListener::from_std(socket.into()),
+                keepalive: config.keepalive,
+                socket_options: config.socket_options,
+            },
             local_addr,
         })
     }
@@ -313,10 +407,16 @@ impl Local for LocalResource {
     fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
         loop {
             match self.listener.accept() {
-                Ok((stream, addr)) => accept_remote(AcceptedType::Remote(
-                    addr,
-                    RemoteResource { stream, keepalive: self.keepalive.clone() },
-                )),
+                Ok((stream, addr)) => {
+                    let socket = borrow_socket(&stream);
+                    if let Err(err) = set_socket_options(&socket, &self.socket_options) {
+                        log::warn!("TCP set socket options error: {}", err);
+                    }
+                    accept_remote(AcceptedType::Remote(
+                        addr,
+                        RemoteResource { stream, keepalive: self.keepalive.clone() },
+                    ))
+                }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
                 Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
                 Err(err) => break log::error!("TCP accept error: {}", err), // Should not happen
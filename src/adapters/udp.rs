@@ -2,7 +2,8 @@ use crate::network::adapter::{
     Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
     ListeningInfo, PendingStatus,
 };
-use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen};
+use crate::network::{RemoteAddr, Readiness, TransportConnect, TransportListen, SocketOption};
+use crate::network::{borrow_socket, set_socket_options, adopt_socket, inet_addr};
 
 use mio::net::{UdpSocket};
 use mio::event::{Source};
@@ -16,9 +17,7 @@ use nix::sys::socket::{self, sockopt, MsgFlags, SockaddrStorage, ControlMessageO
 #[cfg(target_os = "linux")]
 use nix::ifaddrs::{getifaddrs};
 
-use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
-#[cfg(target_os = "linux")]
-use std::net::{IpAddr, Ipv6Addr};
+use std::net::{SocketAddr, IpAddr, Ipv4Addr, Ipv6Addr};
 use std::io::{self, ErrorKind};
 use std::mem::{MaybeUninit};
 #[cfg(target_os = "linux")]
@@ -37,18 +36,21 @@ pub const MAX_LOCAL_PAYLOAD_LEN: usize = 65535 - 20 - 8;
 #[cfg(target_os = "macos")]
 pub const MAX_LOCAL_PAYLOAD_LEN: usize = 9216 - 20 - 8;
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub struct UdpConnectConfig {
-    source_address: SocketAddr,
+    source_address: Option<SocketAddr>,
     broadcast: bool,
     reuse_address: bool,
     reuse_port: bool,
+    socket_options: Vec<SocketOption>,
+    multicast_interfaces: MulticastInterfaces,
 }
 
 impl UdpConnectConfig {
     /// Specify the source address and port.
+    /// By default, any address of the family of the peer and any port.
     pub fn with_source_address(mut self, addr: SocketAddr) -> Self {
-        self.source_address = addr;
+        self.source_address = Some(addr);
         self
     }
 
@@ -74,16 +76,38 @@ impl UdpConnectConfig {
         self.reuse_port = true;
         self
     }
-}
 
-impl Default for UdpConnectConfig {
-    fn default() -> Self {
-        Self {
-            source_address: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0).into(),
-            broadcast: false,
-            reuse_address: false,
-            reuse_port: false,
-        }
+    /// Sets an option in the socket.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
+
+    /// Address of the interface that sends the IPv4 multicast datagrams (`IP_MULTICAST_IF`).
+    /// By default, the interface chosen by the system according to the routing table.
+    pub fn with_multicast_interface_v4(mut self, interface: Ipv4Addr) -> Self {
+        self.multicast_interfaces.v4 = Some(interface);
+        self
+    }
+
+    /// Index of the interface that sends the IPv6 multicast datagrams (`IPV6_MULTICAST_IF`).
+    /// By default, the interface chosen by the system according to the routing table.
+    pub fn with_multicast_interface_v6(mut self, index: u32) -> Self {
+        self.multicast_interfaces.v6 = Some(index);
+        self
+    }
+
+    /// Time to live of the multicast datagrams sent.
+    /// See [`SocketOption::MulticastTtl`].
+    pub fn with_multicast_ttl(self, ttl: u32) -> Self {
+        self.with_socket_option(SocketOption::MulticastTtl(ttl))
+    }
+
+    /// Whether the multicast datagrams sent are also received by the listeners of the same host.
+    /// See [`SocketOption::MulticastLoop`].
+    pub fn with_multicast_loop(self, enabled: bool) -> Self {
+        self.with_socket_option(SocketOption::MulticastLoop(enabled))
     }
 }
 
@@ -93,6 +117,10 @@ pub struct UdpListenConfig {
     receive_broadcasts: bool,
     reuse_address: bool,
     reuse_port: bool,
+    socket_options: Vec<SocketOption>,
+    multicast_interfaces: MulticastInterfaces,
+    multicast_groups: Vec<IpAddr>,
+    source_multicast_groups: Vec<(Ipv4Addr, Ipv4Addr)>,
 }
 
 impl UdpListenConfig {
@@ -128,6 +156,126 @@ impl UdpListenConfig {
         self.reuse_port = true;
         self
     }
+
+    /// Sets an option in the socket.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
+
+    /// Joins a multicast group in addition to the address of the listener,
+    /// so a socket can receive the datagrams of several groups.
+    /// The groups must be of the family of the listening address, that should be unspecified
+    /// (e.g. `0.0.0.0:3010`) to receive the datagrams sent to all of them.
+    pub fn with_multicast_group(mut self, group: IpAddr) -> Self {
+        self.multicast_groups.push(group);
+        self
+    }
+
+    /// Joins an IPv4 multicast group receiving only the datagrams sent by `source`
+    /// (source-specific multicast, `IP_ADD_SOURCE_MEMBERSHIP`),
+    /// usually in the range `232.0.0.0/8`.
+    /// See also [`UdpListenConfig::with_multicast_group()`].
+    pub fn with_source_multicast_group(mut self, group: Ipv4Addr, source: Ipv4Addr) -> Self {
+        self.source_multicast_groups.push((group, source));
+        self
+    }
+
+    /// Address of the interface where the IPv4 multicast groups are joined, which also sends the
+    /// multicast datagrams of the listener (`IP_MULTICAST_IF`).
+    /// By default, the interface chosen by the system.
+    /// It allows to pin the multicast traffic to a network card in a host with several of them.
+    pub fn with_multicast_interface_v4(mut self, interface: Ipv4Addr) -> Self {
+        self.multicast_interfaces.v4 = Some(interface);
+        self
+    }
+
+    /// Index of the interface where the IPv6 multicast groups are joined, which also sends the
+    /// multicast datagrams of the listener (`IPV6_MULTICAST_IF`).
+    /// By default, the interface chosen by the system.
+    /// The link-local groups (`ff02::/16`) usually require a specific interface.
+    pub fn with_multicast_interface_v6(mut self, index: u32) -> Self {
+        self.multicast_interfaces.v6 = Some(index);
+        self
+    }
+
+    /// Time to live of the multicast datagrams sent by the listener.
+    /// See [`SocketOption::MulticastTtl`].
+    pub fn with_multicast_ttl(self, ttl: u32) -> Self {
+        self.with_socket_option(SocketOption::MulticastTtl(ttl))
+    }
+
+    /// Whether the multicast datagrams sent by the listener are also received by the listeners
+    /// of the same host.
+    /// See [`SocketOption::MulticastLoop`].
+    pub fn with_multicast_loop(self, enabled: bool) -> Self {
+        self.with_socket_option(SocketOption::MulticastLoop(enabled))
+    }
+}
+
+/// Interfaces of the multicast traffic of a socket, by default chosen by the system.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+struct MulticastInterfaces {
+    v4: Option<Ipv4Addr>,
+    v6: Option<u32>,
+}
+
+impl MulticastInterfaces {
+    /// Sets the interface that sends the multicast datagrams of the socket, given its family.
+    fn apply(&self, socket: &Socket, ipv6: bool) -> io::Result<()> {
+        match (ipv6, self.v4, self.v6) {
+            (false, Some(interface), _) => socket.set_multicast_if_v4(&interface),
+            (true, _, Some(index)) => socket.set_multicast_if_v6(index),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Multicast group joined by a listener.
+#[derive(Clone, Copy)]
+enum MulticastGroup {
+    /// Group along with the address of the interface where it is joined.
+    V4(Ipv4Addr, Ipv4Addr),
+
+    /// Group along with the index of the interface where it is joined.
+    V6(Ipv6Addr, u32),
+
+    /// Group, the only source whose datagrams are received, and the interface.
+    SourceV4(Ipv4Addr, Ipv4Addr, Ipv4Addr),
+}
+
+impl MulticastGroup {
+    fn new(group: IpAddr, interfaces: &MulticastInterfaces) -> Self {
+        match group {
+            IpAddr::V4(group) => {
+                MulticastGroup::V4(group, interfaces.v4.unwrap_or(Ipv4Addr::UNSPECIFIED))
+            }
+            IpAddr::V6(group) => MulticastGroup::V6(group, interfaces.v6.unwrap_or(0)),
+        }
+    }
+
+    fn is_ipv6(&self) -> bool {
+        matches!(self, MulticastGroup::V6(..))
+    }
+
+    fn join(&self, socket: &Socket) -> io::Result<()> {
+        match self {
+            MulticastGroup::V4(group, interface) => socket.join_multicast_v4(group, interface),
+            MulticastGroup::V6(group, interface) => socket.join_multicast_v6(group, *interface),
+            MulticastGroup::SourceV4(group, source, interface) => {
+                socket.join_ssm_v4(source, group, interface)
+            }
+        }
+    }
+}
+
+/// Returns the unspecified address of the family of the given address, with its port.
+fn unspecified_addr(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4 { .. } => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port()),
+        SocketAddr::V6 { .. } => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), addr.port()),
+    }
 }
 
 pub(crate) struct UdpAdapter;
@@ -144,6 +292,10 @@ impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.socket
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.socket))
+    }
 }
 
 impl Remote for RemoteResource {
@@ -172,8 +324,15 @@ impl Remote for RemoteResource {
         #[cfg(unix)]
         socket.set_reuse_port(config.reuse_port)?;
         socket.set_broadcast(config.broadcast)?;
+        config.multicast_interfaces.apply(&socket, peer_addr.is_ipv6())?;
+        set_socket_options(&socket, &config.socket_options)?;
 
-        socket.bind(&config.source_address.into())?;
+        let source_address = match (config.source_address, peer_addr) {
+            (Some(source_address), _) => source_address,
+            (None, SocketAddr::V4 { .. }) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            (None, SocketAddr::V6 { .. }) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        socket.bind(&source_address.into())?;
         socket.connect(&peer_addr.into())?;
 
         let socket = UdpSocket::from_std(socket.into());
@@ -181,6 +340,17 @@ impl Remote for RemoteResource {
         Ok(ConnectionInfo { remote: RemoteResource { socket }, local_addr, peer_addr })
     }
 
+    fn adopt_with(config: TransportConnect, socket: Socket) -> io::Result<ConnectionInfo<Self>> {
+        let config = match config {
+            TransportConnect::Udp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &config.socket_options)?;
+        let peer_addr = inet_addr(socket.peer_addr()?)?;
+        let socket = UdpSocket::from_std(socket.into());
+        Ok(ConnectionInfo { remote: RemoteResource { socket }, local_addr, peer_addr })
+    }
+
     fn receive(&self, mut process_data: impl FnMut(&[u8])) -> ReadStatus {
         let buffer: MaybeUninit<[u8; MAX_LOCAL_PAYLOAD_LEN]> = MaybeUninit::uninit();
         let mut input_buffer = unsafe { buffer.assume_init() }; // Avoid to initialize the array
@@ -213,6 +383,9 @@ impl Remote for RemoteResource {
 }
 
 pub(crate) struct LocalResource {
+    // The multicast groups are not left explicitly: the membership belongs to the socket,
+    // that could have been handed over to other process, and it is dropped by the OS once the
+    // socket is closed by all of them.
     socket: UdpSocket,
     #[cfg(target_os = "linux")]
     ingress_addresses: Option<Vec<IpAddr>>,
@@ -222,6 +395,10 @@ impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
         &mut self.socket
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&borrow_socket(&self.socket))
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -303,10 +480,20 @@ impl Local for LocalResource {
             _ => panic!("Internal error: Got wrong config"),
         };
 
-        let multicast = match addr {
-            SocketAddr::V4(addr) if addr.ip().is_multicast() => Some(addr),
-            _ => None,
-        };
+        let interfaces = &config.multicast_interfaces;
+        let listen_group = addr.ip().is_multicast().then(|| addr.ip());
+        let groups = listen_group.iter().chain(&config.multicast_groups);
+        let multicast = groups
+            .map(|group| MulticastGroup::new(*group, interfaces))
+            .chain(config.source_multicast_groups.iter().map(|(group, source)| {
+                let interface = interfaces.v4.unwrap_or(Ipv4Addr::UNSPECIFIED);
+                MulticastGroup::SourceV4(*group, *source, interface)
+            }))
+            .collect::<Vec<_>>();
+        if multicast.iter().any(|group| group.is_ipv6() != addr.is_ipv6()) {
+            let message = "The multicast groups must be of the family of the listening address";
+            return Err(io::Error::new(ErrorKind::InvalidInput, message))
+        }
 
         let socket = Socket::new(
             match addr {
@@ -318,14 +505,16 @@ impl Local for LocalResource {
         )?;
         socket.set_nonblocking(true)?;
 
-        if config.reuse_address || multicast.is_some() {
+        if config.reuse_address || !multicast.is_empty() {
             socket.set_reuse_address(true)?;
         }
         #[cfg(unix)]
-        if config.reuse_port || multicast.is_some() {
+        if config.reuse_port || !multicast.is_empty() {
             socket.set_reuse_port(true)?;
         }
         socket.set_broadcast(config.send_broadcasts)?;
+        interfaces.apply(&socket, addr.is_ipv6())?;
+        set_socket_options(&socket, &config.socket_options)?;
 
         #[cfg(target_os = "linux")]
         let ingress_addresses = if config.receive_broadcasts {
@@ -378,10 +567,7 @@ impl Local for LocalResource {
                     )?;
 
                     // Listen on UNSPECIFIED
-                    addr.set_ip(match addr {
-                        SocketAddr::V4 { .. } => Ipv4Addr::UNSPECIFIED.into(),
-                        SocketAddr::V6 { .. } => Ipv6Addr::UNSPECIFIED.into(),
-                    });
+                    addr = unspecified_addr(addr);
 
                     Some(ingress_addresses)
                 }
@@ -391,9 +577,13 @@ impl Local for LocalResource {
             None
         };
 
-        if let Some(multicast) = multicast {
-            socket.join_multicast_v4(multicast.ip(), &Ipv4Addr::UNSPECIFIED)?;
-            socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, addr.port()).into())?;
+        multicast.iter().try_for_each(|group| group.join(&socket))?;
+        if let Some(group) = listen_group {
+            if group.is_ipv6() {
+                // Otherwise, it would also receive the IPv4 datagrams of the port.
+                socket.set_only_v6(true)?;
+            }
+            socket.bind(&unspecified_addr(addr).into())?;
         }
         else {
             socket.bind(&addr.into())?;
@@ -413,6 +603,22 @@ impl Local for LocalResource {
         })
     }
 
+    fn adopt_with(config: TransportListen, socket: Socket) -> io::Result<ListeningInfo<Self>> {
+        let config = match config {
+            TransportListen::Udp(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let local_addr = adopt_socket(&socket, &config.socket_options)?;
+        Ok(ListeningInfo {
+            local: LocalResource {
+                socket: UdpSocket::from_std(socket.into()),
+                #[cfg(target_os = "linux")]
+                ingress_addresses: None,
+            },
+            local_addr,
+        })
+    }
+
     fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
         #[cfg(target_os = "linux")]
         if let Some(ingress_addresses) = &self.ingress_addresses {
@@ -440,16 +646,6 @@ impl Local for LocalResource {
     }
 }
 
-impl Drop for LocalResource {
-    fn drop(&mut self) {
-        if let SocketAddr::V4(addr) = self.socket.local_addr().unwrap() {
-            if addr.ip().is_multicast() {
-                self.socket.leave_multicast_v4(addr.ip(), &Ipv4Addr::UNSPECIFIED).unwrap();
-            }
-        }
-    }
-}
-
 fn send_packet(data: &[u8], send_method: impl Fn(&[u8]) -> io::Result<usize>) -> SendStatus {
     loop {
         match send_method(data) {
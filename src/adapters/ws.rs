@@ -1,35 +1,572 @@
 use crate::network::adapter::{
     Resource, Remote, Local, Adapter, SendStatus, AcceptedType, ReadStatus, ConnectionInfo,
-    ListeningInfo, PendingStatus,
+    ListeningInfo, PendingStatus, MessageKind,
 };
 use crate::network::{RemoteAddr, Readiness};
 use crate::util::thread::{OTHER_THREAD_ERR};
-use crate::network::{TransportConnect, TransportListen};
+use crate::network::{TransportConnect, TransportListen, SocketOption};
+use crate::network::{set_socket_options};
+use crate::adapters::tcp::{self, TcpKeepalive};
+
+mod deflate;
+pub use deflate::{WsDeflateConfig};
+use deflate::{Deflater};
+
+mod http;
+pub use http::{HttpRequest, HttpResponse};
+use http::{HttpHandler, HttpStatus};
+
+mod stream;
+use stream::{WsStream, WsListener};
+
+mod reader;
+use reader::{FrameReader};
 
 use mio::event::{Source};
-use mio::net::{TcpStream, TcpListener};
 
-use tungstenite::protocol::{WebSocket, Message};
-use tungstenite::{accept as ws_accept};
-use tungstenite::client::{client as ws_connect};
+use tungstenite::protocol::{WebSocket, WebSocketConfig, Message};
+use tungstenite::protocol::frame::{Frame, FrameHeader};
+use tungstenite::protocol::frame::coding::{OpCode, Data};
+use tungstenite::{accept_hdr_with_config as ws_accept};
+use tungstenite::client::{client_with_config as ws_connect, IntoClientRequest};
 use tungstenite::handshake::{
     HandshakeError, MidHandshake,
-    server::{ServerHandshake, NoCallback},
-    client::{ClientHandshake},
+    server::{ServerHandshake, Callback, Request as ServerRequest, Response, ErrorResponse},
+    client::{ClientHandshake, Request},
+};
+use tungstenite::http::{StatusCode, HeaderMap};
+use tungstenite::http::header::{
+    HeaderName, HeaderValue, SEC_WEBSOCKET_PROTOCOL, SEC_WEBSOCKET_EXTENSIONS,
 };
 use tungstenite::error::{Error};
 
+use socket2::{Socket};
+
 use url::Url;
 
-use std::sync::{Mutex, Arc};
+use std::sync::{
+    Mutex, Arc,
+    atomic::{AtomicBool, Ordering},
+};
 use std::net::{SocketAddr};
-use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+use std::io::{self, ErrorKind, Cursor};
 use std::ops::{DerefMut};
+use std::any::{Any};
 
-/// Max message size for default config
+/// Max message size for default config.
+/// It can be changed by the `with_max_message_size()` of [`WsConnectConfig`] and
+/// [`WsListenConfig`].
 // From https://docs.rs/tungstenite/0.13.0/src/tungstenite/protocol/mod.rs.html#65
 pub const MAX_PAYLOAD_LEN: usize = 32 << 20;
 
+/// `tungstenite` configuration limited to [`MAX_PAYLOAD_LEN`].
+fn default_websocket_config() -> WebSocketConfig {
+    WebSocketConfig::default().max_message_size(Some(MAX_PAYLOAD_LEN))
+}
+
+#[derive(Clone, Debug)]
+pub struct WsConnectConfig {
+    bind_device: Option<String>,
+    source_address: Option<SocketAddr>,
+    keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
+    path: Option<String>,
+    headers: Vec<(String, String)>,
+    subprotocols: Vec<String>,
+    websocket_config: WebSocketConfig,
+    ping: Option<PingConfig>,
+    deflate: Option<WsDeflateConfig>,
+    fragments: bool,
+}
+
+impl Default for WsConnectConfig {
+    fn default() -> Self {
+        Self {
+            bind_device: None,
+            source_address: None,
+            keepalive: None,
+            socket_options: Vec::new(),
+            path: None,
+            headers: Vec::new(),
+            subprotocols: Vec::new(),
+            websocket_config: default_websocket_config(),
+            ping: None,
+            deflate: None,
+            fragments: false,
+        }
+    }
+}
+
+impl WsConnectConfig {
+    /// Bind the TCP connection to a specific interface, identified by its name. This option works
+    /// in Unix, on other systems, it will be ignored.
+    pub fn with_bind_device(mut self, device: String) -> Self {
+        self.bind_device = Some(device);
+        self
+    }
+
+    /// Enables TCP keepalive settings on the socket.
+    pub fn with_keepalive(mut self, keepalive: TcpKeepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Specify the source address and port.
+    pub fn with_source_address(mut self, source_address: SocketAddr) -> Self {
+        self.source_address = Some(source_address);
+        self
+    }
+
+    /// Sets an option in the socket once it is created.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
+
+    /// Path of the request when connecting to a socket address instead of an URL,
+    /// or over an adopted stream (by default `/message-io-default`).
+    /// The path may contain a query string, e.g. `/chat?room=1`.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Adds a header to the HTTP upgrade request, e.g. an authorization token or a cookie.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds a subprotocol to the `Sec-WebSocket-Protocol` header of the request,
+    /// in order of preference.
+    /// If any subprotocol is requested, the server must choose one of them
+    /// or the connection will fail.
+    pub fn with_subprotocol(mut self, subprotocol: impl Into<String>) -> Self {
+        self.subprotocols.push(subprotocol.into());
+        self
+    }
+
+    /// Max size of the received messages ([`MAX_PAYLOAD_LEN`] by default).
+    pub fn with_max_message_size(mut self, size: usize) -> Self {
+        self.websocket_config.max_message_size = Some(size);
+        self
+    }
+
+    /// Max size of the received frames (16 MiB by default).
+    pub fn with_max_frame_size(mut self, size: usize) -> Self {
+        self.websocket_config.max_frame_size = Some(size);
+        self
+    }
+
+    /// Sends a ping each `interval` once the connection is established,
+    /// measuring the round trip time of its pong
+    /// (see [`crate::network::NetworkController::round_trip_time()`]).
+    /// If the peer does not answer `max_missed_pongs` consecutive pings (at least 1),
+    /// each one before the next ping is sent, the connection is removed and a
+    /// [`crate::network::NetEvent::Disconnected`] is generated with
+    /// [`crate::network::DisconnectReason::TimedOut`] as reason.
+    /// It allows to detect the connections silently dropped by the network, e.g. by a proxy,
+    /// and to keep them active meanwhile.
+    pub fn with_ping(mut self, interval: Duration, max_missed_pongs: u32) -> Self {
+        self.ping = Some(PingConfig::new(interval, max_missed_pongs));
+        self
+    }
+
+    /// Requests the compression of the messages with the `permessage-deflate` extension.
+    /// The messages are compressed if the server supports it,
+    /// otherwise the connection is established without compression.
+    /// See [`WsDeflateConfig`].
+    pub fn with_deflate(mut self, config: WsDeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+
+    /// Receives the fragments of the messages as they arrive instead of the entire messages:
+    /// each fragment generates a [`crate::network::NetEvent::Message`], and
+    /// [`crate::network::NetworkController::is_message_complete()`] tells if it is the last one
+    /// of its message.
+    /// The fragments of a text message can split its characters.
+    /// The max message size is applied to each fragment.
+    /// See also [`crate::network::NetworkController::send_fragment()`].
+    pub fn with_message_fragments(mut self) -> Self {
+        self.fragments = true;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WsListenConfig {
+    bind_device: Option<String>,
+    keepalive: Option<TcpKeepalive>,
+    reuse_port: bool,
+    socket_options: Vec<SocketOption>,
+    headers: Vec<(String, String)>,
+    subprotocols: Vec<String>,
+    websocket_config: WebSocketConfig,
+    accept_callback: Option<AcceptFilter>,
+    http_handler: Option<HttpHandler>,
+    ping: Option<PingConfig>,
+    deflate: Option<WsDeflateConfig>,
+    fragments: bool,
+}
+
+impl Default for WsListenConfig {
+    fn default() -> Self {
+        Self {
+            bind_device: None,
+            keepalive: None,
+            reuse_port: false,
+            socket_options: Vec::new(),
+            headers: Vec::new(),
+            subprotocols: Vec::new(),
+            websocket_config: default_websocket_config(),
+            accept_callback: None,
+            http_handler: None,
+            ping: None,
+            deflate: None,
+            fragments: false,
+        }
+    }
+}
+
+impl WsListenConfig {
+    /// Bind the TCP listener to a specific interface, identified by its name. This option works in
+    /// Unix, on other systems, it will be ignored.
+    pub fn with_bind_device(mut self, device: String) -> Self {
+        self.bind_device = Some(device);
+        self
+    }
+
+    /// Enables TCP keepalive settings on client connection sockets.
+    pub fn with_keepalive(mut self, keepalive: TcpKeepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Set value for the `SO_REUSEPORT` option on this socket (only available in unix).
+    /// This allows to have several listeners in the same port (each one set with this option),
+    /// the incoming connections will be distributed among them by the OS.
+    /// See also [`crate::network::NetworkController::listen_group()`].
+    pub fn with_reuse_port(mut self) -> Self {
+        self.reuse_port = true;
+        self
+    }
+
+    /// Sets an option in the sockets of the accepted connections.
+    /// See [`SocketOption`].
+    pub fn with_socket_option(mut self, option: SocketOption) -> Self {
+        self.socket_options.push(option);
+        self
+    }
+
+    /// Adds a header to the HTTP upgrade responses.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds a supported subprotocol, in order of preference.
+    /// The first supported subprotocol requested by the client is answered in the
+    /// `Sec-WebSocket-Protocol` header of the response.
+    /// If none of them is requested, the response does not contain the header.
+    pub fn with_subprotocol(mut self, subprotocol: impl Into<String>) -> Self {
+        self.subprotocols.push(subprotocol.into());
+        self
+    }
+
+    /// Max size of the received messages ([`MAX_PAYLOAD_LEN`] by default).
+    pub fn with_max_message_size(mut self, size: usize) -> Self {
+        self.websocket_config.max_message_size = Some(size);
+        self
+    }
+
+    /// Max size of the received frames (16 MiB by default).
+    pub fn with_max_frame_size(mut self, size: usize) -> Self {
+        self.websocket_config.max_frame_size = Some(size);
+        self
+    }
+
+    /// Sends pings to the accepted connections.
+    /// See [`WsConnectConfig::with_ping()`].
+    pub fn with_ping(mut self, interval: Duration, max_missed_pongs: u32) -> Self {
+        self.ping = Some(PingConfig::new(interval, max_missed_pongs));
+        self
+    }
+
+    /// Supports the compression of the messages with the `permessage-deflate` extension
+    /// for the clients that request it.
+    /// See [`WsDeflateConfig`].
+    pub fn with_deflate(mut self, config: WsDeflateConfig) -> Self {
+        self.deflate = Some(config);
+        self
+    }
+
+    /// Receives the fragments of the messages of the accepted connections as they arrive.
+    /// See [`WsConnectConfig::with_message_fragments()`].
+    pub fn with_message_fragments(mut self) -> Self {
+        self.fragments = true;
+        self
+    }
+
+    /// Sets a callback to inspect the HTTP upgrade request of each incoming connection
+    /// before answering it, e.g. to authenticate the client by its query string or headers.
+    /// Returning an HTTP status code (e.g. `401`) rejects the connection with it,
+    /// and no [`crate::network::NetEvent::Accepted`] is generated.
+    /// The callback is called from the thread that processes the network.
+    ///
+    /// The request of the accepted connections is also available as metadata of the endpoint
+    /// with [`crate::network::NetworkController::metadata()`].
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, TransportListen};
+    /// use message_io::adapters::ws::{WsListenConfig};
+    ///
+    /// let config = WsListenConfig::default().with_accept_callback(|request| {
+    ///     match request.query.as_deref() == Some("token=1234") {
+    ///         true => Ok(()),
+    ///         false => Err(401),
+    ///     }
+    /// });
+    /// let (controller, _) = network::split();
+    /// controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+    /// ```
+    pub fn with_accept_callback(
+        mut self,
+        callback: impl Fn(&WsRequest) -> Result<(), u16> + Send + Sync + 'static,
+    ) -> Self {
+        self.accept_callback = Some(AcceptFilter(Arc::new(callback)));
+        self
+    }
+
+    /// Sets a handler for the HTTP requests that do not upgrade the connection to WebSocket,
+    /// so the same port can serve e.g. health checks or a static page.
+    /// Without handler, these requests close the connection.
+    ///
+    /// The connection is closed once the response is sent,
+    /// and no [`crate::network::NetEvent`] is generated for it.
+    /// The requests can have a body of a known `Content-Length`, up to 64 KiB including the
+    /// headers.
+    /// The handler is called from the thread that processes the network.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, TransportListen};
+    /// use message_io::adapters::ws::{WsListenConfig, HttpResponse};
+    ///
+    /// let config = WsListenConfig::default().with_http_handler(|request| {
+    ///     match request.path.as_str() {
+    ///         "/healthz" => HttpResponse::new(200).with_body("ok"),
+    ///         _ => HttpResponse::new(404),
+    ///     }
+    /// });
+    /// let (controller, _) = network::split();
+    /// controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+    /// ```
+    pub fn with_http_handler(
+        mut self,
+        handler: impl Fn(&HttpRequest) -> HttpResponse + Send + Sync + 'static,
+    ) -> Self {
+        self.http_handler = Some(HttpHandler(Arc::new(handler)));
+        self
+    }
+}
+
+fn invalid_input(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, err)
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+fn parse_headers(headers: &[(String, String)]) -> io::Result<Vec<(HeaderName, HeaderValue)>> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(invalid_input)?;
+            let value = HeaderValue::from_str(value).map_err(invalid_input)?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Builds the HTTP upgrade request of a connection.
+fn client_request(url: Url, config: &WsConnectConfig) -> io::Result<Request> {
+    let mut request = url.as_str().into_client_request().map_err(invalid_input)?;
+    let headers = request.headers_mut();
+    for (name, value) in parse_headers(&config.headers)? {
+        headers.append(name, value);
+    }
+    if !config.subprotocols.is_empty() {
+        let subprotocols =
+            HeaderValue::from_str(&config.subprotocols.join(", ")).map_err(invalid_input)?;
+        headers.insert(SEC_WEBSOCKET_PROTOCOL, subprotocols);
+    }
+    if let Some(deflate) = &config.deflate {
+        headers.insert(SEC_WEBSOCKET_EXTENSIONS, deflate::offer(deflate));
+    }
+    Ok(request)
+}
+
+/// See [`WsConnectConfig::with_ping()`].
+#[derive(Clone, Copy, Debug)]
+struct PingConfig {
+    interval: Duration,
+    max_missed_pongs: u32,
+}
+
+impl PingConfig {
+    fn new(interval: Duration, max_missed_pongs: u32) -> Self {
+        PingConfig { interval, max_missed_pongs: max_missed_pongs.max(1) }
+    }
+}
+
+#[derive(Default)]
+struct PingState {
+    /// Payload of the last ping sent.
+    sequence: u64,
+
+    /// Time when the last ping was sent, while its pong has not been received.
+    sent: Option<Instant>,
+
+    missed_pongs: u32,
+    round_trip_time: Option<Duration>,
+}
+
+/// Data of the HTTP upgrade request of an accepted WebSocket connection.
+/// See [`WsListenConfig::with_accept_callback()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WsRequest {
+    /// Path of the request, e.g. `/chat`.
+    pub path: String,
+
+    /// Query string of the request without the `?`, e.g. `token=1234`.
+    pub query: Option<String>,
+
+    /// Headers of the request in the order they were received.
+    /// The names are in lowercase.
+    pub headers: Vec<(String, String)>,
+
+    /// Subprotocol chosen for the connection.
+    pub subprotocol: Option<String>,
+}
+
+impl WsRequest {
+    fn new(request: &ServerRequest, subprotocol: Option<&String>) -> Self {
+        WsRequest {
+            path: request.uri().path().into(),
+            query: request.uri().query().map(Into::into),
+            headers: request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (name.as_str().into(), String::from_utf8_lossy(value.as_bytes()).into())
+                })
+                .collect(),
+            subprotocol: subprotocol.cloned(),
+        }
+    }
+
+    /// Returns the value of the first header with the given name (case insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+type AcceptFn = dyn Fn(&WsRequest) -> Result<(), u16> + Send + Sync;
+
+/// Callback that filters the HTTP upgrade requests of a listener.
+/// See [`WsListenConfig::with_accept_callback()`].
+#[derive(Clone)]
+struct AcceptFilter(Arc<AcceptFn>);
+
+impl std::fmt::Debug for AcceptFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AcceptFilter")
+    }
+}
+
+/// Answers the HTTP upgrade requests of a listener.
+struct AcceptConfig {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    subprotocols: Vec<String>,
+    websocket_config: WebSocketConfig,
+    filter: Option<AcceptFilter>,
+    http_handler: Option<HttpHandler>,
+    deflate: Option<WsDeflateConfig>,
+    fragments: bool,
+}
+
+impl AcceptConfig {
+    fn new(config: &WsListenConfig) -> io::Result<Self> {
+        Ok(AcceptConfig {
+            headers: parse_headers(&config.headers)?,
+            subprotocols: config.subprotocols.clone(),
+            websocket_config: config.websocket_config,
+            filter: config.accept_callback.clone(),
+            http_handler: config.http_handler.clone(),
+            deflate: config.deflate,
+            fragments: config.fragments,
+        })
+    }
+
+    /// Chooses the first supported subprotocol requested by the client.
+    fn subprotocol(&self, request: &ServerRequest) -> Option<&String> {
+        let requested = request.headers().get(SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+        let requested = requested.split(',').map(str::trim).collect::<Vec<_>>();
+        self.subprotocols.iter().find(|subprotocol| requested.contains(&subprotocol.as_str()))
+    }
+}
+
+/// Place where the accepted request is stored once the handshake callback is called.
+type RequestSlot = Arc<Mutex<Option<Arc<WsRequest>>>>;
+
+/// Place where the compressor of the connection is stored once the compression is negotiated.
+type DeflaterSlot = Arc<Mutex<Option<Deflater>>>;
+
+struct AcceptCallback(Arc<AcceptConfig>, RequestSlot, DeflaterSlot);
+
+impl Callback for AcceptCallback {
+    fn on_request(
+        self,
+        request: &ServerRequest,
+        mut response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        let subprotocol = self.0.subprotocol(request);
+        let ws_request = WsRequest::new(request, subprotocol);
+        if let Some(AcceptFilter(filter)) = &self.0.filter {
+            if let Err(status) = filter(&ws_request) {
+                let mut rejection = ErrorResponse::new(None);
+                *rejection.status_mut() =
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(rejection)
+            }
+        }
+
+        let headers = response.headers_mut();
+        for (name, value) in &self.0.headers {
+            headers.append(name, value.clone());
+        }
+        if let Some(subprotocol) = subprotocol.and_then(|s| HeaderValue::from_str(s).ok()) {
+            headers.insert(SEC_WEBSOCKET_PROTOCOL, subprotocol);
+        }
+        if let Some(deflate) = &self.0.deflate {
+            if let Some((answer, deflater)) = deflate::accept(deflate, request.headers()) {
+                headers.insert(SEC_WEBSOCKET_EXTENSIONS, answer);
+                *self.2.lock().expect(OTHER_THREAD_ERR) = Some(deflater);
+            }
+        }
+        *self.1.lock().expect(OTHER_THREAD_ERR) = Some(Arc::new(ws_request));
+        Ok(response)
+    }
+}
+
 pub(crate) struct WsAdapter;
 impl Adapter for WsAdapter {
     type Remote = RemoteResource;
@@ -37,53 +574,125 @@ impl Adapter for WsAdapter {
 }
 
 enum PendingHandshake {
-    Connect(Url, ArcTcpStream),
-    Accept(ArcTcpStream),
-    Client(MidHandshake<ClientHandshake<ArcTcpStream>>),
-    Server(MidHandshake<ServerHandshake<ArcTcpStream, NoCallback>>),
+    Connect(Box<Request>, WebSocketConfig, ArcStream),
+    Accept(Arc<AcceptConfig>, ArcStream),
+    Respond(Cursor<Vec<u8>>, ArcStream),
+    Client(MidHandshake<ClientHandshake<ArcStream>>),
+    Server(MidHandshake<ServerHandshake<ArcStream, AcceptCallback>>),
 }
 
 #[allow(clippy::large_enum_variant)]
 enum RemoteState {
-    WebSocket(WebSocket<ArcTcpStream>),
+    WebSocket(WebSocket<ArcStream>),
     Handshake(Option<PendingHandshake>),
-    Error(ArcTcpStream),
+    Error(ArcStream),
+}
+
+impl RemoteState {
+    fn stream(&self) -> &dyn WsStream {
+        match self {
+            RemoteState::WebSocket(web_socket) => &*web_socket.get_ref().0,
+            RemoteState::Handshake(Some(handshake)) => match handshake {
+                PendingHandshake::Connect(_, _, stream) => &*stream.0,
+                PendingHandshake::Accept(_, stream) => &*stream.0,
+                PendingHandshake::Respond(_, stream) => &*stream.0,
+                PendingHandshake::Client(handshake) => &*handshake.get_ref().get_ref().0,
+                PendingHandshake::Server(handshake) => &*handshake.get_ref().get_ref().0,
+            },
+            RemoteState::Handshake(None) => unreachable!(),
+            RemoteState::Error(stream) => &*stream.0,
+        }
+    }
 }
 
 pub(crate) struct RemoteResource {
     state: Mutex<RemoteState>,
+    request: RequestSlot,
+    receiving_text: AtomicBool,
+    receiving_complete: AtomicBool,
+    sending_fragments: AtomicBool,
+    ping: Option<(PingConfig, Mutex<PingState>)>,
+
+    /// Compression requested by the client.
+    deflate_offer: Option<WsDeflateConfig>,
+
+    /// It is always locked after the state.
+    deflater: DeflaterSlot,
+}
+
+impl RemoteResource {
+    /// Creates the resource of a client that will perform the handshake over the `stream`.
+    fn client(request: Request, stream: Box<dyn WsStream>, config: WsConnectConfig) -> Self {
+        if let Some(keepalive) = &config.keepalive {
+            stream.set_keepalive(keepalive);
+        }
+        let stream = ArcStream::new(
+            stream,
+            config.deflate.is_some(),
+            config.fragments,
+            config.websocket_config.max_message_size,
+        );
+        RemoteResource::new(
+            PendingHandshake::Connect(Box::new(request), config.websocket_config, stream),
+            config.ping,
+            config.deflate,
+        )
+    }
+
+    fn new(
+        pending: PendingHandshake,
+        ping: Option<PingConfig>,
+        deflate_offer: Option<WsDeflateConfig>,
+    ) -> Self {
+        RemoteResource {
+            state: Mutex::new(RemoteState::Handshake(Some(pending))),
+            request: RequestSlot::default(),
+            receiving_text: AtomicBool::new(false),
+            receiving_complete: AtomicBool::new(true),
+            sending_fragments: AtomicBool::new(false),
+            ping: ping.map(|config| (config, Mutex::default())),
+            deflate_offer,
+            deflater: DeflaterSlot::default(),
+        }
+    }
 }
 
 impl Resource for RemoteResource {
     fn source(&mut self) -> &mut dyn Source {
         match self.state.get_mut().unwrap() {
-            RemoteState::WebSocket(web_socket) => {
-                Arc::get_mut(&mut web_socket.get_mut().0).unwrap()
-            }
+            RemoteState::WebSocket(web_socket) => web_socket.get_mut().source(),
             RemoteState::Handshake(Some(handshake)) => match handshake {
-                PendingHandshake::Connect(_, stream) => Arc::get_mut(&mut stream.0).unwrap(),
-                PendingHandshake::Accept(stream) => Arc::get_mut(&mut stream.0).unwrap(),
-                PendingHandshake::Client(handshake) => {
-                    Arc::get_mut(&mut handshake.get_mut().get_mut().0).unwrap()
-                }
-                PendingHandshake::Server(handshake) => {
-                    Arc::get_mut(&mut handshake.get_mut().get_mut().0).unwrap()
-                }
+                PendingHandshake::Connect(_, _, stream) => stream.source(),
+                PendingHandshake::Accept(_, stream) => stream.source(),
+                PendingHandshake::Respond(_, stream) => stream.source(),
+                PendingHandshake::Client(handshake) => handshake.get_mut().get_mut().source(),
+                PendingHandshake::Server(handshake) => handshake.get_mut().get_mut().source(),
             },
             RemoteState::Handshake(None) => unreachable!(),
-            RemoteState::Error(stream) => Arc::get_mut(&mut stream.0).unwrap(),
+            RemoteState::Error(stream) => stream.source(),
         }
     }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        let state = self.state.lock().expect(OTHER_THREAD_ERR);
+        action(&state.stream().socket())
+    }
 }
 
 impl Remote for RemoteResource {
     fn connect_with(
-        _: TransportConnect,
+        config: TransportConnect,
         remote_addr: RemoteAddr,
     ) -> io::Result<ConnectionInfo<Self>> {
+        let config = match config {
+            TransportConnect::Ws(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
         let (peer_addr, url) = match remote_addr {
             RemoteAddr::Socket(addr) => {
-                (addr, Url::parse(&format!("ws://{addr}/message-io-default")).unwrap())
+                let path = config.path.as_deref().unwrap_or("/message-io-default");
+                let url = Url::parse(&format!("ws://{addr}")).unwrap().join(path);
+                (addr, url.map_err(invalid_input)?)
             }
             RemoteAddr::Str(path) => {
                 let url = Url::parse(&path).expect("A valid URL");
@@ -98,16 +707,36 @@ impl Remote for RemoteResource {
             }
         };
 
-        let stream = TcpStream::connect(peer_addr)?;
+        let request = client_request(url, &config)?;
+        let stream = tcp::connect_stream(
+            peer_addr,
+            config.source_address,
+            config.bind_device.as_deref(),
+            &config.socket_options,
+        )?;
         let local_addr = stream.local_addr()?;
+        Ok(ConnectionInfo {
+            remote: RemoteResource::client(request, Box::new(stream), config),
+            local_addr,
+            peer_addr,
+        })
+    }
 
+    fn adopt_with(config: TransportConnect, socket: Socket) -> io::Result<ConnectionInfo<Self>> {
+        let config = match config {
+            TransportConnect::Ws(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let (stream, local_addr, peer_addr) = stream::adopt_stream(socket, &config.socket_options)?;
+        let host = match peer_addr == stream::UNIX_SOCKET_ADDR {
+            true => String::from("localhost"),
+            false => peer_addr.to_string(),
+        };
+        let path = config.path.as_deref().unwrap_or("/message-io-default");
+        let url = Url::parse(&format!("ws://{host}")).unwrap().join(path);
+        let request = client_request(url.map_err(invalid_input)?, &config)?;
         Ok(ConnectionInfo {
-            remote: RemoteResource {
-                state: Mutex::new(RemoteState::Handshake(Some(PendingHandshake::Connect(
-                    url,
-                    stream.into(),
-                )))),
-            },
+            remote: RemoteResource::client(request, stream, config),
             local_addr,
             peer_addr,
         })
@@ -121,31 +750,41 @@ impl Remote for RemoteResource {
 
             match deref_state {
                 RemoteState::WebSocket(web_socket) => match web_socket.read() {
-                    Ok(message) => match message {
-                        Message::Binary(data) => {
-                            // As an optimization.
-                            // Fast check to know if there is more data to avoid call
-                            // WebSocket::read_message() again.
-                            // TODO: investigate why this code doesn't work in windows.
-                            // Seems like windows consume the `WouldBlock` notification
-                            // at peek() when it happens, and the poll never wakes it again.
-                            #[cfg(not(target_os = "windows"))]
-                            let _peek_result = web_socket.get_ref().0.peek(&mut [0; 0]);
-
-                            // We can not call process_data while the socket is blocked.
-                            // The user could lock it again if sends from the callback.
-                            drop(state);
-                            process_data(&data);
-
-                            #[cfg(not(target_os = "windows"))]
-                            if let Err(err) = _peek_result {
-                                break Self::io_error_to_read_status(&err);
+                    Ok(message) => {
+                        let fragment = web_socket.get_mut().1.as_mut().and_then(|reader| {
+                            match message.is_binary() || message.is_text() {
+                                true => reader.next_fragment(),
+                                false => None,
                             }
-                        }
-                        Message::Close(_) => break ReadStatus::Disconnected,
-                        _ => continue,
-                    },
+                        });
+                        let (data, kind) = match message {
+                            Message::Binary(data) => (data, MessageKind::Binary),
+                            Message::Text(text) => (text.into(), MessageKind::Text),
+                            Message::Close(_) => break ReadStatus::Disconnected,
+                            Message::Pong(payload) => {
+                                self.process_pong(&payload);
+                                continue
+                            }
+                            // The pings are answered by the websocket.
+                            Message::Ping(_) | Message::Frame(_) => continue,
+                        };
+
+                        // We can not call process_data while the socket is blocked.
+                        // The user could lock it again if sends from the callback.
+                        // The socket is not checked for more data before reading again:
+                        // the next messages can be already buffered by the websocket.
+                        drop(state);
+                        let (kind, complete) = fragment.unwrap_or((kind, true));
+                        self.receiving_text.store(kind == MessageKind::Text, Ordering::Relaxed);
+                        self.receiving_complete.store(complete, Ordering::Relaxed);
+                        process_data(&data);
+                    }
                     Err(Error::Io(ref err)) => break Self::io_error_to_read_status(err),
+                    Err(Error::Capacity(err)) => {
+                        // The peer exceeded the configured limits.
+                        log::warn!("WS receive error: {}", err);
+                        break ReadStatus::Disconnected;
+                    }
                     Err(err) => {
                         log::error!("WS receive error: {}", err);
                         break ReadStatus::Disconnected; // should not happen
@@ -158,50 +797,74 @@ impl Remote for RemoteResource {
     }
 
     fn send(&self, data: &[u8]) -> SendStatus {
+        self.send_message(Message::Binary(data.to_vec().into()))
+    }
+
+    fn send_fragment(&self, data: &[u8], last: bool) -> SendStatus {
         let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
         let deref_state = state.deref_mut();
         match deref_state {
             RemoteState::WebSocket(web_socket) => {
-                let message = Message::Binary(data.to_vec().into());
-
-                let mut result = web_socket.send(message);
-                loop {
-                    match result {
-                        Ok(_) => break SendStatus::Sent,
-                        Err(Error::Io(ref err)) if err.kind() == ErrorKind::WouldBlock => {
-                            result = web_socket.flush();
-                        }
-                        Err(Error::Capacity(_)) => break SendStatus::MaxPacketSizeExceeded,
-                        Err(err) => {
-                            log::error!("WS send error: {}", err);
-                            break SendStatus::ResourceNotFound; // should not happen
-                        }
-                    }
+                let first = !self.sending_fragments.load(Ordering::Relaxed);
+                let opcode = match first {
+                    true => Data::Binary,
+                    false => Data::Continue,
+                };
+                let (payload, compressed) =
+                    match self.deflater.lock().expect(OTHER_THREAD_ERR).as_mut() {
+                        Some(deflater) => (deflater.compress_fragment(data, last), first),
+                        None => (data.to_vec(), false),
+                    };
+                let header = FrameHeader {
+                    is_final: last,
+                    rsv1: compressed,
+                    opcode: OpCode::Data(opcode),
+                    ..Default::default()
+                };
+                let frame = Frame::from_payload(header, payload.into());
+                let status = Self::write_message(web_socket, Message::Frame(frame));
+                if status == SendStatus::Sent {
+                    self.sending_fragments.store(!last, Ordering::Relaxed);
                 }
+                status
             }
             RemoteState::Handshake(_) => unreachable!(),
             RemoteState::Error(_) => unreachable!(),
         }
     }
 
+    fn send_text(&self, text: &str) -> SendStatus {
+        self.send_message(Message::Text(text.into()))
+    }
+
+    fn message_kind(&self) -> MessageKind {
+        match self.receiving_text.load(Ordering::Relaxed) {
+            true => MessageKind::Text,
+            false => MessageKind::Binary,
+        }
+    }
+
+    fn message_complete(&self) -> bool {
+        self.receiving_complete.load(Ordering::Relaxed)
+    }
+
     fn pending(&self, _readiness: Readiness) -> PendingStatus {
         let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
         let deref_state = state.deref_mut();
         match deref_state {
             RemoteState::WebSocket(_) => PendingStatus::Ready,
             RemoteState::Handshake(pending) => match pending.take().unwrap() {
-                PendingHandshake::Connect(url, stream) => {
-                    let tcp_status = super::tcp::check_stream_ready(&stream.0);
-                    if tcp_status != PendingStatus::Ready {
+                PendingHandshake::Connect(request, config, stream) => {
+                    let connect_status = stream.0.connect_status();
+                    if connect_status != PendingStatus::Ready {
                         // TCP handshake not ready yet.
-                        *pending = Some(PendingHandshake::Connect(url, stream));
-                        return tcp_status;
+                        *pending = Some(PendingHandshake::Connect(request, config, stream));
+                        return connect_status;
                     }
                     let stream_backup = stream.clone();
-                    match ws_connect(url, stream) {
-                        Ok((web_socket, _)) => {
-                            *state = RemoteState::WebSocket(web_socket);
-                            PendingStatus::Ready
+                    match ws_connect(*request, stream, Some(config)) {
+                        Ok((web_socket, response)) => {
+                            self.client_ready(&mut state, web_socket, response.headers())
                         }
                         Err(HandshakeError::Interrupted(mid_handshake)) => {
                             *pending = Some(PendingHandshake::Client(mid_handshake));
@@ -211,6 +874,14 @@ impl Remote for RemoteResource {
                             *state = RemoteState::Error(stream_backup);
                             PendingStatus::Disconnected
                         }
+                        Err(HandshakeError::Failure(Error::Http(response))) => {
+                            *state = RemoteState::Error(stream_backup);
+                            log::warn!(
+                                "WS connection rejected by the server: {}",
+                                response.status()
+                            );
+                            PendingStatus::Disconnected
+                        }
                         Err(HandshakeError::Failure(err)) => {
                             *state = RemoteState::Error(stream_backup);
                             log::error!("WS connect handshake error: {}", err);
@@ -218,9 +889,29 @@ impl Remote for RemoteResource {
                         }
                     }
                 }
-                PendingHandshake::Accept(stream) => {
+                PendingHandshake::Accept(config, stream) => {
+                    if let Some(handler) = &config.http_handler {
+                        match http::process_request(&*stream.0, handler) {
+                            Ok(HttpStatus::Upgrade) => (),
+                            Ok(HttpStatus::Incomplete) => {
+                                *pending = Some(PendingHandshake::Accept(config, stream));
+                                return PendingStatus::Incomplete
+                            }
+                            Ok(HttpStatus::Answered(response)) => {
+                                return Self::respond(&mut state, response, stream)
+                            }
+                            Err(err) => {
+                                log::trace!("HTTP request error: {}", err);
+                                *state = RemoteState::Error(stream);
+                                return PendingStatus::Disconnected
+                            }
+                        }
+                    }
                     let stream_backup = stream.clone();
-                    match ws_accept(stream) {
+                    let websocket_config = config.websocket_config;
+                    let callback =
+                        AcceptCallback(config, self.request.clone(), self.deflater.clone());
+                    match ws_accept(stream, callback, Some(websocket_config)) {
                         Ok(web_socket) => {
                             *state = RemoteState::WebSocket(web_socket);
                             PendingStatus::Ready
@@ -233,6 +924,11 @@ impl Remote for RemoteResource {
                             *state = RemoteState::Error(stream_backup);
                             PendingStatus::Disconnected
                         }
+                        Err(HandshakeError::Failure(Error::Http(response))) => {
+                            *state = RemoteState::Error(stream_backup);
+                            log::trace!("WS connection rejected with {}", response.status());
+                            PendingStatus::Disconnected
+                        }
                         Err(HandshakeError::Failure(err)) => {
                             *state = RemoteState::Error(stream_backup);
                             log::error!("WS accept handshake error: {}", err);
@@ -240,12 +936,14 @@ impl Remote for RemoteResource {
                         }
                     }
                 }
+                PendingHandshake::Respond(response, stream) => {
+                    Self::respond(&mut state, response, stream)
+                }
                 PendingHandshake::Client(mid_handshake) => {
                     let stream_backup = mid_handshake.get_ref().get_ref().clone();
                     match mid_handshake.handshake() {
-                        Ok((web_socket, _)) => {
-                            *state = RemoteState::WebSocket(web_socket);
-                            PendingStatus::Ready
+                        Ok((web_socket, response)) => {
+                            self.client_ready(&mut state, web_socket, response.headers())
                         }
                         Err(HandshakeError::Interrupted(mid_handshake)) => {
                             *pending = Some(PendingHandshake::Client(mid_handshake));
@@ -255,6 +953,14 @@ impl Remote for RemoteResource {
                             *state = RemoteState::Error(stream_backup);
                             PendingStatus::Disconnected
                         }
+                        Err(HandshakeError::Failure(Error::Http(response))) => {
+                            *state = RemoteState::Error(stream_backup);
+                            log::warn!(
+                                "WS connection rejected by the server: {}",
+                                response.status()
+                            );
+                            PendingStatus::Disconnected
+                        }
                         Err(HandshakeError::Failure(err)) => {
                             *state = RemoteState::Error(stream_backup);
                             log::error!("WS client handshake error: {}", err);
@@ -277,6 +983,11 @@ impl Remote for RemoteResource {
                             *state = RemoteState::Error(stream_backup);
                             PendingStatus::Disconnected
                         }
+                        Err(HandshakeError::Failure(Error::Http(response))) => {
+                            *state = RemoteState::Error(stream_backup);
+                            log::trace!("WS connection rejected with {}", response.status());
+                            PendingStatus::Disconnected
+                        }
                         Err(HandshakeError::Failure(err)) => {
                             *state = RemoteState::Error(stream_backup);
                             log::error!("WS server handshake error: {}", err);
@@ -289,6 +1000,42 @@ impl Remote for RemoteResource {
         }
     }
 
+    fn keepalive_interval(&self) -> Option<Duration> {
+        self.ping.as_ref().map(|(config, _)| config.interval)
+    }
+
+    fn keepalive(&self) -> bool {
+        let (config, ping_state) = match &self.ping {
+            Some(ping) => ping,
+            None => return true,
+        };
+
+        let mut ping_state = ping_state.lock().expect(OTHER_THREAD_ERR);
+        if ping_state.sent.is_some() {
+            ping_state.missed_pongs += 1;
+            if ping_state.missed_pongs >= config.max_missed_pongs {
+                log::trace!("WS peer missed {} pongs", ping_state.missed_pongs);
+                return false
+            }
+        }
+        ping_state.sequence += 1;
+        ping_state.sent = Some(Instant::now());
+        let payload = ping_state.sequence.to_be_bytes();
+        drop(ping_state);
+
+        self.send_message(Message::Ping(payload.to_vec().into())) == SendStatus::Sent
+    }
+
+    fn round_trip_time(&self) -> Option<Duration> {
+        let (_, ping_state) = self.ping.as_ref()?;
+        ping_state.lock().expect(OTHER_THREAD_ERR).round_trip_time
+    }
+
+    fn metadata(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        let request = self.request.lock().expect(OTHER_THREAD_ERR).clone()?;
+        Some(request)
+    }
+
     fn ready_to_write(&self) -> bool {
         true
         /* Is this needed?
@@ -307,6 +1054,107 @@ impl Remote for RemoteResource {
 }
 
 impl RemoteResource {
+    /// Completes the handshake of a client with the compression accepted by the server.
+    fn client_ready(
+        &self,
+        state: &mut RemoteState,
+        web_socket: WebSocket<ArcStream>,
+        headers: &HeaderMap,
+    ) -> PendingStatus {
+        let deflater = match &self.deflate_offer {
+            Some(config) => deflate::accepted(config, headers),
+            None => Ok(None),
+        };
+        match deflater {
+            Ok(deflater) => {
+                *self.deflater.lock().expect(OTHER_THREAD_ERR) = deflater;
+                *state = RemoteState::WebSocket(web_socket);
+                PendingStatus::Ready
+            }
+            Err(err) => {
+                log::warn!("WS connection with invalid extensions: {}", err);
+                *state = RemoteState::Error(web_socket.get_ref().clone());
+                PendingStatus::Disconnected
+            }
+        }
+    }
+
+    /// Writes the response of an HTTP request,
+    /// the connection is closed once it is completely written.
+    fn respond(
+        state: &mut RemoteState,
+        mut response: Cursor<Vec<u8>>,
+        stream: ArcStream,
+    ) -> PendingStatus {
+        match http::write_response(&*stream.0, &mut response) {
+            Ok(true) => {
+                *state = RemoteState::Error(stream);
+                PendingStatus::Disconnected
+            }
+            Ok(false) => {
+                *state = RemoteState::Handshake(Some(PendingHandshake::Respond(response, stream)));
+                PendingStatus::Incomplete
+            }
+            Err(err) => {
+                log::trace!("HTTP response error: {}", err);
+                *state = RemoteState::Error(stream);
+                PendingStatus::Disconnected
+            }
+        }
+    }
+
+    fn send_message(&self, message: Message) -> SendStatus {
+        let mut state = self.state.lock().expect(OTHER_THREAD_ERR);
+        let deref_state = state.deref_mut();
+        match deref_state {
+            RemoteState::WebSocket(web_socket) => {
+                // The data messages can not be interleaved with the fragments of other message.
+                let data_message = message.is_binary() || message.is_text();
+                if data_message && self.sending_fragments.load(Ordering::Relaxed) {
+                    return SendStatus::ResourceNotAvailable
+                }
+                let message = match self.deflater.lock().expect(OTHER_THREAD_ERR).as_mut() {
+                    Some(deflater) => deflater.compress(message),
+                    None => message,
+                };
+                Self::write_message(web_socket, message)
+            }
+            RemoteState::Handshake(_) => unreachable!(),
+            RemoteState::Error(_) => unreachable!(),
+        }
+    }
+
+    fn write_message(web_socket: &mut WebSocket<ArcStream>, message: Message) -> SendStatus {
+        let mut result = web_socket.send(message);
+        loop {
+            match result {
+                Ok(_) => break SendStatus::Sent,
+                Err(Error::Io(ref err)) if err.kind() == ErrorKind::WouldBlock => {
+                    result = web_socket.flush();
+                }
+                Err(Error::Capacity(_)) => break SendStatus::MaxPacketSizeExceeded,
+                Err(err) => {
+                    log::error!("WS send error: {}", err);
+                    break SendStatus::ResourceNotFound; // should not happen
+                }
+            }
+        }
+    }
+
+    fn process_pong(&self, payload: &[u8]) {
+        if let Some((_, ping_state)) = &self.ping {
+            let mut ping_state = ping_state.lock().expect(OTHER_THREAD_ERR);
+            // Any pong shows that the peer is alive,
+            // but only the pong of the last ping measures the round trip time.
+            ping_state.missed_pongs = 0;
+            if payload == ping_state.sequence.to_be_bytes() {
+                if let Some(sent) = ping_state.sent.take() {
+                    ping_state.round_trip_time = Some(sent.elapsed());
+                }
+            }
+        }
+    }
+
     fn io_error_to_read_status(err: &io::Error) -> ReadStatus {
         if err.kind() == io::ErrorKind::WouldBlock {
             ReadStatus::WaitNextEvent
@@ -322,33 +1170,83 @@ impl RemoteResource {
 }
 
 pub(crate) struct LocalResource {
-    listener: TcpListener,
+    listener: Box<dyn WsListener>,
+    keepalive: Option<TcpKeepalive>,
+    socket_options: Vec<SocketOption>,
+    accept_config: Arc<AcceptConfig>,
+    ping: Option<PingConfig>,
+}
+
+impl LocalResource {
+    fn new(listener: Box<dyn WsListener>, config: WsListenConfig) -> io::Result<Self> {
+        Ok(LocalResource {
+            listener,
+            accept_config: Arc::new(AcceptConfig::new(&config)?),
+            keepalive: config.keepalive,
+            socket_options: config.socket_options,
+            ping: config.ping,
+        })
+    }
 }
 
 impl Resource for LocalResource {
     fn source(&mut self) -> &mut dyn Source {
-        &mut self.listener
+        self.listener.source()
+    }
+
+    fn with_socket(&self, action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        action(&self.listener.socket())
     }
 }
 
 impl Local for LocalResource {
     type Remote = RemoteResource;
 
-    fn listen_with(_: TransportListen, addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
-        let listener = TcpListener::bind(addr)?;
+    fn listen_with(config: TransportListen, addr: SocketAddr) -> io::Result<ListeningInfo<Self>> {
+        let config = match config {
+            TransportListen::Ws(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let socket = tcp::listener_socket(addr, config.reuse_port)?;
+        if let Some(device) = &config.bind_device {
+            tcp::bind_device(&socket, device)?;
+        }
+        let listener = tcp::bind_listener(socket, addr)?;
         let local_addr = listener.local_addr().unwrap();
-        Ok(ListeningInfo { local: LocalResource { listener }, local_addr })
+        Ok(ListeningInfo { local: LocalResource::new(Box::new(listener), config)?, local_addr })
+    }
+
+    fn adopt_with(config: TransportListen, socket: Socket) -> io::Result<ListeningInfo<Self>> {
+        let config = match config {
+            TransportListen::Ws(config) => config,
+            _ => panic!("Internal error: Got wrong config"),
+        };
+        let (listener, local_addr) = stream::adopt_listener(socket)?;
+        Ok(ListeningInfo { local: LocalResource::new(listener, config)?, local_addr })
     }
 
     fn accept(&self, mut accept_remote: impl FnMut(AcceptedType<'_, Self::Remote>)) {
         loop {
             match self.listener.accept() {
                 Ok((stream, addr)) => {
-                    let remote = RemoteResource {
-                        state: Mutex::new(RemoteState::Handshake(Some(PendingHandshake::Accept(
-                            stream.into(),
-                        )))),
-                    };
+                    if let Err(err) = set_socket_options(&stream.socket(), &self.socket_options) {
+                        log::warn!("WS set socket options error: {}", err);
+                    }
+                    if let Some(keepalive) = &self.keepalive {
+                        stream.set_keepalive(keepalive);
+                    }
+                    let config = &self.accept_config;
+                    let stream = ArcStream::new(
+                        stream,
+                        config.deflate.is_some(),
+                        config.fragments,
+                        config.websocket_config.max_message_size,
+                    );
+                    let remote = RemoteResource::new(
+                        PendingHandshake::Accept(config.clone(), stream),
+                        self.ping,
+                        None,
+                    );
                     accept_remote(AcceptedType::Remote(addr, remote));
                 }
                 Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
@@ -363,32 +1261,49 @@ impl Local for LocalResource {
 /// an drop it without allow to the driver to deregister from the poll.
 /// It can be removed when this issue is resolved:
 /// https://github.com/snapview/tungstenite-rs/issues/51
-struct ArcTcpStream(Arc<TcpStream>);
+/// If the connection is compressed or receives the fragments of the messages,
+/// the stream is read by a [`FrameReader`].
+struct ArcStream(Arc<dyn WsStream>, Option<FrameReader>);
 
-impl From<TcpStream> for ArcTcpStream {
-    fn from(stream: TcpStream) -> Self {
-        Self(Arc::new(stream))
+impl ArcStream {
+    fn new(
+        stream: Box<dyn WsStream>,
+        inflate: bool,
+        fragments: bool,
+        max_message_size: Option<usize>,
+    ) -> Self {
+        let reader =
+            (inflate || fragments).then(|| FrameReader::new(inflate, fragments, max_message_size));
+        Self(stream.into(), reader)
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        Arc::get_mut(&mut self.0).unwrap().source()
     }
 }
 
-impl io::Read for ArcTcpStream {
+impl io::Read for ArcStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        (&*self.0).read(buf)
+        match &mut self.1 {
+            Some(reader) => reader.read(&*self.0, buf),
+            None => self.0.read(buf),
+        }
     }
 }
 
-impl io::Write for ArcTcpStream {
+impl io::Write for ArcStream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        (&*self.0).write(buf)
+        self.0.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        (&*self.0).flush()
+        self.0.flush()
     }
 }
 
-impl Clone for ArcTcpStream {
+/// The clones are only used to keep the stream while it is not read.
+impl Clone for ArcStream {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self(self.0.clone(), None)
     }
 }
@@ -0,0 +1,486 @@
+use tungstenite::protocol::{Message};
+use tungstenite::protocol::frame::{Frame, FrameHeader};
+use tungstenite::protocol::frame::coding::{OpCode, Data};
+use tungstenite::http::{HeaderMap, HeaderValue};
+use tungstenite::http::header::{SEC_WEBSOCKET_EXTENSIONS};
+
+use flate2::{Compress, Decompress, Compression, FlushCompress, FlushDecompress, Status};
+
+use super::{invalid_data};
+
+use std::io::{self};
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// Trailing bytes that the sender removes from each compressed message (RFC 7692 7.2.1).
+const MESSAGE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Window bits supported by the compressor.
+/// The value `8` of the RFC is not supported by zlib.
+const MIN_WINDOW_BITS: u8 = 9;
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// Options of the `permessage-deflate` extension (RFC 7692), which compresses the messages.
+/// See [`super::WsConnectConfig::with_deflate()`] and [`super::WsListenConfig::with_deflate()`].
+///
+/// The client requests these options and the server can impose stricter ones:
+/// in a connection, the strictest options of both sides are used.
+/// If the other side does not support the extension, the messages are not compressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WsDeflateConfig {
+    server_max_window_bits: u8,
+    client_max_window_bits: u8,
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+}
+
+impl Default for WsDeflateConfig {
+    fn default() -> Self {
+        WsDeflateConfig {
+            server_max_window_bits: MAX_WINDOW_BITS,
+            client_max_window_bits: MAX_WINDOW_BITS,
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+        }
+    }
+}
+
+impl WsDeflateConfig {
+    /// Size of the window used by the server to compress, as a power of two
+    /// (from 9 to 15 bits, 15 by default).
+    /// A smaller window uses less memory but compresses less.
+    ///
+    /// # Panics
+    /// If `bits` is out of the range.
+    pub fn with_server_max_window_bits(mut self, bits: u8) -> Self {
+        self.server_max_window_bits = checked_window_bits(bits);
+        self
+    }
+
+    /// Size of the window used by the client to compress, as a power of two
+    /// (from 9 to 15 bits, 15 by default).
+    /// A smaller window uses less memory but compresses less.
+    ///
+    /// # Panics
+    /// If `bits` is out of the range.
+    pub fn with_client_max_window_bits(mut self, bits: u8) -> Self {
+        self.client_max_window_bits = checked_window_bits(bits);
+        self
+    }
+
+    /// The server compresses each message independently of the previous ones.
+    /// It uses less memory but compresses less.
+    pub fn with_server_no_context_takeover(mut self) -> Self {
+        self.server_no_context_takeover = true;
+        self
+    }
+
+    /// The client compresses each message independently of the previous ones.
+    /// It uses less memory but compresses less.
+    pub fn with_client_no_context_takeover(mut self) -> Self {
+        self.client_no_context_takeover = true;
+        self
+    }
+}
+
+fn checked_window_bits(bits: u8) -> u8 {
+    assert!(
+        (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits),
+        "The window bits must be in the range [{}, {}]",
+        MIN_WINDOW_BITS,
+        MAX_WINDOW_BITS,
+    );
+    bits
+}
+
+/// Parameters of an offer or an answer of the extension.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Params {
+    server_max_window_bits: Option<u8>,
+
+    /// The client can send the parameter without value in its offer.
+    client_max_window_bits: Option<Option<u8>>,
+
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+}
+
+impl Params {
+    fn parse<'a>(params: impl Iterator<Item = &'a str>) -> Result<Params, String> {
+        let mut parsed = Params::default();
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            let window_bits = |value: Option<&str>| match value.map(str::parse::<u8>) {
+                Some(Ok(bits)) if (8..=MAX_WINDOW_BITS).contains(&bits) => Ok(Some(bits)),
+                None => Ok(None),
+                _ => Err(format!("Invalid value of {name}")),
+            };
+            let duplicated = match name {
+                "server_max_window_bits" => match window_bits(value)? {
+                    Some(bits) => parsed.server_max_window_bits.replace(bits).is_some(),
+                    None => return Err(format!("Missing value of {name}")),
+                },
+                "client_max_window_bits" => {
+                    parsed.client_max_window_bits.replace(window_bits(value)?).is_some()
+                }
+                "server_no_context_takeover" if value.is_none() => {
+                    std::mem::replace(&mut parsed.server_no_context_takeover, true)
+                }
+                "client_no_context_takeover" if value.is_none() => {
+                    std::mem::replace(&mut parsed.client_no_context_takeover, true)
+                }
+                _ => return Err(format!("Unknown parameter {param}")),
+            };
+            if duplicated {
+                return Err(format!("Duplicated parameter {name}"))
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn to_header(&self) -> HeaderValue {
+        let mut header = String::from(EXTENSION_NAME);
+        if let Some(bits) = self.server_max_window_bits {
+            header += &format!("; server_max_window_bits={bits}");
+        }
+        match self.client_max_window_bits {
+            Some(Some(bits)) => header += &format!("; client_max_window_bits={bits}"),
+            Some(None) => header += "; client_max_window_bits",
+            None => (),
+        }
+        if self.server_no_context_takeover {
+            header += "; server_no_context_takeover";
+        }
+        if self.client_no_context_takeover {
+            header += "; client_no_context_takeover";
+        }
+        HeaderValue::from_str(&header).expect("A valid header")
+    }
+}
+
+/// Iterates over the extensions of the headers, giving their name and their parameters.
+fn extensions(headers: &HeaderMap) -> impl Iterator<Item = (&str, impl Iterator<Item = &str>)> {
+    headers
+        .get_all(SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|extension| {
+            let mut parts = extension.split(';').map(str::trim);
+            let name = parts.next().filter(|name| !name.is_empty())?;
+            Some((name, parts.filter(|param| !param.is_empty())))
+        })
+}
+
+/// Header of the extension offered by a client.
+pub(super) fn offer(config: &WsDeflateConfig) -> HeaderValue {
+    Params {
+        server_max_window_bits: Some(config.server_max_window_bits)
+            .filter(|&bits| bits < MAX_WINDOW_BITS),
+        client_max_window_bits: Some(
+            Some(config.client_max_window_bits).filter(|&bits| bits < MAX_WINDOW_BITS),
+        ),
+        server_no_context_takeover: config.server_no_context_takeover,
+        client_no_context_takeover: config.client_no_context_takeover,
+    }
+    .to_header()
+}
+
+/// Chooses the first acceptable offer of the client headers.
+/// Returns the header of the answer and the compressor of the server,
+/// or `None` if the client has not offered the extension or its offers are not acceptable.
+pub(super) fn accept(
+    config: &WsDeflateConfig,
+    headers: &HeaderMap,
+) -> Option<(HeaderValue, Deflater)> {
+    extensions(headers)
+        .filter(|(name, _)| name.eq_ignore_ascii_case(EXTENSION_NAME))
+        .filter_map(|(_, params)| Params::parse(params).ok())
+        .find_map(|offer| {
+            let server_bits = offer
+                .server_max_window_bits
+                .unwrap_or(MAX_WINDOW_BITS)
+                .min(config.server_max_window_bits);
+            if server_bits < MIN_WINDOW_BITS {
+                return None
+            }
+
+            // The window of the client can only be limited if the client supports it.
+            let client_bits = offer
+                .client_max_window_bits
+                .map(|bits| bits.unwrap_or(MAX_WINDOW_BITS).min(config.client_max_window_bits));
+
+            let answer = Params {
+                server_max_window_bits: Some(server_bits).filter(|&bits| bits < MAX_WINDOW_BITS),
+                client_max_window_bits: client_bits
+                    .filter(|&bits| bits < MAX_WINDOW_BITS)
+                    .map(Some),
+                server_no_context_takeover: offer.server_no_context_takeover
+                    || config.server_no_context_takeover,
+                client_no_context_takeover: offer.client_no_context_takeover
+                    || config.client_no_context_takeover,
+            };
+            let deflater = Deflater::new(server_bits, answer.server_no_context_takeover);
+            Some((answer.to_header(), deflater))
+        })
+}
+
+/// Checks the answer of the server to the offer of the client.
+/// Returns the compressor of the client, or `None` if the server has not accepted the extension.
+/// It fails if the answer does not match the offer.
+pub(super) fn accepted(
+    config: &WsDeflateConfig,
+    headers: &HeaderMap,
+) -> Result<Option<Deflater>, String> {
+    let mut answers = extensions(headers);
+    let answer = match (answers.next(), answers.next()) {
+        (None, _) => return Ok(None),
+        (Some((name, params)), None) if name.eq_ignore_ascii_case(EXTENSION_NAME) => {
+            Params::parse(params)?
+        }
+        _ => return Err("Extensions not offered".into()),
+    };
+
+    let server_bits = answer.server_max_window_bits.unwrap_or(MAX_WINDOW_BITS);
+    if server_bits > config.server_max_window_bits {
+        return Err("Window bits of the server not accepted".into())
+    }
+    if config.server_no_context_takeover && !answer.server_no_context_takeover {
+        return Err("Context takeover of the server not accepted".into())
+    }
+    let client_bits = match answer.client_max_window_bits {
+        Some(Some(bits)) => bits.min(config.client_max_window_bits),
+        Some(None) => return Err("Missing value of client_max_window_bits".into()),
+        None => config.client_max_window_bits,
+    };
+    if client_bits < MIN_WINDOW_BITS {
+        return Err(format!("Window bits not supported: {client_bits}"))
+    }
+    let no_context_takeover =
+        config.client_no_context_takeover || answer.client_no_context_takeover;
+    Ok(Some(Deflater::new(client_bits, no_context_takeover)))
+}
+
+/// Compresses the messages sent by a connection.
+pub(super) struct Deflater {
+    compress: Compress,
+    no_context_takeover: bool,
+}
+
+impl Deflater {
+    pub fn new(window_bits: u8, no_context_takeover: bool) -> Self {
+        Deflater {
+            compress: Compress::new_with_window_bits(Compression::default(), false, window_bits),
+            no_context_takeover,
+        }
+    }
+
+    /// Compresses the data messages into a frame, the rest of messages are not modified.
+    pub fn compress(&mut self, message: Message) -> Message {
+        let (data, opcode) = match &message {
+            Message::Binary(data) => (data.as_ref(), Data::Binary),
+            Message::Text(text) => (text.as_bytes(), Data::Text),
+            _ => return message,
+        };
+        let output = self.compress_fragment(data, true);
+        let header = FrameHeader { rsv1: true, opcode: OpCode::Data(opcode), ..Default::default() };
+        Message::Frame(Frame::from_payload(header, output.into()))
+    }
+
+    /// Compresses a fragment of a message, the fragments are compressed in order.
+    /// Only the first frame of the message has the `rsv1` bit set.
+    pub fn compress_fragment(&mut self, data: &[u8], last: bool) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&data[consumed..], &mut output, FlushCompress::Sync)
+                .expect("A valid compression stream");
+
+            // The flush is completed once the output has not been filled.
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && output.len() < output.capacity() {
+                break
+            }
+            output.reserve(output.capacity());
+        }
+        if last {
+            if output.ends_with(&MESSAGE_TAIL) {
+                output.truncate(output.len() - MESSAGE_TAIL.len());
+            }
+            if self.no_context_takeover {
+                self.compress.reset();
+            }
+        }
+        output
+    }
+}
+
+/// Inflates the messages received by a connection.
+///
+/// The context of the messages is always kept to inflate them:
+/// it is valid although the peer does not keep its context.
+pub(super) struct Inflater {
+    decompress: Decompress,
+}
+
+impl Inflater {
+    pub fn new() -> Self {
+        Inflater { decompress: Decompress::new_with_window_bits(false, MAX_WINDOW_BITS) }
+    }
+
+    /// Inflates the compressed data of a message, that can be received in several fragments.
+    /// The `max_size` of the output is checked while it is inflated.
+    pub fn inflate(
+        &mut self,
+        mut data: Vec<u8>,
+        last: bool,
+        max_size: usize,
+    ) -> io::Result<Vec<u8>> {
+        if last {
+            data.extend_from_slice(&MESSAGE_TAIL);
+        }
+        let mut output = Vec::with_capacity(data.len() * 2);
+        let start = self.decompress.total_in();
+        while !data.is_empty() {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let status = self
+                .decompress
+                .decompress_vec(&data[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(invalid_data)?;
+            if output.len() > max_size {
+                return Err(invalid_data("Message too big"))
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if status == Status::StreamEnd {
+                // The peer ended its stream, the next messages start a new one.
+                self.decompress.reset(false);
+                break
+            }
+            let inflated = output.len() < output.capacity() || status == Status::BufError;
+            if consumed == data.len() && inflated {
+                break
+            }
+            if status == Status::BufError {
+                return Err(invalid_data("Invalid compressed message"))
+            }
+            output.reserve(output.capacity());
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(extensions: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_str(extensions).unwrap());
+        headers
+    }
+
+    #[test]
+    fn negotiation() {
+        let config = WsDeflateConfig::default()
+            .with_server_max_window_bits(10)
+            .with_client_no_context_takeover();
+        let offer = offer(&config);
+        assert_eq!(
+            offer,
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits; \
+             client_no_context_takeover"
+        );
+
+        let server_config = WsDeflateConfig::default()
+            .with_client_max_window_bits(12)
+            .with_server_no_context_takeover();
+        let (answer, _) = accept(&server_config, &headers(offer.to_str().unwrap())).unwrap();
+        assert_eq!(
+            answer,
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits=12; \
+             server_no_context_takeover; client_no_context_takeover"
+        );
+        assert!(accepted(&config, &headers(answer.to_str().unwrap())).unwrap().is_some());
+    }
+
+    #[test]
+    fn negotiation_without_extension() {
+        let config = WsDeflateConfig::default();
+        assert!(accept(&config, &HeaderMap::new()).is_none());
+        assert!(accept(&config, &headers("x-webkit-deflate-frame")).is_none());
+        assert!(accepted(&config, &HeaderMap::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn negotiation_chooses_acceptable_offer() {
+        let config = WsDeflateConfig::default();
+        let offers = "permessage-deflate; server_max_window_bits=8, \
+                      permessage-deflate; unknown, \
+                      permessage-deflate; server_max_window_bits=\"11\"";
+        let (answer, _) = accept(&config, &headers(offers)).unwrap();
+        assert_eq!(answer, "permessage-deflate; server_max_window_bits=11");
+    }
+
+    #[test]
+    fn invalid_answers() {
+        let config = WsDeflateConfig::default().with_server_max_window_bits(10);
+        for answer in [
+            "permessage-deflate",
+            "permessage-deflate; server_max_window_bits=12",
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits",
+            "permessage-deflate; server_max_window_bits=10; client_max_window_bits=8",
+            "permessage-deflate; server_max_window_bits=10; server_max_window_bits=10",
+            "permessage-deflate; server_max_window_bits=10, x-other",
+        ] {
+            assert!(accepted(&config, &headers(answer)).is_err(), "{}", answer);
+        }
+    }
+
+    #[test]
+    fn compress_and_inflate() {
+        let text = "{\"key\": \"value\"}".repeat(100);
+        let mut deflater = Deflater::new(MAX_WINDOW_BITS, false);
+        let mut inflater = Inflater::new();
+        for _ in 0..2 {
+            let compressed = match deflater.compress(Message::Text(text.clone().into())) {
+                Message::Frame(frame) => frame,
+                _ => unreachable!(),
+            };
+            assert!(compressed.header().rsv1);
+            assert!(compressed.payload().len() < text.len() / 10);
+            let inflated = inflater.inflate(compressed.payload().to_vec(), true, usize::MAX);
+            assert_eq!(inflated.unwrap(), text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn compress_and_inflate_fragments() {
+        let data = (0..3000).map(|index| (index % 7) as u8).collect::<Vec<_>>();
+        let mut deflater = Deflater::new(MAX_WINDOW_BITS, true);
+        let mut inflater = Inflater::new();
+        for _ in 0..2 {
+            let chunks = data.chunks(1000).collect::<Vec<_>>();
+            let mut inflated = Vec::new();
+            for (index, chunk) in chunks.iter().enumerate() {
+                let last = index == chunks.len() - 1;
+                let compressed = deflater.compress_fragment(chunk, last);
+                inflated.extend(inflater.inflate(compressed, last, usize::MAX).unwrap());
+            }
+            assert_eq!(inflated, data);
+        }
+    }
+
+    #[test]
+    fn message_too_big() {
+        let mut deflater = Deflater::new(MAX_WINDOW_BITS, false);
+        let compressed = deflater.compress_fragment(&[0; 1000], true);
+        let err = Inflater::new().inflate(compressed, true, 999).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
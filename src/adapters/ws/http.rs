@@ -0,0 +1,256 @@
+use super::stream::{WsStream};
+
+use tungstenite::http::{StatusCode};
+use tungstenite::http::header::{HeaderName, HeaderValue};
+
+use std::sync::{Arc};
+use std::io::{self, Read, Cursor, ErrorKind};
+
+/// Max size of an HTTP request, including its body.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+const MAX_HEADERS: usize = 64;
+
+/// HTTP request received by a WebSocket listener that does not upgrade the connection.
+/// See [`super::WsListenConfig::with_http_handler()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Method of the request, e.g. `GET`.
+    pub method: String,
+
+    /// Path of the request, e.g. `/healthz`.
+    pub path: String,
+
+    /// Query string of the request without the `?`, e.g. `page=1`.
+    pub query: Option<String>,
+
+    /// Headers of the request in the order they were received.
+    pub headers: Vec<(String, String)>,
+
+    /// Body of the request, given by its `Content-Length` header.
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Returns the value of the first header with the given name (case insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Response to an [`HttpRequest`].
+/// The `Content-Length` and `Connection: close` headers are added when it is sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Creates an empty response with the given status code, e.g. `200`.
+    pub fn new(status: u16) -> Self {
+        HttpResponse { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    /// Adds a header to the response, e.g. `Content-Type`.
+    /// Invalid headers are discarded when the response is sent.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body of the response.
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Serializes the response, without body for `HEAD` requests.
+    fn to_bytes(&self, method: &str) -> Vec<u8> {
+        let status = StatusCode::from_u16(self.status).unwrap_or_else(|_| {
+            log::warn!("Invalid HTTP status: {}", self.status);
+            StatusCode::INTERNAL_SERVER_ERROR
+        });
+        let reason = status.canonical_reason().unwrap_or_default();
+        let mut bytes = format!("HTTP/1.1 {} {}\r\n", status.as_u16(), reason).into_bytes();
+        for (name, value) in &self.headers {
+            let valid = HeaderName::from_bytes(name.as_bytes()).is_ok()
+                && HeaderValue::from_str(value).is_ok();
+            match valid {
+                true => bytes.extend_from_slice(format!("{name}: {value}\r\n").as_bytes()),
+                false => log::warn!("Invalid HTTP header: {}", name),
+            }
+        }
+        let length = self.body.len();
+        bytes.extend_from_slice(format!("Content-Length: {length}\r\n").as_bytes());
+        bytes.extend_from_slice(b"Connection: close\r\n\r\n");
+        if method != "HEAD" {
+            bytes.extend_from_slice(&self.body);
+        }
+        bytes
+    }
+}
+
+type HttpFn = dyn Fn(&HttpRequest) -> HttpResponse + Send + Sync;
+
+/// Callback that answers the HTTP requests of a listener.
+/// See [`super::WsListenConfig::with_http_handler()`].
+#[derive(Clone)]
+pub(super) struct HttpHandler(pub Arc<HttpFn>);
+
+impl std::fmt::Debug for HttpHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HttpHandler")
+    }
+}
+
+pub(super) enum HttpStatus {
+    /// The request has not been completely received yet.
+    Incomplete,
+
+    /// The request upgrades the connection to WebSocket, it has not been read.
+    Upgrade,
+
+    /// The request has been read, the connection must be closed once the response is written.
+    Answered(Cursor<Vec<u8>>),
+}
+
+/// Peeks the request of the stream and answers it with the handler,
+/// unless it is a WebSocket upgrade request.
+pub(super) fn process_request(
+    stream: &dyn WsStream,
+    handler: &HttpHandler,
+) -> io::Result<HttpStatus> {
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+    let size = match stream.peek(&mut buffer) {
+        Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+        Ok(size) => size,
+        Err(ref err) if err.kind() == ErrorKind::WouldBlock => return Ok(HttpStatus::Incomplete),
+        Err(err) => return Err(err),
+    };
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut request = httparse::Request::new(&mut headers);
+    let head_size = match request.parse(&buffer[..size]) {
+        Ok(httparse::Status::Complete(head_size)) => head_size,
+        Ok(httparse::Status::Partial) if size < MAX_REQUEST_SIZE => {
+            return Ok(HttpStatus::Incomplete)
+        }
+        Ok(httparse::Status::Partial) | Err(httparse::Error::TooManyHeaders) => {
+            return reject(stream, size, 431)
+        }
+        Err(_) => return reject(stream, size, 400),
+    };
+
+    let header = |name: &str| {
+        request
+            .headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| String::from_utf8_lossy(header.value))
+    };
+    let upgrade = header("upgrade");
+    let websocket = |value: &str| value.trim().eq_ignore_ascii_case("websocket");
+    if upgrade.is_some_and(|value| value.split(',').any(websocket)) {
+        return Ok(HttpStatus::Upgrade)
+    }
+    if header("transfer-encoding").is_some() {
+        return reject(stream, size, 411)
+    }
+    let body_size = match header("content-length").map(|value| value.trim().parse::<usize>()) {
+        Some(Ok(body_size)) => body_size,
+        Some(Err(_)) => return reject(stream, size, 400),
+        None => 0,
+    };
+    let request_size = head_size + body_size;
+    if request_size > MAX_REQUEST_SIZE {
+        return reject(stream, size, 413)
+    }
+    if size < request_size {
+        return Ok(HttpStatus::Incomplete)
+    }
+
+    let target = request.path.unwrap_or("/");
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.into())),
+        None => (target, None),
+    };
+    let http_request = HttpRequest {
+        method: request.method.unwrap_or_default().into(),
+        path: path.into(),
+        query,
+        headers: request
+            .headers
+            .iter()
+            .map(|header| (header.name.into(), String::from_utf8_lossy(header.value).into()))
+            .collect(),
+        body: buffer[head_size..request_size].to_vec(),
+    };
+    consume(stream, request_size)?;
+
+    let response = (handler.0)(&http_request);
+    Ok(HttpStatus::Answered(Cursor::new(response.to_bytes(&http_request.method))))
+}
+
+/// Answers an invalid request with the status code, without calling the handler.
+fn reject(stream: &dyn WsStream, size: usize, status: u16) -> io::Result<HttpStatus> {
+    log::trace!("Invalid HTTP request, answered with {}", status);
+    consume(stream, size)?;
+    Ok(HttpStatus::Answered(Cursor::new(HttpResponse::new(status).to_bytes(""))))
+}
+
+/// Reads the bytes already peeked from the stream.
+/// Otherwise, closing the connection with unread data would reset it, losing the response.
+fn consume(stream: &dyn WsStream, size: usize) -> io::Result<()> {
+    io::copy(&mut stream.take(size as u64), &mut io::sink()).map(|_| ())
+}
+
+/// Writes the response into the stream.
+/// Returns `true` when it has been completely written.
+pub(super) fn write_response(
+    stream: &dyn WsStream,
+    response: &mut Cursor<Vec<u8>>,
+) -> io::Result<bool> {
+    loop {
+        let position = response.position() as usize;
+        let pending = &response.get_ref()[position..];
+        if pending.is_empty() {
+            break Ok(true)
+        }
+        match stream.write(pending) {
+            Ok(0) => break Err(ErrorKind::WriteZero.into()),
+            Ok(size) => response.set_position((position + size) as u64),
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => break Ok(false),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => break Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_bytes() {
+        let response = HttpResponse::new(200)
+            .with_header("Content-Type", "text/plain")
+            .with_header("Invalid\r\n", "header")
+            .with_body("ok");
+
+        let expected = "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+            Content-Length: 2\r\nConnection: close\r\n\r\n";
+        assert_eq!(response.to_bytes("GET"), format!("{expected}ok").as_bytes());
+        assert_eq!(response.to_bytes("HEAD"), expected.as_bytes());
+    }
+
+    #[test]
+    fn invalid_status() {
+        let bytes = HttpResponse::new(1000).to_bytes("GET");
+        assert!(bytes.starts_with(b"HTTP/1.1 500 Internal Server Error\r\n"));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::network::adapter::{MessageKind};
+
+use super::{invalid_data};
+use super::deflate::{Inflater};
+
+use tungstenite::protocol::frame::{FrameHeader};
+use tungstenite::protocol::frame::coding::{OpCode, Data};
+
+use std::collections::{VecDeque};
+use std::io::{self, Read, Cursor};
+
+/// End of the HTTP handshake, the frames start after it.
+const HANDSHAKE_END: &[u8] = b"\r\n\r\n";
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    data.iter_mut().enumerate().for_each(|(index, byte)| *byte ^= mask[index % 4]);
+}
+
+/// Reads the stream of a connection processing its data frames before the websocket reads them:
+/// the compressed messages are inflated and, if the fragments are received as they arrive,
+/// each fragment is read as an entire binary message.
+/// The bytes of the HTTP handshake and the rest of frames are read as they are.
+pub(super) struct FrameReader {
+    inflater: Option<Inflater>,
+    max_message_size: usize,
+
+    /// Kind of the fragments read and whether they end their message,
+    /// until the websocket returns them.
+    fragments: Option<VecDeque<(MessageKind, bool)>>,
+
+    /// Bytes of the handshake end already read, the frames start once it is complete.
+    handshake_end: usize,
+
+    /// Read bytes that do not complete a frame yet.
+    input: Vec<u8>,
+
+    /// Processed bytes pending to be read by the websocket.
+    output: Vec<u8>,
+    output_position: usize,
+
+    /// Header of the first frame of the message being processed,
+    /// and its compressed data if it is inflated entirely.
+    message: Option<(FrameHeader, Vec<u8>)>,
+}
+
+impl FrameReader {
+    pub fn new(inflate: bool, fragments: bool, max_message_size: Option<usize>) -> Self {
+        FrameReader {
+            inflater: inflate.then(Inflater::new),
+            max_message_size: max_message_size.unwrap_or(usize::MAX),
+            fragments: fragments.then(VecDeque::new),
+            handshake_end: 0,
+            input: Vec::new(),
+            output: Vec::new(),
+            output_position: 0,
+            message: None,
+        }
+    }
+
+    /// Returns the kind of the next fragment returned by the websocket
+    /// and whether it ends its message, if the fragments are read as they arrive.
+    pub fn next_fragment(&mut self) -> Option<(MessageKind, bool)> {
+        self.fragments.as_mut()?.pop_front()
+    }
+
+    pub fn read(&mut self, mut stream: impl Read, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.output_position < self.output.len() {
+                let pending = &self.output[self.output_position..];
+                let size = pending.len().min(buf.len());
+                buf[..size].copy_from_slice(&pending[..size]);
+                self.output_position += size;
+                if self.output_position == self.output.len() {
+                    self.output.clear();
+                    self.output_position = 0;
+                }
+                return Ok(size)
+            }
+
+            let mut chunk = [0; 4096];
+            let size = stream.read(&mut chunk)?;
+            if size == 0 {
+                return Ok(0)
+            }
+            self.process(&chunk[..size])?;
+        }
+    }
+
+    fn process(&mut self, mut data: &[u8]) -> io::Result<()> {
+        if self.handshake_end < HANDSHAKE_END.len() {
+            let handshake_size = data.iter().position(|&byte| {
+                self.handshake_end = match byte == HANDSHAKE_END[self.handshake_end] {
+                    true => self.handshake_end + 1,
+                    false => (byte == HANDSHAKE_END[0]) as usize,
+                };
+                self.handshake_end == HANDSHAKE_END.len()
+            });
+            let handshake_size = handshake_size.map(|position| position + 1).unwrap_or(data.len());
+            self.output.extend_from_slice(&data[..handshake_size]);
+            data = &data[handshake_size..];
+        }
+
+        self.input.extend_from_slice(data);
+        let input = std::mem::take(&mut self.input);
+        let mut consumed = 0;
+        while let Some(size) = self.process_frame(&input[consumed..])? {
+            consumed += size;
+        }
+        self.input = input;
+        self.input.drain(..consumed);
+        Ok(())
+    }
+
+    /// Processes the frame at the beginning of the input.
+    /// Returns its size, or `None` if the input does not contain the entire frame.
+    fn process_frame(&mut self, input: &[u8]) -> io::Result<Option<usize>> {
+        let mut cursor = Cursor::new(input);
+        let (header, length) = match FrameHeader::parse(&mut cursor).map_err(invalid_data)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        };
+        if length > self.max_message_size as u64 {
+            return Err(invalid_data("Frame too big"))
+        }
+        let header_size = cursor.position() as usize;
+        let frame_size = header_size + length as usize;
+        if input.len() < frame_size {
+            return Ok(None)
+        }
+        let frame = &input[..frame_size];
+
+        let starts_message = matches!(header.opcode, OpCode::Data(Data::Text | Data::Binary))
+            && (self.fragments.is_some() || header.rsv1 && self.inflater.is_some());
+        let continues_message =
+            header.opcode == OpCode::Data(Data::Continue) && self.message.is_some();
+        if !starts_message && !continues_message {
+            // Control frames or data frames read as they are.
+            self.output.extend_from_slice(frame);
+            return Ok(Some(frame_size))
+        }
+
+        let mut payload = frame[header_size..].to_vec();
+        if let Some(mask) = header.mask {
+            apply_mask(&mut payload, mask);
+        }
+        match &mut self.message {
+            Some(_) if starts_message => return Err(invalid_data("Unexpected message start")),
+            Some(_) => (),
+            None => self.message = Some((header.clone(), Vec::new())),
+        }
+
+        let (first_header, data) = self.message.as_mut().unwrap();
+        let first_header = first_header.clone();
+        if let Some(fragments) = &mut self.fragments {
+            let kind = match first_header.opcode {
+                OpCode::Data(Data::Text) => MessageKind::Text,
+                _ => MessageKind::Binary,
+            };
+            fragments.push_back((kind, header.is_final));
+            let fragment = match (&mut self.inflater, first_header.rsv1) {
+                (Some(inflater), true) => {
+                    inflater.inflate(payload, header.is_final, self.max_message_size)?
+                }
+                _ => payload,
+            };
+            // The text of a fragment can be incomplete, so it is read as binary.
+            let header = FrameHeader { opcode: OpCode::Data(Data::Binary), ..header };
+            self.write_frame(header, fragment)?;
+        }
+        else {
+            data.extend_from_slice(&payload);
+            if data.len() > self.max_message_size {
+                return Err(invalid_data("Compressed message too big"))
+            }
+            if header.is_final {
+                let data = std::mem::take(data);
+                let inflater = self.inflater.as_mut().unwrap();
+                let message = inflater.inflate(data, true, self.max_message_size)?;
+                self.write_frame(first_header, message)?;
+            }
+        }
+        if header.is_final {
+            self.message = None;
+        }
+        Ok(Some(frame_size))
+    }
+
+    /// Writes an entire and uncompressed frame with the `data`, masked as the given `header`.
+    fn write_frame(&mut self, header: FrameHeader, mut data: Vec<u8>) -> io::Result<()> {
+        let header = FrameHeader { is_final: true, rsv1: false, ..header };
+        header.format(data.len() as u64, &mut self.output).map_err(invalid_data)?;
+        if let Some(mask) = header.mask {
+            apply_mask(&mut data, mask);
+        }
+        self.output.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::deflate::{Deflater};
+
+    use tungstenite::protocol::frame::coding::{Control};
+
+    const HANDSHAKE: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+    const MASK: [u8; 4] = [1, 2, 3, 4];
+
+    fn frame(opcode: OpCode, is_final: bool, rsv1: bool, payload: &[u8]) -> Vec<u8> {
+        let header = FrameHeader { is_final, rsv1, opcode, mask: Some(MASK), ..Default::default() };
+        let mut bytes = Vec::new();
+        header.format(payload.len() as u64, &mut bytes).unwrap();
+        let mut payload = payload.to_vec();
+        apply_mask(&mut payload, MASK);
+        bytes.extend(payload);
+        bytes
+    }
+
+    /// Reads the input received in small chunks with a small buffer.
+    fn read_all(reader: &mut FrameReader, input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        let mut buffer = [0; 5];
+        for mut chunk in input.chunks(7) {
+            loop {
+                match reader.read(&mut chunk, &mut buffer)? {
+                    0 => break,
+                    size => output.extend_from_slice(&buffer[..size]),
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Parses the frames read after the handshake, with their payload unmasked.
+    fn parse_frames(output: &[u8]) -> Vec<(FrameHeader, Vec<u8>)> {
+        assert!(output.starts_with(HANDSHAKE));
+        let mut cursor = Cursor::new(&output[HANDSHAKE.len()..]);
+        let mut frames = Vec::new();
+        while let Some((header, length)) = FrameHeader::parse(&mut cursor).unwrap() {
+            let start = cursor.position() as usize;
+            let end = start + length as usize;
+            let mut payload = cursor.get_ref()[start..end].to_vec();
+            apply_mask(&mut payload, header.mask.unwrap());
+            frames.push((header, payload));
+            cursor.set_position(end as u64);
+        }
+        assert_eq!(cursor.position() as usize, cursor.get_ref().len());
+        frames
+    }
+
+    #[test]
+    fn frames_read_as_they_are() {
+        let input = [
+            HANDSHAKE,
+            &frame(OpCode::Data(Data::Text), false, false, b"uncom"),
+            &frame(OpCode::Control(Control::Ping), true, false, b"ping"),
+            &frame(OpCode::Data(Data::Continue), true, false, b"pressed"),
+        ]
+        .concat();
+        let mut reader = FrameReader::new(true, false, None);
+        assert_eq!(read_all(&mut reader, &input).unwrap(), input);
+        assert_eq!(reader.next_fragment(), None);
+    }
+
+    #[test]
+    fn inflate_fragmented_message() {
+        let text = "compressed ".repeat(100);
+        let mut deflater = Deflater::new(15, false);
+        let chunks = text.as_bytes().chunks(400).collect::<Vec<_>>();
+        let mut input = HANDSHAKE.to_vec();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let (opcode, rsv1) = match index {
+                0 => (Data::Text, true),
+                _ => (Data::Continue, false),
+            };
+            let last = index == chunks.len() - 1;
+            let payload = deflater.compress_fragment(chunk, last);
+            input.extend(frame(OpCode::Data(opcode), last, rsv1, &payload));
+            if index == 0 {
+                input.extend(frame(OpCode::Control(Control::Ping), true, false, b"ping"));
+            }
+        }
+
+        let mut reader = FrameReader::new(true, false, None);
+        let frames = parse_frames(&read_all(&mut reader, &input).unwrap());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].0.opcode, OpCode::Control(Control::Ping));
+        let (header, payload) = &frames[1];
+        assert_eq!(
+            (header.opcode, header.is_final, header.rsv1),
+            (OpCode::Data(Data::Text), true, false)
+        );
+        assert_eq!(payload, text.as_bytes());
+    }
+
+    #[test]
+    fn read_fragments() {
+        let mut deflater = Deflater::new(15, false);
+        let compressed = deflater.compress_fragment(b"compressed", true);
+        let input = [
+            HANDSHAKE,
+            &frame(OpCode::Data(Data::Text), false, false, b"frag"),
+            &frame(OpCode::Control(Control::Ping), true, false, b"ping"),
+            &frame(OpCode::Data(Data::Continue), true, false, b"ment"),
+            &frame(OpCode::Data(Data::Binary), true, true, &compressed),
+        ]
+        .concat();
+
+        let mut reader = FrameReader::new(true, true, None);
+        let frames = parse_frames(&read_all(&mut reader, &input).unwrap());
+        let frames = frames
+            .iter()
+            .map(|(header, payload)| (header.opcode, header.is_final, header.rsv1, &payload[..]))
+            .collect::<Vec<_>>();
+        let binary = OpCode::Data(Data::Binary);
+        assert_eq!(
+            frames,
+            [
+                (binary, true, false, &b"frag"[..]),
+                (OpCode::Control(Control::Ping), true, false, b"ping"),
+                (binary, true, false, b"ment"),
+                (binary, true, false, b"compressed"),
+            ]
+        );
+        assert_eq!(reader.next_fragment(), Some((MessageKind::Text, false)));
+        assert_eq!(reader.next_fragment(), Some((MessageKind::Text, true)));
+        assert_eq!(reader.next_fragment(), Some((MessageKind::Binary, true)));
+        assert_eq!(reader.next_fragment(), None);
+    }
+
+    #[test]
+    fn unexpected_message_start() {
+        let input = [
+            HANDSHAKE,
+            &frame(OpCode::Data(Data::Binary), false, false, b"first"),
+            &frame(OpCode::Data(Data::Binary), true, false, b"second"),
+        ]
+        .concat();
+        let err = read_all(&mut FrameReader::new(false, true, None), &input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn message_too_big() {
+        let mut deflater = Deflater::new(15, false);
+        let compressed = deflater.compress_fragment(&[0; 1000], true);
+        let input =
+            [HANDSHAKE, &frame(OpCode::Data(Data::Binary), true, true, &compressed)].concat();
+        let err = read_all(&mut FrameReader::new(true, false, Some(999)), &input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let input =
+            [HANDSHAKE, &frame(OpCode::Data(Data::Binary), true, false, &[0; 1000])].concat();
+        let err = read_all(&mut FrameReader::new(false, true, Some(999)), &input).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
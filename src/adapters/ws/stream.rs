@@ -0,0 +1,211 @@
+use crate::network::adapter::{PendingStatus};
+use crate::network::{borrow_socket, set_socket_options, inet_addr, SocketOption};
+use crate::adapters::tcp::{self, TcpKeepalive};
+
+use mio::event::{Source};
+use mio::net::{TcpStream, TcpListener};
+#[cfg(unix)]
+use mio::net::{UnixStream, UnixListener};
+
+use socket2::{Socket};
+
+use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
+use std::io::{self, Read, Write};
+use std::mem::{ManuallyDrop, MaybeUninit};
+
+/// Address of the endpoints over Unix domain sockets, which have no IP address.
+pub(super) const UNIX_SOCKET_ADDR: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+/// Stream that transports a WebSocket connection, e.g. a TCP or a Unix domain socket stream.
+/// The handshakes and the protocol are performed over it regardless of its kind.
+/// It is not public, so other streams (e.g. TLS) can not be plugged in by the user.
+pub(super) trait WsStream: Send + Sync {
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize>;
+    fn write(&self, buf: &[u8]) -> io::Result<usize>;
+    fn flush(&self) -> io::Result<()>;
+
+    /// Checks if the connection of the stream has been established.
+    fn connect_status(&self) -> PendingStatus;
+
+    fn source(&mut self) -> &mut dyn Source;
+    fn socket(&self) -> ManuallyDrop<Socket>;
+
+    /// Only the streams over TCP support keepalive.
+    fn set_keepalive(&self, _keepalive: &TcpKeepalive) {}
+
+    /// Reads the data without removing it from the stream.
+    fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // The socket only writes initialized bytes into the buffer.
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.socket().peek(buf)
+    }
+}
+
+impl io::Read for &dyn WsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        WsStream::read(*self, buf)
+    }
+}
+
+impl io::Write for &dyn WsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        WsStream::write(*self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        WsStream::flush(*self)
+    }
+}
+
+impl WsStream for TcpStream {
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(&mut &*self, buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+
+    fn connect_status(&self) -> PendingStatus {
+        tcp::check_stream_ready(self)
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+
+    fn socket(&self) -> ManuallyDrop<Socket> {
+        borrow_socket(self)
+    }
+
+    fn set_keepalive(&self, keepalive: &TcpKeepalive) {
+        tcp::set_keepalive(self, keepalive)
+    }
+}
+
+#[cfg(unix)]
+impl WsStream for UnixStream {
+    fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(&mut &*self, buf)
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        Write::write(&mut &*self, buf)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Write::flush(&mut &*self)
+    }
+
+    fn connect_status(&self) -> PendingStatus {
+        // The Unix domain streams are always adopted already connected.
+        match self.take_error() {
+            Ok(None) => PendingStatus::Ready,
+            _ => PendingStatus::Disconnected,
+        }
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+
+    fn socket(&self) -> ManuallyDrop<Socket> {
+        borrow_socket(self)
+    }
+}
+
+/// Listener of the streams of WebSocket connections.
+pub(super) trait WsListener: Send + Sync {
+    /// Accepts a stream, returning it along with its peer address.
+    fn accept(&self) -> io::Result<(Box<dyn WsStream>, SocketAddr)>;
+
+    fn source(&mut self) -> &mut dyn Source;
+    fn socket(&self) -> ManuallyDrop<Socket>;
+}
+
+impl WsListener for TcpListener {
+    fn accept(&self) -> io::Result<(Box<dyn WsStream>, SocketAddr)> {
+        let (stream, addr) = TcpListener::accept(self)?;
+        Ok((Box::new(stream), addr))
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+
+    fn socket(&self) -> ManuallyDrop<Socket> {
+        borrow_socket(self)
+    }
+}
+
+#[cfg(unix)]
+impl WsListener for UnixListener {
+    fn accept(&self) -> io::Result<(Box<dyn WsStream>, SocketAddr)> {
+        let (stream, _) = UnixListener::accept(self)?;
+        Ok((Box::new(stream), UNIX_SOCKET_ADDR))
+    }
+
+    fn source(&mut self) -> &mut dyn Source {
+        self
+    }
+
+    fn socket(&self) -> ManuallyDrop<Socket> {
+        borrow_socket(self)
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "Unix domain sockets are not supported")
+}
+
+/// Prepares a socket created outside the network, setting the non-blocking mode and the
+/// `options`.
+/// Returns `None` as address if it is a Unix domain socket.
+fn adopt_socket(socket: &Socket, options: &[SocketOption]) -> io::Result<Option<SocketAddr>> {
+    socket.set_nonblocking(true)?;
+    set_socket_options(socket, options)?;
+    let local_addr = socket.local_addr()?;
+    match local_addr.is_unix() {
+        true => Ok(None),
+        false => inet_addr(local_addr).map(Some),
+    }
+}
+
+/// Converts a connected socket created outside the network into a stream.
+/// Returns the stream along with its local and peer addresses.
+pub(super) fn adopt_stream(
+    socket: Socket,
+    options: &[SocketOption],
+) -> io::Result<(Box<dyn WsStream>, SocketAddr, SocketAddr)> {
+    match adopt_socket(&socket, options)? {
+        Some(local_addr) => {
+            let peer_addr = inet_addr(socket.peer_addr()?)?;
+            Ok((Box::new(TcpStream::from_std(socket.into())), local_addr, peer_addr))
+        }
+        #[cfg(unix)]
+        None => {
+            let stream = UnixStream::from_std(socket.into());
+            Ok((Box::new(stream), UNIX_SOCKET_ADDR, UNIX_SOCKET_ADDR))
+        }
+        #[cfg(not(unix))]
+        None => Err(unix_unsupported()),
+    }
+}
+
+/// Converts a listening socket created outside the network into a listener.
+/// Returns the listener along with its local address.
+pub(super) fn adopt_listener(socket: Socket) -> io::Result<(Box<dyn WsListener>, SocketAddr)> {
+    match adopt_socket(&socket, &[])? {
+        Some(local_addr) => Ok((Box::new(TcpListener::from_std(socket.into())), local_addr)),
+        #[cfg(unix)]
+        None => Ok((Box::new(UnixListener::from_std(socket.into())), UNIX_SOCKET_ADDR)),
+        #[cfg(not(unix))]
+        None => Err(unix_unsupported()),
+    }
+}
@@ -1,7 +1,10 @@
-use crossbeam_channel::{self, Sender, Receiver, select};
+use crossbeam_channel::{self, Sender, Receiver, select, SendTimeoutError};
+
+use crate::util::thread::{OTHER_THREAD_ERR};
 
 use std::time::{Instant, Duration};
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
 
 /// As a shortcut, it returns the sender and receiver queue as a tuple.
 ///
@@ -21,15 +24,206 @@ pub fn split<E: Send + 'static>() -> (EventSender<E>, EventReceiver<E>) {
     (event_sender, event_queue)
 }
 
+/// Behaviour of a bounded [`EventReceiver`] when an event is sent and its queue is full.
+/// See [`EventReceiver::bounded_with()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The sender blocks until there is space in the queue.
+    /// It propagates the backpressure to the producer.
+    Block,
+
+    /// The event sent is discarded.
+    DropNewest,
+
+    /// The oldest event in the queue is discarded to make room for the event sent.
+    DropOldest,
+
+    /// The event sent is discarded by [`EventSender::send()`] and returned as a
+    /// [`TrySendError::Full`] error by [`EventSender::try_send()`].
+    Error,
+}
+
+/// Error returned when an event could not be sent into the queue.
+/// The event is returned back inside the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrySendError<E> {
+    /// The queue is full.
+    Full(E),
+
+    /// The [`EventReceiver`] has been dropped.
+    Disconnected(E),
+}
+
+impl<E> TrySendError<E> {
+    /// Returns the event that could not be sent.
+    pub fn into_inner(self) -> E {
+        match self {
+            TrySendError::Full(event) => event,
+            TrySendError::Disconnected(event) => event,
+        }
+    }
+}
+
+impl<E> From<crossbeam_channel::TrySendError<E>> for TrySendError<E> {
+    fn from(error: crossbeam_channel::TrySendError<E>) -> Self {
+        match error {
+            crossbeam_channel::TrySendError::Full(event) => TrySendError::Full(event),
+            crossbeam_channel::TrySendError::Disconnected(event) => TrySendError::Disconnected(event),
+        }
+    }
+}
+
+impl<E> From<SendTimeoutError<E>> for TrySendError<E> {
+    fn from(error: SendTimeoutError<E>) -> Self {
+        match error {
+            SendTimeoutError::Timeout(event) => TrySendError::Full(event),
+            SendTimeoutError::Disconnected(event) => TrySendError::Disconnected(event),
+        }
+    }
+}
+
+// Sending side of a queue, in charge of applying the overflow policy when it is full.
+struct QueueSender<E> {
+    sender: Sender<E>,
+    policy: OverflowPolicy,
+    // Only used by `OverflowPolicy::DropOldest` to discard events from the sender side.
+    evictor: Option<Receiver<E>>,
+}
+
+impl<E> QueueSender<E> {
+    fn new(capacity: Option<usize>, policy: OverflowPolicy) -> (Self, Receiver<E>) {
+        let (sender, receiver) = match capacity {
+            Some(capacity) => crossbeam_channel::bounded(capacity),
+            None => crossbeam_channel::unbounded(),
+        };
+        let evictor = match policy {
+            OverflowPolicy::DropOldest => Some(receiver.clone()),
+            _ => None,
+        };
+        (QueueSender { sender, policy, evictor }, receiver)
+    }
+
+    fn send(&self, event: E) {
+        match self.policy {
+            OverflowPolicy::Block => self.sender.send(event).ok(),
+            _ => self.try_send(event).ok(),
+        };
+    }
+
+    fn try_send(&self, mut event: E) -> Result<(), TrySendError<E>> {
+        match &self.evictor {
+            Some(evictor) => loop {
+                match self.sender.try_send(event) {
+                    Ok(()) => break Ok(()),
+                    Err(crossbeam_channel::TrySendError::Full(rejected)) => {
+                        evictor.try_recv().ok();
+                        event = rejected;
+                    }
+                    Err(error) => break Err(error.into()),
+                }
+            },
+            None => Ok(self.sender.try_send(event)?),
+        }
+    }
+
+    fn send_timeout(&self, event: E, timeout: Duration) -> Result<(), TrySendError<E>> {
+        match self.policy {
+            OverflowPolicy::Block => Ok(self.sender.send_timeout(event, timeout)?),
+            _ => self.try_send(event),
+        }
+    }
+}
+
+impl<E> Clone for QueueSender<E> {
+    fn clone(&self) -> Self {
+        QueueSender {
+            sender: self.sender.clone(),
+            policy: self.policy,
+            evictor: self.evictor.clone(),
+        }
+    }
+}
+
 /// An ID that represents a timer scheduled.
-/// It can be used to cancel the event.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct TimerId(Instant);
-
-// Internal enum to enqueue different timer commands in a single queue
-enum TimerCommand<E> {
-    Create(E),
-    Cancel,
+/// It can be used to cancel or reschedule the timer.
+/// Each scheduled timer has its own id, even if several timers expire at the same instant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+// Internal representation of the event a timer generates when it expires.
+enum TimerEvent<E> {
+    Once(E),
+    Interval(Box<dyn FnMut() -> E + Send>, Duration),
+}
+
+// Timers shared by the senders and the receiver.
+// The schedule is sorted by expiration, the id resolves the timers that expire at the same instant.
+struct Timers<E> {
+    last_id: u64,
+    schedule: BTreeMap<(Instant, TimerId), TimerEvent<E>>,
+    deadlines: HashMap<TimerId, Instant>,
+}
+
+impl<E> Default for Timers<E> {
+    fn default() -> Self {
+        Self { last_id: 0, schedule: BTreeMap::new(), deadlines: HashMap::new() }
+    }
+}
+
+impl<E> Timers<E> {
+    fn insert(&mut self, when: Instant, event: TimerEvent<E>) -> TimerId {
+        self.last_id += 1;
+        let id = TimerId(self.last_id);
+        self.schedule.insert((when, id), event);
+        self.deadlines.insert(id, when);
+        id
+    }
+
+    fn remove(&mut self, id: TimerId) -> bool {
+        match self.deadlines.remove(&id) {
+            Some(when) => self.schedule.remove(&(when, id)).is_some(),
+            None => false,
+        }
+    }
+
+    fn reschedule(&mut self, id: TimerId, when: Instant) -> bool {
+        match self.deadlines.get_mut(&id) {
+            Some(deadline) => {
+                let event = self.schedule.remove(&(*deadline, id)).unwrap();
+                self.schedule.insert((when, id), event);
+                *deadline = when;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.schedule.keys().next().map(|(when, _)| *when)
+    }
+
+    /// Extracts the next expired event.
+    /// Interval timers are scheduled again from its previous deadline in order to avoid drift.
+    fn pop_expired(&mut self, now: Instant) -> Option<E> {
+        let (when, id) = *self.schedule.keys().next()?;
+        if when > now {
+            return None;
+        }
+
+        match self.schedule.remove(&(when, id)).unwrap() {
+            TimerEvent::Once(event) => {
+                self.deadlines.remove(&id);
+                Some(event)
+            }
+            TimerEvent::Interval(mut generate, period) => {
+                let event = generate();
+                let next = when + period;
+                self.schedule.insert((next, id), TimerEvent::Interval(generate, period));
+                self.deadlines.insert(id, next);
+                Some(event)
+            }
+        }
+    }
 }
 
 /// A generic and synchronized queue where the user can send and receive events.
@@ -39,32 +233,76 @@ enum TimerCommand<E> {
 pub struct EventReceiver<E> {
     event_sender: EventSender<E>, // Should be before receiver in order to drop first.
     receiver: Receiver<E>,
-    timer_receiver: Receiver<(Instant, TimerCommand<E>)>,
+    timer_receiver: Receiver<()>,
     priority_receiver: Receiver<E>,
-    timers: BTreeMap<Instant, E>,
+    timers: Arc<Mutex<Timers<E>>>,
 }
 
 impl<E> Default for EventReceiver<E>
 where E: Send + 'static
 {
     /// Creates a new event queue for generic incoming events.
+    /// The queue is unbounded, see [`EventReceiver::bounded()`] to limit its size.
     fn default() -> Self {
-        let (sender, receiver) = crossbeam_channel::unbounded();
-        let (timer_sender, timer_receiver) = crossbeam_channel::unbounded();
-        let (priority_sender, priority_receiver) = crossbeam_channel::unbounded();
+        Self::new(None, OverflowPolicy::Block)
+    }
+}
+
+impl<E> EventReceiver<E>
+where E: Send + 'static
+{
+    fn new(capacity: Option<usize>, policy: OverflowPolicy) -> Self {
+        let (sender, receiver) = QueueSender::new(capacity, policy);
+        let (priority_sender, priority_receiver) = QueueSender::new(capacity, policy);
+        // Only one notification is needed to wake up the receiver.
+        let (timer_sender, timer_receiver) = crossbeam_channel::bounded(1);
+        let timers = Arc::new(Mutex::new(Timers::default()));
         EventReceiver {
-            event_sender: EventSender::new(sender, timer_sender, priority_sender),
+            event_sender: EventSender::new(sender, timer_sender, priority_sender, timers.clone()),
             receiver,
             timer_receiver,
             priority_receiver,
-            timers: BTreeMap::new(),
+            timers,
         }
     }
-}
 
-impl<E> EventReceiver<E>
-where E: Send + 'static
-{
+    /// Creates a new event queue that can hold at most `capacity` events.
+    /// If the queue is full, the senders will be blocked until the receiver processes some event.
+    /// Equivalent to call [`EventReceiver::bounded_with()`] with [`OverflowPolicy::Block`].
+    ///
+    /// # Panics
+    /// If `capacity` is zero, see [`EventReceiver::bounded_with()`].
+    pub fn bounded(capacity: usize) -> Self {
+        Self::bounded_with(capacity, OverflowPolicy::Block)
+    }
+
+    /// Creates a new event queue that can hold at most `capacity` events.
+    /// The `policy` specifies what happens when an event is sent and the queue is full.
+    ///
+    /// The standard and priority events have a separated queue each of them with the same
+    /// `capacity`.
+    /// Timed events are not limited by the capacity, they are stored until they expire.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::events::{EventReceiver, OverflowPolicy};
+    ///
+    /// let mut queue = EventReceiver::bounded_with(2, OverflowPolicy::DropOldest);
+    /// queue.sender().send(1);
+    /// queue.sender().send(2);
+    /// queue.sender().send(3);
+    ///
+    /// assert_eq!(queue.try_receive(), Some(2));
+    /// assert_eq!(queue.try_receive(), Some(3));
+    /// ```
+    ///
+    /// # Panics
+    /// If `capacity` is zero, the queue could never store an event to be received later.
+    pub fn bounded_with(capacity: usize, policy: OverflowPolicy) -> Self {
+        assert!(capacity > 0, "The capacity of a bounded queue must be greater than zero");
+        Self::new(Some(capacity), policy)
+    }
+
     /// Returns the internal sender reference to this queue.
     /// This reference can be safety cloned and shared to other threads
     /// in order to get several senders to the same queue.
@@ -72,43 +310,44 @@ where E: Send + 'static
         &self.event_sender
     }
 
-    fn enque_timers(&mut self) {
-        for timer in self.timer_receiver.try_iter() {
-            match timer.1 {
-                TimerCommand::Create(e) => self.timers.insert(timer.0, e),
-                TimerCommand::Cancel => self.timers.remove(&timer.0),
-            };
+    /// Returns the next expired timer event or the instant when the next timer expires.
+    fn next_timer(&mut self) -> Result<E, Option<Instant>> {
+        // The notifications are only used to wake up the receiver.
+        // Since the timers are going to be checked, the pending ones can be discarded.
+        for _ in self.timer_receiver.try_iter() {}
+
+        let mut timers = self.timers.lock().expect(OTHER_THREAD_ERR);
+        match timers.pop_expired(Instant::now()) {
+            Some(event) => Ok(event),
+            None => Err(timers.next_deadline()),
         }
     }
 
     /// Blocks the current thread until an event is received by this queue.
     pub fn receive(&mut self) -> E {
-        self.enque_timers();
         // Since [`EventReceiver`] always has a sender attribute,
         // any call to [`receive()`] always has a living sender in that time
         // and the channel never can be considered disconnected.
-        if !self.priority_receiver.is_empty() {
-            self.priority_receiver.recv().unwrap()
-        }
-        else if self.timers.is_empty() {
-            select! {
-                recv(self.receiver) -> event => event.unwrap(),
-                recv(self.priority_receiver) -> event => event.unwrap(),
+        loop {
+            if let Ok(priority_event) = self.priority_receiver.try_recv() {
+                return priority_event;
             }
-        }
-        else {
-            let next_instant = *self.timers.iter().next().unwrap().0;
-            if next_instant <= Instant::now() {
-                self.timers.remove(&next_instant).unwrap()
-            }
-            else {
-                select! {
-                    recv(self.receiver) -> event => event.unwrap(),
-                    recv(self.priority_receiver) -> event => event.unwrap(),
-                    recv(crossbeam_channel::at(next_instant)) -> _ => {
-                        self.timers.remove(&next_instant).unwrap()
-                    }
-                }
+
+            let next_instant = match self.next_timer() {
+                Ok(timed_event) => return timed_event,
+                Err(next_instant) => next_instant,
+            };
+
+            let timeout = match next_instant {
+                Some(instant) => crossbeam_channel::at(instant),
+                None => crossbeam_channel::never(),
+            };
+
+            select! {
+                recv(self.receiver) -> event => return event.unwrap(),
+                recv(self.priority_receiver) -> event => return event.unwrap(),
+                recv(self.timer_receiver) -> _ => continue, // The timers were modified
+                recv(timeout) -> _ => continue,
             }
         }
     }
@@ -116,32 +355,28 @@ where E: Send + 'static
     /// Blocks the current thread until an event is received by this queue or timeout is exceeded.
     /// If timeout is reached a None is returned, otherwise the event is returned.
     pub fn receive_timeout(&mut self, timeout: Duration) -> Option<E> {
-        self.enque_timers();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Ok(priority_event) = self.priority_receiver.try_recv() {
+                return Some(priority_event);
+            }
+
+            let next_instant = match self.next_timer() {
+                Ok(timed_event) => return Some(timed_event),
+                Err(next_instant) => next_instant,
+            };
+
+            let timer_timeout = match next_instant {
+                Some(instant) if instant < deadline => crossbeam_channel::at(instant),
+                _ => crossbeam_channel::never(),
+            };
 
-        if !self.priority_receiver.is_empty() {
-            Some(self.priority_receiver.recv().unwrap())
-        }
-        else if self.timers.is_empty() {
             select! {
-                recv(self.receiver) -> event => Some(event.unwrap()),
-                recv(self.priority_receiver) -> event => Some(event.unwrap()),
-                default(timeout) => None
-            }
-        }
-        else {
-            let next_instant = *self.timers.iter().next().unwrap().0;
-            if next_instant <= Instant::now() {
-                self.timers.remove(&next_instant)
-            }
-            else {
-                select! {
-                    recv(self.receiver) -> event => Some(event.unwrap()),
-                    recv(self.priority_receiver) -> event => Some(event.unwrap()),
-                    recv(crossbeam_channel::at(next_instant)) -> _ => {
-                        self.timers.remove(&next_instant)
-                    }
-                    default(timeout) => None
-                }
+                recv(self.receiver) -> event => return Some(event.unwrap()),
+                recv(self.priority_receiver) -> event => return Some(event.unwrap()),
+                recv(self.timer_receiver) -> _ => continue, // The timers were modified
+                recv(timer_timeout) -> _ => continue,
+                default(deadline.saturating_duration_since(Instant::now())) => return None,
             }
         }
     }
@@ -149,54 +384,65 @@ where E: Send + 'static
     /// Attempts to receive an event without blocking.
     /// Returns Some(E) if an event was received by this queue, otherwise returns None.
     pub fn try_receive(&mut self) -> Option<E> {
-        self.enque_timers();
-
         if let Ok(priority_event) = self.priority_receiver.try_recv() {
             return Some(priority_event);
         }
-        else if let Some(next_instant) = self.timers.iter().next() {
-            if *next_instant.0 <= Instant::now() {
-                let instant = *next_instant.0;
-                return self.timers.remove(&instant);
-            }
-        }
-        else if let Ok(event) = self.receiver.try_recv() {
-            return Some(event);
+
+        if let Ok(timed_event) = self.next_timer() {
+            return Some(timed_event);
         }
 
-        None
+        self.receiver.try_recv().ok()
     }
 }
 
 /// Struct used to send events into a [`EventReceiver`].
 /// This type can only be generated by the receiver `EventReceiver`.
 pub struct EventSender<E> {
-    sender: Sender<E>,
-    timer_sender: Sender<(Instant, TimerCommand<E>)>,
-    priority_sender: Sender<E>,
+    sender: QueueSender<E>,
+    timer_sender: Sender<()>,
+    priority_sender: QueueSender<E>,
+    timers: Arc<Mutex<Timers<E>>>,
 }
 
 impl<E> EventSender<E>
 where E: Send + 'static
 {
     fn new(
-        sender: Sender<E>,
-        timer_sender: Sender<(Instant, TimerCommand<E>)>,
-        priority_sender: Sender<E>,
+        sender: QueueSender<E>,
+        timer_sender: Sender<()>,
+        priority_sender: QueueSender<E>,
+        timers: Arc<Mutex<Timers<E>>>,
     ) -> EventSender<E> {
-        EventSender { sender, timer_sender, priority_sender }
+        EventSender { sender, timer_sender, priority_sender, timers }
     }
 
     /// Send instantly an event to the event queue.
+    /// If the queue is bounded and full, the [`OverflowPolicy`] of the queue is applied.
     pub fn send(&self, event: E) {
-        self.sender.send(event).ok();
+        self.sender.send(event);
+    }
+
+    /// Attempts to send an event to the event queue without blocking.
+    /// If the queue is bounded and full, the event is returned as a [`TrySendError::Full`] error,
+    /// except for the [`OverflowPolicy::DropOldest`] policy,
+    /// where the oldest event is discarded instead.
+    pub fn try_send(&self, event: E) -> Result<(), TrySendError<E>> {
+        self.sender.try_send(event)
+    }
+
+    /// Similar to [`EventSender::send()`] but in a queue with the [`OverflowPolicy::Block`] policy
+    /// it waits at most `timeout` for space in the queue.
+    /// For the rest of policies it behaves like [`EventSender::try_send()`].
+    pub fn send_timeout(&self, event: E, timeout: Duration) -> Result<(), TrySendError<E>> {
+        self.sender.send_timeout(event, timeout)
     }
 
     /// Send instantly an event that would be process before any other event sent
     /// by the [`EventSender::send()`] method.
     /// Successive calls to send_with_priority will maintain the order of arrival.
     pub fn send_with_priority(&self, event: E) {
-        self.priority_sender.send(event).ok();
+        self.priority_sender.send(event);
     }
 
     /// Send a timed event to the [`EventReceiver`].
@@ -204,15 +450,54 @@ where E: Send + 'static
     /// If the [`EventSender`] is dropped, the event will be generated as well unless
     /// [`EventSender::cancel_timer()`] be called.
     pub fn send_with_timer(&self, event: E, duration: Duration) -> TimerId {
+        self.schedule(Instant::now() + duration, TimerEvent::Once(event))
+    }
+
+    /// Send an event to the [`EventReceiver`] periodically, each `period` of time.
+    /// The first event is sent after the first `period`.
+    /// Each expiration is computed from the previous one and not from the moment the event was
+    /// received, so the schedule does not drift although the receiver processes it late.
+    /// The timer works until [`EventSender::cancel_timer()`] is called.
+    ///
+    /// # Panics
+    /// If `period` is zero, the timer would expire continuously starving the receiver.
+    pub fn send_with_interval(&self, event: E, period: Duration) -> TimerId
+    where E: Clone {
+        assert!(!period.is_zero(), "The period of an interval timer must be greater than zero");
+        let generate = Box::new(move || event.clone());
+        self.schedule(Instant::now() + period, TimerEvent::Interval(generate, period))
+    }
+
+    /// Changes the expiration of a timer already sent to be after `duration` from now.
+    /// In interval timers, the following periods are computed from this new expiration.
+    /// Returns `false` if the timer no longer exists because it was already received
+    /// or cancelled.
+    pub fn reschedule(&self, timer_id: TimerId, duration: Duration) -> bool {
         let when = Instant::now() + duration;
-        self.timer_sender.send((when, TimerCommand::Create(event))).ok();
-        TimerId(when)
+        let rescheduled = self.timers.lock().expect(OTHER_THREAD_ERR).reschedule(timer_id, when);
+        if rescheduled {
+            self.timer_sender.try_send(()).ok();
+        }
+        rescheduled
     }
 
-    /// Remove a timer previously sent by [`EventSender::send_with_timer()`].
+    /// Remove a timer previously sent by [`EventSender::send_with_timer()`] or
+    /// [`EventSender::send_with_interval()`].
     /// The timer will not be receive by the [`EventReceiver`].
-    pub fn cancel_timer(&self, timer_id: TimerId) {
-        self.timer_sender.send((timer_id.0, TimerCommand::Cancel)).ok();
+    /// Returns `true` if the timer was removed, or `false` if it no longer exists because it was
+    /// already received or cancelled.
+    pub fn cancel_timer(&self, timer_id: TimerId) -> bool {
+        let removed = self.timers.lock().expect(OTHER_THREAD_ERR).remove(timer_id);
+        if removed {
+            self.timer_sender.try_send(()).ok();
+        }
+        removed
+    }
+
+    fn schedule(&self, when: Instant, event: TimerEvent<E>) -> TimerId {
+        let id = self.timers.lock().expect(OTHER_THREAD_ERR).insert(when, event);
+        self.timer_sender.try_send(()).ok();
+        id
     }
 }
 
@@ -224,6 +509,7 @@ where E: Send + 'static
             self.sender.clone(),
             self.timer_sender.clone(),
             self.priority_sender.clone(),
+            self.timers.clone(),
         )
     }
 }
@@ -389,11 +675,203 @@ mod tests {
     fn cancel_timers() {
         let mut queue = EventReceiver::default();
         let id = queue.sender().send_with_timer("timed", *TIMER_TIME);
-        queue.sender().cancel_timer(id);
+        assert!(queue.sender().cancel_timer(id));
 
         std::thread::sleep(*TIMEOUT);
         // The timed event has been received at this point, but was cancelled.
 
         assert_eq!(queue.try_receive(), None);
     }
+
+    #[test]
+    fn cancel_timer_status() {
+        let queue = EventReceiver::default();
+        let id = queue.sender().send_with_timer("timed", *TIMER_TIME);
+        assert!(queue.sender().cancel_timer(id));
+        assert!(!queue.sender().cancel_timer(id));
+    }
+
+    #[test]
+    fn cancel_timer_at_same_instant() {
+        let mut queue = EventReceiver::default();
+        let first = queue.sender().send_with_timer("first", *ZERO_MS);
+        let second = queue.sender().send_with_timer("second", *ZERO_MS);
+        assert_ne!(first, second);
+
+        assert!(queue.sender().cancel_timer(first));
+        assert_eq!(queue.try_receive().unwrap(), "second");
+        assert_eq!(queue.try_receive(), None);
+        assert!(!queue.sender().cancel_timer(second));
+    }
+
+    #[test]
+    fn timer_ids_unique() {
+        let queue = EventReceiver::default();
+        let sender = queue.sender().clone();
+        let ids = (0..100)
+            .map(|_| sender.send_with_timer((), *TIMER_TIME))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids.len(), 100);
+    }
+
+    #[test]
+    fn interval_timer() {
+        let mut queue = EventReceiver::default();
+        let id = queue.sender().send_with_interval("tick", *TIMER_TIME);
+
+        assert_eq!(queue.try_receive(), None);
+        for _ in 0..3 {
+            assert_eq!(queue.receive_timeout(*TIMEOUT).unwrap(), "tick");
+        }
+
+        assert!(queue.sender().cancel_timer(id));
+        std::thread::sleep(*TIMER_TIME * 2);
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "greater than zero")]
+    fn interval_timer_zero_period() {
+        let queue = EventReceiver::default();
+        queue.sender().send_with_interval((), Duration::ZERO);
+    }
+
+    #[test]
+    fn interval_timer_without_drift() {
+        let mut queue = EventReceiver::default();
+        let start = Instant::now();
+        queue.sender().send_with_interval((), *TIMER_TIME);
+
+        // Processing late do not delay the next expirations.
+        std::thread::sleep(*TIMER_TIME * 3 + *TIMER_TIME / 2);
+        for _ in 0..3 {
+            assert_eq!(queue.try_receive(), Some(()));
+        }
+        assert_eq!(queue.try_receive(), None);
+
+        queue.receive_timeout(*TIMEOUT).unwrap();
+        assert!(start.elapsed() < *TIMER_TIME * 5);
+    }
+
+    #[test]
+    fn reschedule_timer() {
+        let mut queue = EventReceiver::default();
+        let id = queue.sender().send_with_timer("timed", *TIMEOUT);
+        assert!(queue.sender().reschedule(id, *ZERO_MS));
+        assert_eq!(queue.try_receive().unwrap(), "timed");
+        assert!(!queue.sender().reschedule(id, *ZERO_MS));
+    }
+
+    #[test]
+    fn reschedule_wakes_receiver() {
+        let mut queue = EventReceiver::default();
+        let sender = queue.sender().clone();
+        let id = sender.send_with_timer("timed", *TIMEOUT * 10);
+
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(*TIMER_TIME);
+            assert!(sender.reschedule(id, *ZERO_MS));
+        });
+
+        assert_eq!(queue.receive_timeout(*TIMEOUT).unwrap(), "timed");
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn pending_timer_not_block_standard_events_try_receive() {
+        let mut queue = EventReceiver::default();
+        queue.sender().send_with_timer("timed", *TIMEOUT);
+        queue.sender().send("standard");
+        assert_eq!(queue.try_receive().unwrap(), "standard");
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    fn bounded_block() {
+        let mut queue = EventReceiver::bounded(1);
+        let sender = queue.sender().clone();
+        sender.send("first");
+        assert_eq!(sender.try_send("second"), Err(TrySendError::Full("second")));
+
+        let thread = std::thread::spawn(move || sender.send("second")); // Blocked until receive
+        std::thread::sleep(*TIMER_TIME);
+        assert_eq!(queue.receive_timeout(*TIMEOUT).unwrap(), "first");
+        assert_eq!(queue.receive_timeout(*TIMEOUT).unwrap(), "second");
+        thread.join().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "greater than zero")]
+    fn bounded_zero_capacity() {
+        EventReceiver::<()>::bounded_with(0, OverflowPolicy::DropOldest);
+    }
+
+    #[test]
+    fn bounded_block_timeout() {
+        let queue = EventReceiver::bounded(1);
+        queue.sender().send("first");
+        let result = queue.sender().send_timeout("second", *TIMER_TIME);
+        assert_eq!(result, Err(TrySendError::Full("second")));
+    }
+
+    #[test]
+    fn bounded_drop_newest() {
+        let mut queue = EventReceiver::bounded_with(2, OverflowPolicy::DropNewest);
+        queue.sender().send("first");
+        queue.sender().send("second");
+        queue.sender().send("third");
+        assert_eq!(queue.try_receive().unwrap(), "first");
+        assert_eq!(queue.try_receive().unwrap(), "second");
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    fn bounded_drop_oldest() {
+        let mut queue = EventReceiver::bounded_with(2, OverflowPolicy::DropOldest);
+        queue.sender().send("first");
+        queue.sender().send("second");
+        queue.sender().send("third");
+        assert_eq!(queue.sender().try_send("fourth"), Ok(()));
+        assert_eq!(queue.try_receive().unwrap(), "third");
+        assert_eq!(queue.try_receive().unwrap(), "fourth");
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    fn bounded_error() {
+        let mut queue = EventReceiver::bounded_with(1, OverflowPolicy::Error);
+        assert_eq!(queue.sender().try_send("first"), Ok(()));
+        assert_eq!(queue.sender().try_send("second"), Err(TrySendError::Full("second")));
+        queue.sender().send("third"); // Discarded
+        assert_eq!(queue.try_receive().unwrap(), "first");
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    fn bounded_priority() {
+        let mut queue = EventReceiver::bounded_with(1, OverflowPolicy::DropNewest);
+        queue.sender().send("standard");
+        queue.sender().send_with_priority("priority_first");
+        queue.sender().send_with_priority("priority_second");
+        assert_eq!(queue.try_receive().unwrap(), "priority_first");
+        assert_eq!(queue.try_receive().unwrap(), "standard");
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    #[test]
+    fn bounded_timers_not_limited() {
+        let mut queue = EventReceiver::bounded_with(1, OverflowPolicy::Error);
+        queue.sender().send_with_timer("timed_first", *ZERO_MS);
+        queue.sender().send_with_timer("timed_second", *ZERO_MS);
+        assert_eq!(queue.try_receive().unwrap(), "timed_first");
+        assert_eq!(queue.try_receive().unwrap(), "timed_second");
+    }
+
+    #[test]
+    fn send_to_dropped_queue() {
+        let queue = EventReceiver::bounded(1);
+        let sender = queue.sender().clone();
+        drop(queue);
+        assert_eq!(sender.try_send(()), Err(TrySendError::Disconnected(())));
+    }
 }
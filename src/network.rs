@@ -6,6 +6,12 @@ mod driver;
 mod remote_addr;
 mod transport;
 mod loader;
+mod shard;
+mod socket_option;
+#[cfg(unix)]
+mod handover;
+#[cfg(feature = "async")]
+mod future;
 
 /// Module that specify the pattern to follow to create adapters.
 /// This module is not part of the public API itself,
@@ -13,44 +19,105 @@ mod loader;
 pub mod adapter;
 
 // Reexports
-pub use adapter::{SendStatus};
+pub use adapter::{SendStatus, MessageKind};
 pub use resource_id::{ResourceId, ResourceType};
 pub use endpoint::{Endpoint};
 pub use remote_addr::{RemoteAddr, ToRemoteAddr};
 pub use transport::{Transport, TransportConnect, TransportListen};
-pub use driver::{NetEvent};
-pub use poll::{Readiness};
+pub use driver::{NetEvent, UserData, ResourceInfo, DisconnectReason};
+pub use poll::{Readiness, SourceId};
+pub use shard::{ShardBalance};
+pub use socket_option::{SocketOption, SocketOptionName};
+#[cfg(unix)]
+pub use handover::{MAX_HANDED_LISTENERS};
+pub(crate) use socket_option::{borrow_socket, set_socket_options, adopt_socket, inet_addr};
+#[cfg(feature = "async")]
+pub use future::{ConnectFuture, SendFuture};
+
+/// Internals only exposed to be measured by the benchmarks (`__bench` feature).
+/// This module is not part of the public API.
+#[cfg(feature = "__bench")]
+#[doc(hidden)]
+pub mod internal {
+    pub use super::poll::{Poll, PollRegistry};
+    pub use super::registry::{ResourceRegistry};
+}
 
 use loader::{DriverLoader, ActionControllerList, EventProcessorList};
-use poll::{Poll, PollEvent};
+use poll::{Poll, PollEvent, SourceRegistry};
 
 use strum::{IntoEnumIterator};
 
+use mio::{Interest};
+use mio::event::{Source};
+
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::time::{Duration, Instant};
 use std::io::{self};
+use std::any::{Any};
+use std::sync::{Arc};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixStream};
+#[cfg(feature = "async")]
+use std::task::{Waker};
 
 /// Create a network instance giving its controller and processor.
 pub fn split() -> (NetworkController, NetworkProcessor) {
-    let mut drivers = DriverLoader::default();
+    let (network_controller, mut network_processors) = split_sharded(1, ShardBalance::default());
+    (network_controller, network_processors.remove(0))
+}
+
+/// Create a network instance whose resources are distributed among several `shards`,
+/// giving its controller and a processor by shard.
+/// Each processor has its own internal poll, so the network can be processed by
+/// several threads at the same time (one by processor).
+///
+/// Each new resource (a connection, a listener or an accepted connection) is placed
+/// in a shard chosen by the `balance` strategy, and its [`ResourceId`] encodes that shard
+/// (see [`ResourceId::shard()`]).
+/// All the events of a resource are generated by the processor of its shard.
+/// Note that a listener and the connections it accepts could be in different shards.
+///
+/// The user sources (see [`NetworkController::register_source()`]) are only processed by the
+/// processor of the shard `0`.
+///
+/// # Panics
+/// If `shards` is `0` or greater than [`ResourceId::MAX_SHARDS`].
+pub fn split_sharded(
+    shards: usize,
+    balance: ShardBalance,
+) -> (NetworkController, Vec<NetworkProcessor>) {
+    assert!(
+        shards > 0 && shards <= ResourceId::MAX_SHARDS,
+        "The number of shards must be in the range [1, {}]",
+        ResourceId::MAX_SHARDS,
+    );
+
+    let mut drivers = DriverLoader::new(shards, balance);
     Transport::iter().for_each(|transport| transport.mount_adapter(&mut drivers));
 
-    let (poll, controllers, processors) = drivers.take();
+    let (mut polls, controllers, processors) = drivers.take();
+    let sources = polls[0].create_source_registry();
 
-    let network_controller = NetworkController::new(controllers);
-    let network_processor = NetworkProcessor::new(poll, processors);
+    let network_controller = NetworkController::new(controllers, sources);
+    let processors = Arc::new(processors);
+    let network_processors =
+        polls.into_iter().map(|poll| NetworkProcessor::new(poll, processors.clone())).collect();
 
-    (network_controller, network_processor)
+    (network_controller, network_processors)
 }
 
 /// Shareable instance in charge of control all the connections.
 pub struct NetworkController {
     controllers: ActionControllerList,
+    sources: SourceRegistry,
 }
 
 impl NetworkController {
-    fn new(controllers: ActionControllerList) -> NetworkController {
-        Self { controllers }
+    fn new(controllers: ActionControllerList, sources: SourceRegistry) -> NetworkController {
+        Self { controllers, sources }
     }
 
     /// Creates a connection to the specified address.
@@ -95,6 +162,7 @@ impl NetworkController {
     ///         _ => (),
     ///     }
     ///     NodeEvent::Signal(_) => handler.stop(),
+    ///     NodeEvent::Source(..) => (),
     /// });
     /// ```
     pub fn connect(
@@ -146,6 +214,7 @@ impl NetworkController {
     ///         _ => (),
     ///     }
     ///     NodeEvent::Signal(_) => handler.stop(),
+    ///     NodeEvent::Source(..) => (),
     /// });
     /// ```
     pub fn connect_with(
@@ -260,6 +329,56 @@ impl NetworkController {
         }
     }
 
+    /// Creates a connection to the specified address.
+    /// This function is similar to [`NetworkController::connect()`] but returns a future
+    /// (available through the *async* feature) that resolves once the connection is ready.
+    /// If the connection can not be established, a `ConnectionRefused` error will be returned.
+    ///
+    /// Note that the `Connect` event will be also generated.
+    ///
+    /// The future does not depend on any async runtime. It is woken by the thread that
+    /// processes the network events, so the node must be processing them
+    /// (i.e. [`crate::node::NodeListener::for_each_async()`]) in order to be resolved.
+    ///
+    /// Example
+    /// ```
+    /// use message_io::node::{self};
+    /// use message_io::network::{Transport};
+    ///
+    /// let (handler, listener) = node::split::<()>();
+    /// let (_, addr) = handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    /// let _task = listener.for_each_async(|_| ());
+    ///
+    /// futures::executor::block_on(async {
+    ///     let connection = handler.network().connect_async(Transport::FramedTcp, addr).await;
+    ///     let (endpoint, _) = connection.unwrap();
+    ///     handler.network().send_async(endpoint, &[42]).await;
+    /// });
+    /// handler.stop();
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn connect_async(
+        &self,
+        transport: Transport,
+        addr: impl ToRemoteAddr,
+    ) -> ConnectFuture<'_> {
+        self.connect_async_with(transport.into(), addr)
+    }
+
+    /// Creates a connection to the specified address with custom transport options for transports
+    /// that support it.
+    /// This function is similar to [`NetworkController::connect_with()`] but returns a future
+    /// that resolves once the connection is ready.
+    /// See [`NetworkController::connect_async()`].
+    #[cfg(feature = "async")]
+    pub fn connect_async_with(
+        &self,
+        transport_connect: TransportConnect,
+        addr: impl ToRemoteAddr,
+    ) -> ConnectFuture<'_> {
+        ConnectFuture::new(self, self.connect_with(transport_connect, addr))
+    }
+
     /// Listen messages from specified transport.
     /// The given address will be used as interface and listening port.
     /// If the port can be opened, a [ResourceId] identifying the listener is returned
@@ -295,6 +414,261 @@ impl NetworkController {
         )
     }
 
+    /// Listen messages from specified transport with a group of listeners,
+    /// one by each shard of the network (see [`split_sharded()`]).
+    /// All listeners are bound to the same address with the `SO_REUSEPORT` option
+    /// (only available in unix), so the OS distributes the incoming connections
+    /// (or datagrams in the case of UDP) among them, and each shard processes its own listener
+    /// without any user-space dispatcher.
+    ///
+    /// The ids of the listeners (ordered by shard) are returned along with the local address.
+    /// Each listener can be removed independently with [`NetworkController::remove()`].
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, ShardBalance};
+    ///
+    /// let (controller, processors) = network::split_sharded(4, ShardBalance::RoundRobin);
+    /// let (listener_ids, addr) = controller.listen_group(Transport::Tcp, "127.0.0.1:0").unwrap();
+    /// assert_eq!(listener_ids.len(), processors.len());
+    /// ```
+    pub fn listen_group(
+        &self,
+        transport: Transport,
+        addr: impl ToSocketAddrs,
+    ) -> io::Result<(Vec<ResourceId>, SocketAddr)> {
+        self.listen_group_with(transport.into(), addr)
+    }
+
+    /// Similar to [`NetworkController::listen_group()`] but with custom transport options for
+    /// transports that support it.
+    /// The `SO_REUSEPORT` option is always enabled for the listeners of the group.
+    pub fn listen_group_with(
+        &self,
+        transport_listen: TransportListen,
+        addr: impl ToSocketAddrs,
+    ) -> io::Result<(Vec<ResourceId>, SocketAddr)> {
+        let addr = addr.to_socket_addrs().unwrap().next().unwrap();
+        let transport_listen = transport_listen.with_reuse_port();
+        self.controllers[transport_listen.id() as usize]
+            .listen_group_with(transport_listen, addr)
+            .map(|(resource_ids, addr)| {
+                log::trace!("Listening at {} by {:?}", addr, resource_ids);
+                (resource_ids, addr)
+            })
+    }
+
+    /// Register an already listening socket created outside the network,
+    /// e.g. a socket inherited through systemd socket activation (`LISTEN_FDS`)
+    /// or bound by a privileged process before dropping the privileges.
+    /// The socket can be any type convertible into a [`socket2::Socket`]:
+    /// a [`std::net::TcpListener`], a [`std::net::UdpSocket`] or an `OwnedFd` in unix.
+    /// It must be of the kind expected by the transport (a listening stream socket for
+    /// connection oriented transports or a bound datagram socket for UDP).
+    /// A raw file descriptor (e.g. the `3` of the first socket passed by systemd)
+    /// can be converted with `unsafe { OwnedFd::from_raw_fd(fd) }`.
+    /// The socket is set in non-blocking mode.
+    /// Once adopted, it behaves as a listener created by [`NetworkController::listen()`].
+    ///
+    /// The [`Transport::Ws`] transport also accepts Unix domain sockets,
+    /// e.g. a [`std::os::unix::net::UnixListener`] behind a local reverse proxy.
+    /// As they have no IP address, the unspecified address `0.0.0.0:0` is used as the address
+    /// of the listener and of its connections.
+    /// Therefore, in a network sharded with [`ShardBalance::PeerHash`],
+    /// all these connections are placed in the same shard.
+    /// Only TCP and Unix domain sockets are supported as WebSocket streams:
+    /// the TLS streams (`wss`) can not be adopted.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport};
+    ///
+    /// let (controller, _) = network::split();
+    /// let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let (resource_id, addr) = controller.adopt_listener(Transport::Tcp, listener).unwrap();
+    /// ```
+    pub fn adopt_listener(
+        &self,
+        transport: Transport,
+        socket: impl Into<socket2::Socket>,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        self.adopt_listener_with(transport.into(), socket)
+    }
+
+    /// Similar to [`NetworkController::adopt_listener()`] but with custom transport options for
+    /// transports that support it.
+    pub fn adopt_listener_with(
+        &self,
+        transport_listen: TransportListen,
+        socket: impl Into<socket2::Socket>,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        self.controllers[transport_listen.id() as usize]
+            .adopt_listener_with(transport_listen, socket.into())
+            .map(|(resource_id, addr)| {
+                log::trace!("Adopted listener at {} by {}", addr, resource_id);
+                (resource_id, addr)
+            })
+    }
+
+    /// Register an already connected socket created outside the network,
+    /// e.g. a connection accepted by another process.
+    /// The socket can be any type convertible into a [`socket2::Socket`]:
+    /// a [`std::net::TcpStream`], a connected [`std::net::UdpSocket`] or an `OwnedFd` in unix.
+    /// The socket is set in non-blocking mode.
+    /// As with [`NetworkController::connect()`], a [`NetEvent::Connected`] is generated
+    /// once the resource is ready.
+    /// The [`Endpoint`] representing the connection is returned along with the local address.
+    /// The [`Transport::Ws`] transport performs the client handshake over the stream,
+    /// which can also be a Unix domain socket, but not a TLS stream
+    /// (see [`NetworkController::adopt_listener()`]).
+    pub fn adopt_stream(
+        &self,
+        transport: Transport,
+        socket: impl Into<socket2::Socket>,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        self.adopt_stream_with(transport.into(), socket)
+    }
+
+    /// Similar to [`NetworkController::adopt_stream()`] but with custom transport options for
+    /// transports that support it.
+    pub fn adopt_stream_with(
+        &self,
+        transport_connect: TransportConnect,
+        socket: impl Into<socket2::Socket>,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        self.controllers[transport_connect.id() as usize]
+            .adopt_stream_with(transport_connect, socket.into())
+            .map(|(endpoint, addr)| {
+                log::trace!("Adopted stream {} at {}", endpoint, addr);
+                (endpoint, addr)
+            })
+    }
+
+    /// Hands over all the listeners of the network to another process
+    /// (usually a freshly exec'd version of the same program) through the unix `stream`.
+    /// The other process takes them with [`NetworkController::take_over_listeners()`],
+    /// so the listening ports are never closed during a restart.
+    /// The listeners are not removed: both processes accept from them until this network
+    /// removes its listeners (or finishes), which does not close the ports of the other one.
+    ///
+    /// The ids of the listeners handed over are returned in the order they are sent.
+    /// At most [`MAX_HANDED_LISTENERS`] listeners can be handed over.
+    /// The `stream` must be in blocking mode.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport};
+    /// use std::os::unix::net::{UnixStream};
+    ///
+    /// // In a real restart, each end of the stream belongs to a different process.
+    /// let (old_stream, new_stream) = UnixStream::pair().unwrap();
+    ///
+    /// let (old_controller, _) = network::split();
+    /// let (_, addr) = old_controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    /// let handed = old_controller.hand_over_listeners(&old_stream).unwrap();
+    ///
+    /// let (new_controller, _) = network::split();
+    /// let listeners = new_controller.take_over_listeners(&new_stream).unwrap();
+    /// assert_eq!(listeners[0].1, addr);
+    ///
+    /// // The old process can stop listening now.
+    /// handed.into_iter().for_each(|id| assert!(old_controller.remove(id)));
+    /// ```
+    #[cfg(unix)]
+    pub fn hand_over_listeners(&self, stream: &UnixStream) -> io::Result<Vec<ResourceId>> {
+        let mut listeners = Vec::new();
+        let mut sockets = Vec::new();
+        for info in self.resources() {
+            if info.id.resource_type() != ResourceType::Local {
+                continue
+            }
+            match self.controllers[info.id.adapter_id() as usize].try_clone_socket(info.id) {
+                Ok(socket) => {
+                    listeners.push(info.id);
+                    sockets.push((info.transport.id(), socket));
+                }
+                // The listener was removed after taking the snapshot.
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let fds = sockets.iter().map(|(transport_id, socket)| (*transport_id, socket.as_raw_fd()));
+        handover::send_sockets(stream, &fds.collect::<Vec<_>>())?;
+        log::trace!("Handed over listeners {:?}", listeners);
+        Ok(listeners)
+    }
+
+    /// Takes the listeners handed over by another process with
+    /// [`NetworkController::hand_over_listeners()`] through the unix `stream`,
+    /// adopting them as in [`NetworkController::adopt_listener()`]
+    /// with the default configuration of their transports.
+    /// The ids of the new listeners are returned along with their local addresses,
+    /// in the same order they were handed over.
+    /// The call blocks until the listeners are received.
+    ///
+    /// Only the sockets are handed over, not the configuration they were listening with
+    /// (e.g. the accept callback, the HTTP handler, the subprotocols, the compression or the
+    /// ping of a [`Transport::Ws`] listener).
+    /// Use [`NetworkController::take_over_listeners_with()`] to set it again.
+    #[cfg(unix)]
+    pub fn take_over_listeners(
+        &self,
+        stream: &UnixStream,
+    ) -> io::Result<Vec<(ResourceId, SocketAddr)>> {
+        self.take_over_listeners_with(stream, TransportListen::from)
+    }
+
+    /// Similar to [`NetworkController::take_over_listeners()`] but the listeners are adopted
+    /// with the configuration returned by `transport_listen` for their transport.
+    /// An [`std::io::ErrorKind::InvalidInput`] error is returned if the configuration
+    /// is of a different transport.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, TransportListen};
+    /// use message_io::adapters::ws::{WsListenConfig};
+    /// use std::os::unix::net::{UnixStream};
+    ///
+    /// let (old_stream, new_stream) = UnixStream::pair().unwrap();
+    ///
+    /// let (old_controller, _) = network::split();
+    /// let config = WsListenConfig::default().with_subprotocol("chat");
+    /// old_controller.listen_with(TransportListen::Ws(config.clone()), "127.0.0.1:0").unwrap();
+    /// old_controller.hand_over_listeners(&old_stream).unwrap();
+    ///
+    /// let (new_controller, _) = network::split();
+    /// let listeners = new_controller.take_over_listeners_with(&new_stream, |transport| {
+    ///     match transport {
+    ///         Transport::Ws => TransportListen::Ws(config.clone()),
+    ///         transport => transport.into(),
+    ///     }
+    /// });
+    /// assert_eq!(listeners.unwrap().len(), 1);
+    /// ```
+    #[cfg(unix)]
+    pub fn take_over_listeners_with(
+        &self,
+        stream: &UnixStream,
+        mut transport_listen: impl FnMut(Transport) -> TransportListen,
+    ) -> io::Result<Vec<(ResourceId, SocketAddr)>> {
+        handover::receive_sockets(stream)?
+            .into_iter()
+            .map(|(transport_id, fd)| {
+                let transport =
+                    Transport::iter().find(|transport| transport.id() == transport_id).ok_or_else(
+                        || io::Error::new(io::ErrorKind::InvalidData, "Not available transport"),
+                    )?;
+                let config = transport_listen(transport);
+                if config.id() != transport_id {
+                    let msg = format!("Configuration not valid for a {:?} listener", transport);
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+                }
+                self.adopt_listener_with(config, fd)
+            })
+            .collect()
+    }
+
     /// Send the data message thought the connection represented by the given endpoint.
     /// This function returns a [`SendStatus`] indicating the status of this send.
     /// There is no guarantee that send over a correct connection generates a [`SendStatus::Sent`]
@@ -310,6 +684,114 @@ impl NetworkController {
         status
     }
 
+    /// Send a text message thought the connection represented by the given endpoint.
+    /// WebSocket sends it in a text frame, received as a string by the web clients.
+    /// Other transports have no text messages and send the bytes of the text
+    /// as [`NetworkController::send()`].
+    pub fn send_text(&self, endpoint: Endpoint, text: &str) -> SendStatus {
+        log::trace!("Sending {} bytes of text to {}...", text.len(), endpoint);
+        let status = self.controllers[endpoint.resource_id().adapter_id() as usize]
+            .send_text(endpoint, text);
+        log::trace!("Send status: {:?}", status);
+        status
+    }
+
+    /// Send a fragment of a message thought the connection represented by the given endpoint,
+    /// so a large message can be sent without having it entirely in memory.
+    /// The message is completed by the fragment sent with `last` as `true`.
+    /// WebSocket sends each fragment in a frame of the same binary message
+    /// (see [`crate::adapters::ws::WsConnectConfig::with_message_fragments()`] to receive them
+    /// as they arrive). Meanwhile, sending other messages to the endpoint returns
+    /// [`SendStatus::ResourceNotAvailable`].
+    /// Other transports have no fragmented messages and send each fragment
+    /// as [`NetworkController::send()`].
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, NetEvent, SendStatus};
+    /// use std::time::{Duration};
+    ///
+    /// let (controller, mut processor) = network::split();
+    /// let (_, addr) = controller.listen(Transport::Ws, "127.0.0.1:0").unwrap();
+    /// let (endpoint, _) = controller.connect(Transport::Ws, addr).unwrap();
+    ///
+    /// processor.process_poll_events_until_timeout(Duration::from_millis(500), |event| {
+    ///     match event {
+    ///         NetEvent::Connected(..) => {
+    ///             let file = vec![0; 100_000];
+    ///             let mut chunks = file.chunks(16 * 1024).peekable();
+    ///             while let Some(chunk) = chunks.next() {
+    ///                 let last = chunks.peek().is_none();
+    ///                 assert_eq!(controller.send_fragment(endpoint, chunk, last), SendStatus::Sent);
+    ///             }
+    ///         }
+    ///         NetEvent::Message(_, data) => assert_eq!(data.len(), 100_000),
+    ///         _ => (),
+    ///     }
+    /// });
+    /// ```
+    pub fn send_fragment(&self, endpoint: Endpoint, data: &[u8], last: bool) -> SendStatus {
+        log::trace!("Sending a fragment of {} bytes to {}...", data.len(), endpoint);
+        let status = self.controllers[endpoint.resource_id().adapter_id() as usize]
+            .send_fragment(endpoint, data, last);
+        log::trace!("Send status: {:?}", status);
+        status
+    }
+
+    /// Returns the kind of the message that is being processed for the endpoint,
+    /// e.g. to tell apart the binary and the text frames of WebSocket.
+    /// It is only meaningful while processing its [`NetEvent::Message`].
+    /// The messages of transports that do not distinguish kinds are [`MessageKind::Binary`].
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, NetEvent, MessageKind};
+    /// use std::time::{Duration};
+    ///
+    /// let (controller, mut processor) = network::split();
+    /// let (_, addr) = controller.listen(Transport::Ws, "127.0.0.1:0").unwrap();
+    /// let (endpoint, _) = controller.connect(Transport::Ws, addr).unwrap();
+    ///
+    /// processor.process_poll_events_until_timeout(Duration::from_millis(500), |event| {
+    ///     match event {
+    ///         NetEvent::Connected(..) => {
+    ///             controller.send_text(endpoint, "{\"name\": \"json\"}");
+    ///         }
+    ///         NetEvent::Message(endpoint, data) => {
+    ///             assert_eq!(controller.message_kind(endpoint), MessageKind::Text);
+    ///             let text = std::str::from_utf8(data).unwrap();
+    ///         }
+    ///         _ => (),
+    ///     }
+    /// });
+    /// ```
+    pub fn message_kind(&self, endpoint: Endpoint) -> MessageKind {
+        self.controllers[endpoint.resource_id().adapter_id() as usize]
+            .message_kind(endpoint.resource_id())
+    }
+
+    /// Returns `false` if the data that is being processed for the endpoint is a fragment
+    /// of a message followed by others, or `true` if it completes its message.
+    /// It is only meaningful while processing its [`NetEvent::Message`].
+    /// Only the WebSocket connections configured to receive the fragments
+    /// (see [`crate::adapters::ws::WsConnectConfig::with_message_fragments()`])
+    /// process incomplete messages.
+    pub fn is_message_complete(&self, endpoint: Endpoint) -> bool {
+        self.controllers[endpoint.resource_id().adapter_id() as usize]
+            .is_message_complete(endpoint.resource_id())
+    }
+
+    /// Send the data message thought the connection represented by the given endpoint.
+    /// This function is similar to [`NetworkController::send()`] but returns a future
+    /// (available through the *async* feature) that waits until the resource is ready to send
+    /// instead of returning [`SendStatus::ResourceNotAvailable`],
+    /// e.g. while the connection is still performing its handshake.
+    /// The future resolves once the entire data has been written to the OS.
+    #[cfg(feature = "async")]
+    pub fn send_async<'a>(&'a self, endpoint: Endpoint, data: &'a [u8]) -> SendFuture<'a> {
+        SendFuture::new(self, endpoint, data)
+    }
+
     /// Remove a network resource.
     /// Returns `false` if the resource id doesn't exists.
     /// This is used to remove resources as connection or listeners.
@@ -329,6 +811,65 @@ impl NetworkController {
         value
     }
 
+    /// Register a user [`Source`] in the network poll, e.g. a pipe, a unix socket,
+    /// or any file descriptor through `mio::unix::SourceFd`.
+    /// This allows to listen to these sources from the same thread that processes the network
+    /// instead of using an extra thread to read them.
+    ///
+    /// Each time the source is ready for the given `interest`, a
+    /// [`crate::node::NodeEvent::Source`] event is generated with the returned [`SourceId`]
+    /// (see also [`NetworkProcessor::process_poll_event_with_sources()`]).
+    /// Like the internal network resources, the sources are registered as edge-triggered:
+    /// once the event is received, the source must be read (or written) until it returns
+    /// [`std::io::ErrorKind::WouldBlock`], otherwise no more events will be generated.
+    ///
+    /// The user keeps the ownership of the source and is in charge of
+    /// calling [`NetworkController::deregister_source()`] before dropping it.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::node::{self, NodeEvent};
+    /// use message_io::network::{Readiness};
+    ///
+    /// use mio::{Interest};
+    /// use mio::net::{UnixStream};
+    ///
+    /// use std::io::{Read, Write};
+    ///
+    /// let (handler, listener) = node::split::<()>();
+    ///
+    /// let (mut writer, mut reader) = UnixStream::pair().unwrap();
+    /// let source_id = handler.network().register_source(&mut reader, Interest::READABLE).unwrap();
+    /// writer.write_all(&[42]).unwrap();
+    ///
+    /// listener.for_each(move |event| match event {
+    ///     NodeEvent::Source(id, Readiness::Read) => {
+    ///         assert_eq!(id, source_id);
+    ///         let mut data = [0; 1];
+    ///         reader.read_exact(&mut data).unwrap();
+    ///         assert_eq!(data, [42]);
+    ///         handler.network().deregister_source(&mut reader).unwrap();
+    ///         handler.stop();
+    ///     }
+    ///     _ => (),
+    /// });
+    /// ```
+    pub fn register_source(
+        &self,
+        source: &mut dyn Source,
+        interest: Interest,
+    ) -> io::Result<SourceId> {
+        let id = self.sources.add(source, interest)?;
+        log::trace!("Register source {}", id);
+        Ok(id)
+    }
+
+    /// Remove a source previously registered with [`NetworkController::register_source()`].
+    /// No more events will be generated for it.
+    pub fn deregister_source(&self, source: &mut dyn Source) -> io::Result<()> {
+        self.sources.remove(source)
+    }
+
     /// Check a resource specified by `resource_id` is ready.
     /// If the status is `true` means that the resource is ready to use.
     /// In connection oriented transports, it implies the resource is connected.
@@ -338,17 +879,159 @@ impl NetworkController {
     pub fn is_ready(&self, resource_id: ResourceId) -> Option<bool> {
         self.controllers[resource_id.adapter_id() as usize].is_ready(resource_id)
     }
+
+    /// Returns a snapshot of all the resources (listeners and connections) of the network.
+    /// It is useful for monitoring or for debugging leaked connections.
+    /// The order of the resources is not specified.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport};
+    ///
+    /// let (controller, _processor) = network::split();
+    /// let (listener_id, addr) = controller.listen(Transport::Udp, "127.0.0.1:0").unwrap();
+    ///
+    /// let resources = controller.resources();
+    /// assert_eq!(resources.len(), 1);
+    /// assert_eq!(resources[0].id, listener_id);
+    /// assert_eq!(resources[0].local_addr, addr);
+    /// ```
+    pub fn resources(&self) -> Vec<ResourceInfo> {
+        let mut resources = Vec::new();
+        for controller in self.controllers.iter() {
+            controller.resources(&mut |info| resources.push(info));
+        }
+        resources
+    }
+
+    /// Sets an option of the OS socket of a resource, e.g. to disable the Nagle algorithm
+    /// or to mark its packets with a DSCP value.
+    /// Note that the endpoints of a non connection-oriented listener as UDP share its socket.
+    /// The options can also be set before connecting or listening through the transport configs,
+    /// e.g. [`crate::adapters::tcp::TcpConnectConfig::with_socket_option()`].
+    ///
+    /// Returns an [`std::io::ErrorKind::NotFound`] error if the resource does not exists.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, SocketOption, SocketOptionName};
+    ///
+    /// let (controller, _processor) = network::split();
+    /// let (_, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    /// let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+    ///
+    /// let id = endpoint.resource_id();
+    /// controller.set_socket_option(id, SocketOption::NoDelay(true)).unwrap();
+    /// let value = controller.socket_option(id, SocketOptionName::NoDelay).unwrap();
+    /// assert_eq!(value, SocketOption::NoDelay(true));
+    /// ```
+    pub fn set_socket_option(
+        &self,
+        resource_id: ResourceId,
+        option: SocketOption,
+    ) -> io::Result<()> {
+        self.controllers[resource_id.adapter_id() as usize].set_socket_option(resource_id, option)
+    }
+
+    /// Gets an option of the OS socket of a resource.
+    /// See [`NetworkController::set_socket_option()`].
+    pub fn socket_option(
+        &self,
+        resource_id: ResourceId,
+        name: SocketOptionName,
+    ) -> io::Result<SocketOption> {
+        self.controllers[resource_id.adapter_id() as usize].socket_option(resource_id, name)
+    }
+
+    /// Attach user data to a resource, replacing the previous one.
+    /// It is useful to keep the state of each connection (a session) without
+    /// maintaining a map that must be synchronized with the network events.
+    /// The data is dropped when the resource is removed or disconnected.
+    /// Note that it is still available while the [`NetEvent::Disconnected`] event is processed.
+    /// Returns `false` if the resource does not exists.
+    ///
+    /// Note that the endpoints of a non connection-oriented listener as UDP share its resource,
+    /// so they also share the user data.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::network::{self, Transport, NetEvent};
+    ///
+    /// struct Session {
+    ///     name: String,
+    /// }
+    ///
+    /// let (controller, mut processor) = network::split();
+    /// let (_, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    /// let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+    ///
+    /// let session = Session { name: String::from("client") };
+    /// controller.set_user_data(endpoint.resource_id(), Box::new(session));
+    ///
+    /// let user_data = controller.user_data(endpoint.resource_id()).unwrap();
+    /// assert_eq!(user_data.downcast::<Session>().unwrap().name, "client");
+    /// ```
+    pub fn set_user_data(&self, resource_id: ResourceId, data: Box<dyn Any + Send + Sync>) -> bool {
+        self.controllers[resource_id.adapter_id() as usize]
+            .set_user_data(resource_id, Some(data.into()))
+    }
+
+    /// Returns the user data attached to the resource by [`NetworkController::set_user_data()`].
+    /// Use [`std::sync::Arc::downcast()`] to get the concrete type.
+    /// Returns `None` if the resource has no user data or does not exists.
+    pub fn user_data(&self, resource_id: ResourceId) -> Option<UserData> {
+        self.controllers[resource_id.adapter_id() as usize].user_data(resource_id)
+    }
+
+    /// Removes the user data attached to the resource.
+    /// Returns `false` if the resource does not exists.
+    pub fn remove_user_data(&self, resource_id: ResourceId) -> bool {
+        self.controllers[resource_id.adapter_id() as usize].set_user_data(resource_id, None)
+    }
+
+    /// Returns the metadata that the adapter keeps for a remote resource if it is of type `T`.
+    /// For example, the accepted WebSocket connections keep the request of their handshake
+    /// as a `WsRequest` (see [`crate::adapters::ws`]),
+    /// available since the [`NetEvent::Accepted`] is generated.
+    /// Returns `None` if the resource does not exists or has no metadata of type `T`.
+    pub fn metadata<T: Any + Send + Sync>(&self, resource_id: ResourceId) -> Option<Arc<T>> {
+        self.controllers[resource_id.adapter_id() as usize]
+            .metadata(resource_id)
+            .and_then(|metadata| metadata.downcast::<T>().ok())
+    }
+
+    /// Returns the last round trip time measured by the keepalive probes of the remote resource,
+    /// e.g. the time between a WebSocket ping and its pong
+    /// (see [`crate::adapters::ws::WsConnectConfig::with_ping()`]).
+    /// Returns `None` if the resource does not exists or has not measured it yet.
+    pub fn round_trip_time(&self, resource_id: ResourceId) -> Option<Duration> {
+        self.controllers[resource_id.adapter_id() as usize].round_trip_time(resource_id)
+    }
+
+    /// Returns the reason of the disconnection of a remote resource.
+    /// It is only available while its [`NetEvent::Disconnected`] is processed,
+    /// e.g. to tell apart a dead peer that has not answered the keepalive probes
+    /// ([`DisconnectReason::TimedOut`]) from a closed connection.
+    /// Returns `None` otherwise.
+    pub fn disconnect_reason(&self, resource_id: ResourceId) -> Option<DisconnectReason> {
+        self.controllers[resource_id.adapter_id() as usize].disconnect_reason(resource_id)
+    }
+
+    #[cfg(feature = "async")]
+    fn register_ready_waker(&self, resource_id: ResourceId, waker: &Waker) -> Option<bool> {
+        self.controllers[resource_id.adapter_id() as usize].register_ready_waker(resource_id, waker)
+    }
 }
 
 /// Instance in charge of process input network events.
 /// These events are offered to the user as a [`NetEvent`] its processing data.
 pub struct NetworkProcessor {
     poll: Poll,
-    processors: EventProcessorList,
+    processors: Arc<EventProcessorList>,
 }
 
 impl NetworkProcessor {
-    fn new(poll: Poll, processors: EventProcessorList) -> Self {
+    fn new(poll: Poll, processors: Arc<EventProcessorList>) -> Self {
         Self { poll, processors }
     }
 
@@ -358,26 +1041,134 @@ impl NetworkProcessor {
     /// Note that there is no 1-1 relation between an internal poll event and a [`NetEvent`].
     /// You need to assume that process an internal poll event could call 0 or N times to
     /// the callback with diferents `NetEvent`s.
+    ///
+    /// The keepalive probes of the resources are also processed while waiting,
+    /// it could generate [`NetEvent::Disconnected`] events for the dead peers.
+    ///
+    /// The events of the user sources are discarded,
+    /// see [`NetworkProcessor::process_poll_event_with_sources()`] to process them.
     pub fn process_poll_event(
+        &mut self,
+        timeout: Option<Duration>,
+        event_callback: impl FnMut(NetEvent<'_>),
+    ) {
+        self.process_poll_event_with_sources(timeout, event_callback, |id, _| {
+            log::warn!("Discarded event of source {}", id);
+        });
+    }
+
+    /// Similar to [`NetworkProcessor::process_poll_event()`] but also processes the events
+    /// of the sources registered by [`NetworkController::register_source()`].
+    /// These events are notified by the `source_callback`.
+    pub fn process_poll_event_with_sources(
         &mut self,
         timeout: Option<Duration>,
         mut event_callback: impl FnMut(NetEvent<'_>),
+        mut source_callback: impl FnMut(SourceId, Readiness),
     ) {
-        let processors = &mut self.processors;
-        self.poll.process_event(timeout, |poll_event| {
-            match poll_event {
-                PollEvent::Network(resource_id, interest) => {
-                    let processor = &processors[resource_id.adapter_id() as usize];
-                    processor.process(resource_id, interest, &mut |net_event| {
-                        log::trace!("Processed {:?}", net_event);
-                        event_callback(net_event);
-                    });
-                }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // The poll is woken up for the next keepalive although no event is generated.
+            let wake_up = match (deadline, self.next_keepalive()) {
+                (Some(deadline), Some(keepalive)) => Some(deadline.min(keepalive)),
+                (deadline, keepalive) => deadline.or(keepalive),
+            };
+            let poll_timeout = wake_up.map(|time| time.saturating_duration_since(Instant::now()));
 
-                #[allow(dead_code)] //TODO: remove it with native event support
-                PollEvent::Waker => todo!(),
+            let mut processed = false;
+            let processors = &self.processors;
+            self.poll.process_event(poll_timeout, |poll_event| {
+                processed = true;
+                Self::dispatch(processors, poll_event, &mut event_callback, &mut source_callback);
+            });
+            self.process_keepalives(&mut |net_event| {
+                processed = true;
+                event_callback(net_event);
+            });
+
+            if processed || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break
             }
+        }
+    }
+
+    /// Process all the poll events that are already ready without blocking.
+    /// It is useful to drive the network from an external event loop instead of a dedicated
+    /// thread: register the file descriptor returned by [`NetworkProcessor::as_raw_fd()`]
+    /// as readable in that event loop and call this method each time it is readable.
+    /// Once this method returns, all the ready events have been processed,
+    /// so it can also be used by edge-triggered event loops.
+    ///
+    /// The due keepalive probes of the resources are also processed.
+    /// Since they do not make the file descriptor readable, the event loop must also call
+    /// this method once the time returned by [`NetworkProcessor::next_timeout()`] elapses.
+    ///
+    /// The events of the user sources are discarded,
+    /// see [`NetworkProcessor::process_ready_events_with_sources()`] to process them.
+    pub fn process_ready_events(&mut self, event_callback: impl FnMut(NetEvent<'_>)) {
+        self.process_ready_events_with_sources(event_callback, |id, _| {
+            log::warn!("Discarded event of source {}", id);
+        });
+    }
+
+    /// Similar to [`NetworkProcessor::process_ready_events()`] but also processes the events
+    /// of the sources registered by [`NetworkController::register_source()`].
+    /// These events are notified by the `source_callback`.
+    pub fn process_ready_events_with_sources(
+        &mut self,
+        mut event_callback: impl FnMut(NetEvent<'_>),
+        mut source_callback: impl FnMut(SourceId, Readiness),
+    ) {
+        let processors = &self.processors;
+        self.poll.process_ready_events(|poll_event| {
+            Self::dispatch(processors, poll_event, &mut event_callback, &mut source_callback);
         });
+        self.process_keepalives(&mut event_callback);
+    }
+
+    /// Returns the time until the next keepalive probe must be processed,
+    /// or `None` if no resource has keepalive.
+    /// It is only needed to drive the network from an external event loop,
+    /// see [`NetworkProcessor::process_ready_events()`].
+    pub fn next_timeout(&self) -> Option<Duration> {
+        self.next_keepalive().map(|time| time.saturating_duration_since(Instant::now()))
+    }
+
+    fn next_keepalive(&self) -> Option<Instant> {
+        let shard = self.poll.shard();
+        self.processors.iter().filter_map(|processor| processor.next_keepalive(shard)).min()
+    }
+
+    fn process_keepalives(&self, event_callback: &mut dyn FnMut(NetEvent<'_>)) {
+        let shard = self.poll.shard();
+        for processor in self.processors.iter() {
+            processor.process_keepalives(shard, &mut |net_event| {
+                log::trace!("Processed {:?}", net_event);
+                event_callback(net_event);
+            });
+        }
+    }
+
+    fn dispatch(
+        processors: &EventProcessorList,
+        poll_event: PollEvent,
+        event_callback: &mut impl FnMut(NetEvent<'_>),
+        source_callback: &mut impl FnMut(SourceId, Readiness),
+    ) {
+        match poll_event {
+            PollEvent::Network(resource_id, interest) => {
+                let processor = &processors[resource_id.adapter_id() as usize];
+                processor.process(resource_id, interest, &mut |net_event| {
+                    log::trace!("Processed {:?}", net_event);
+                    event_callback(net_event);
+                });
+            }
+
+            PollEvent::Source(id, readiness) => source_callback(id, readiness),
+
+            #[allow(dead_code)] //TODO: remove it with native event support
+            PollEvent::Waker => todo!(),
+        }
     }
 
     /// Process poll events until there is no more events during a `timeout` duration.
@@ -397,6 +1188,20 @@ impl NetworkProcessor {
     }
 }
 
+/// The internal poll file descriptor (only available in unix).
+/// It becomes readable when the network has events to process.
+/// See [`NetworkProcessor::process_ready_events()`] to know how to embed the network in an
+/// external event loop.
+///
+/// Note that the file descriptor is owned by the `NetworkProcessor`,
+/// it must not be closed and must not be used after the processor is dropped.
+#[cfg(unix)]
+impl AsRawFd for NetworkProcessor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.poll.as_raw_fd()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -405,6 +1210,9 @@ mod tests {
 
     use test_case::test_case;
 
+    #[cfg(feature = "websocket")]
+    use crate::adapters::ws::{WsDeflateConfig};
+
     lazy_static::lazy_static! {
         static ref TIMEOUT: Duration = Duration::from_millis(1000);
         static ref LOCALHOST_CONN_TIMEOUT: Duration = Duration::from_millis(5000);
@@ -503,18 +1311,140 @@ mod tests {
         thread.join();
     }
 
-    #[test]
-    fn create_remove_listener() {
+    #[cfg(feature = "async")]
+    #[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+    #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+    #[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+    fn successful_connection_async(transport: Transport) {
         let (controller, mut processor) = self::split();
-        let (listener_id, _) = controller.listen(Transport::Tcp, "127.0.0.1:0").unwrap();
-        assert!(controller.remove(listener_id)); // Do not generate an event
-        assert!(!controller.remove(listener_id));
+        let (_, addr) = controller.listen(transport, "127.0.0.1:0").unwrap();
 
-        processor.process_poll_events_until_timeout(*TIMEOUT, |_| unreachable!());
+        let mut thread = NamespacedThread::spawn("test", move || {
+            futures::executor::block_on(async {
+                let (endpoint, _) = controller.connect_async(transport, addr).await.unwrap();
+                assert!(controller.is_ready(endpoint.resource_id()).unwrap());
+                assert_eq!(controller.send_async(endpoint, &[42]).await, SendStatus::Sent);
+            });
+        });
+
+        processor.process_poll_events_until_timeout(*TIMEOUT, |_| ());
+
+        thread.join();
     }
 
-    #[test]
-    fn create_remove_listener_with_connection() {
+    #[cfg(feature = "async")]
+    #[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+    #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+    #[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+    fn unreachable_connection_async(transport: Transport) {
+        let (controller, mut processor) = self::split();
+
+        // Ensure that addr is not using by other process
+        // because it takes some secs to be reusable.
+        let (listener_id, addr) = controller.listen(transport, "127.0.0.1:0").unwrap();
+        controller.remove(listener_id);
+
+        let mut thread = NamespacedThread::spawn("test", move || {
+            futures::executor::block_on(async {
+                let err = controller.connect_async(transport, addr).await.unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+            });
+        });
+
+        processor.process_poll_events_until_timeout(*LOCALHOST_CONN_TIMEOUT, |_| ());
+
+        thread.join();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn send_async_waits_ready() {
+        let (controller, mut processor) = self::split();
+        let (_, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+        assert_eq!(controller.send(endpoint, &[42]), SendStatus::ResourceNotAvailable);
+
+        let mut thread = NamespacedThread::spawn("test", move || {
+            let status = futures::executor::block_on(controller.send_async(endpoint, &[42]));
+            assert_eq!(status, SendStatus::Sent);
+        });
+
+        let mut received = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| {
+            if let NetEvent::Message(_, data) = net_event {
+                assert_eq!(data, &[42]);
+                received = true;
+            }
+        });
+        assert!(received);
+
+        thread.join();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn external_event_loop() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+
+        let (mut writer, mut reader) = mio::net::UnixStream::pair().unwrap();
+        let source_id = controller.register_source(&mut reader, Interest::READABLE).unwrap();
+        std::io::Write::write_all(&mut writer, &[42]).unwrap();
+
+        let mut fds =
+            [libc::pollfd { fd: processor.as_raw_fd(), events: libc::POLLIN, revents: 0 }];
+
+        let (mut was_connected, mut was_accepted, mut was_readable) = (false, false, false);
+        let start = Instant::now();
+        while !(was_connected && was_accepted && was_readable) {
+            assert!(start.elapsed() < *TIMEOUT);
+            let timeout = TIMEOUT.as_millis() as libc::c_int;
+            if unsafe { libc::poll(fds.as_mut_ptr(), 1, timeout) } > 0 {
+                processor.process_ready_events_with_sources(
+                    |net_event| match net_event {
+                        NetEvent::Connected(net_endpoint, status) => {
+                            assert!(status);
+                            assert_eq!(endpoint, net_endpoint);
+                            was_connected = true;
+                        }
+                        NetEvent::Accepted(_, net_listener_id) => {
+                            assert_eq!(listener_id, net_listener_id);
+                            was_accepted = true;
+                        }
+                        _ => unreachable!(),
+                    },
+                    |id, readiness| {
+                        assert_eq!(id, source_id);
+                        assert_eq!(readiness, Readiness::Read);
+                        was_readable = true;
+                    },
+                );
+            }
+        }
+        controller.deregister_source(&mut reader).unwrap();
+    }
+
+    #[test]
+    fn process_ready_events_not_block() {
+        let (_controller, mut processor) = self::split();
+        let start = Instant::now();
+        processor.process_ready_events(|_| unreachable!());
+        assert!(start.elapsed() < *TIMEOUT);
+    }
+
+    #[test]
+    fn create_remove_listener() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, _) = controller.listen(Transport::Tcp, "127.0.0.1:0").unwrap();
+        assert!(controller.remove(listener_id)); // Do not generate an event
+        assert!(!controller.remove(listener_id));
+
+        processor.process_poll_events_until_timeout(*TIMEOUT, |_| unreachable!());
+    }
+
+    #[test]
+    fn create_remove_listener_with_connection() {
         let (controller, mut processor) = self::split();
         let (listener_id, addr) = controller.listen(Transport::Tcp, "127.0.0.1:0").unwrap();
         controller.connect(Transport::Tcp, addr).unwrap();
@@ -531,4 +1461,1247 @@ mod tests {
         });
         assert!(was_accepted);
     }
+
+    #[cfg(feature = "udp")]
+    #[test]
+    fn stale_endpoint() {
+        let (controller, _processor) = self::split();
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let (stale, _) = controller.connect(Transport::Udp, addr).unwrap();
+        assert!(controller.remove(stale.resource_id()));
+
+        // The new connection reuses the slot of the removed one.
+        let (endpoint, _) = controller.connect(Transport::Udp, addr).unwrap();
+        assert_eq!(stale.resource_id().slot(), endpoint.resource_id().slot());
+        assert_ne!(stale.resource_id(), endpoint.resource_id());
+
+        assert_eq!(controller.send(stale, &[42]), SendStatus::ResourceNotFound);
+        assert_eq!(controller.is_ready(stale.resource_id()), None);
+        assert!(!controller.remove(stale.resource_id()));
+        assert!(controller.is_ready(endpoint.resource_id()).is_some());
+    }
+
+    #[test]
+    fn user_data() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+
+        let marker = Arc::new(());
+        assert!(controller.set_user_data(listener_id, Box::new("listener")));
+        assert!(controller.set_user_data(endpoint.resource_id(), Box::new(marker.clone())));
+
+        let mut disconnected = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => {
+                assert!(controller.remove(endpoint.resource_id()));
+                assert_eq!(Arc::strong_count(&marker), 1);
+            }
+            NetEvent::Accepted(accepted, id) => {
+                let name = controller.user_data(id).unwrap().downcast::<&str>().unwrap();
+                assert_eq!(*name, "listener");
+                assert!(controller.set_user_data(accepted.resource_id(), Box::new(42)));
+            }
+            NetEvent::Disconnected(accepted) => {
+                assert_eq!(controller.is_ready(accepted.resource_id()), None);
+                let user_data = controller.user_data(accepted.resource_id()).unwrap();
+                assert_eq!(*user_data.downcast::<i32>().unwrap(), 42);
+                disconnected = true;
+            }
+            NetEvent::Message(..) => unreachable!(),
+        });
+        assert!(disconnected);
+
+        assert!(controller.remove_user_data(listener_id));
+        assert!(controller.user_data(listener_id).is_none());
+        assert!(!controller.set_user_data(endpoint.resource_id(), Box::new(())));
+    }
+
+    #[test]
+    fn resources() {
+        let (controller, mut processor) = self::split();
+        let (listener_id, listen_addr) =
+            controller.listen(Transport::FramedTcp, "0.0.0.0:0").unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], listen_addr.port()));
+        let (endpoint, local_addr) = controller.connect(Transport::FramedTcp, addr).unwrap();
+
+        let mut accepted_id = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Accepted(accepted, _) => accepted_id = Some(accepted.resource_id()),
+            _ => unreachable!(),
+        });
+
+        let mut resources = controller.resources();
+        resources.sort_by_key(|info| info.id.is_remote());
+        assert_eq!(resources.len(), 3);
+
+        let listener = &resources[0];
+        assert_eq!(listener.id, listener_id);
+        assert_eq!(listener.transport, Transport::FramedTcp);
+        assert_eq!(listener.local_addr, listen_addr);
+        assert_eq!(listener.peer_addr, None);
+
+        let connection = resources.iter().find(|info| info.id == endpoint.resource_id()).unwrap();
+        assert_eq!(connection.local_addr, local_addr);
+        assert_eq!(connection.peer_addr, Some(addr));
+        assert_eq!(connection.listener, None);
+        assert!(connection.ready);
+        assert!(connection.age >= *TIMEOUT);
+
+        let accepted = resources.iter().find(|info| Some(info.id) == accepted_id).unwrap();
+        assert_eq!(accepted.local_addr, addr);
+        assert_eq!(accepted.peer_addr, Some(local_addr));
+        assert_eq!(accepted.listener, Some(listener_id));
+
+        controller.remove(endpoint.resource_id());
+        assert_eq!(controller.resources().len(), 2);
+    }
+
+    #[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+    #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+    #[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+    fn socket_options(transport: Transport) {
+        let (controller, mut processor) = self::split();
+        let (listener_id, addr) = controller.listen(transport, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(transport, addr).unwrap();
+
+        let id = endpoint.resource_id();
+        controller.set_socket_option(id, SocketOption::NoDelay(true)).unwrap();
+        controller.set_socket_option(id, SocketOption::Tos(46 << 2)).unwrap();
+        assert_eq!(
+            controller.socket_option(id, SocketOptionName::Tos).unwrap(),
+            SocketOption::Tos(184)
+        );
+
+        let mut accepted = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => {
+                let no_delay = controller.socket_option(id, SocketOptionName::NoDelay).unwrap();
+                assert_eq!(no_delay, SocketOption::NoDelay(true));
+            }
+            NetEvent::Accepted(endpoint, _) => accepted = Some(endpoint),
+            _ => unreachable!(),
+        });
+
+        let accepted = accepted.unwrap().resource_id();
+        controller.set_socket_option(accepted, SocketOption::Ttl(12)).unwrap();
+        assert_eq!(
+            controller.socket_option(accepted, SocketOptionName::Ttl).unwrap(),
+            SocketOption::Ttl(12)
+        );
+        assert!(controller.socket_option(listener_id, SocketOptionName::Ttl).is_ok());
+
+        controller.remove(id);
+        let error = controller.socket_option(id, SocketOptionName::Ttl).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn socket_options_in_config() {
+        use crate::adapters::tcp::{TcpConnectConfig, TcpListenConfig};
+
+        let (controller, mut processor) = self::split();
+        let config = TcpListenConfig::default().with_socket_option(SocketOption::NoDelay(true));
+        let (_, addr) =
+            controller.listen_with(TransportListen::Tcp(config), "127.0.0.1:0").unwrap();
+
+        let config = TcpConnectConfig::default().with_socket_option(SocketOption::Ttl(12));
+        let (endpoint, _) = controller.connect_with(TransportConnect::Tcp(config), addr).unwrap();
+        let ttl = controller.socket_option(endpoint.resource_id(), SocketOptionName::Ttl).unwrap();
+        assert_eq!(ttl, SocketOption::Ttl(12));
+
+        let mut was_accepted = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Accepted(accepted, _) => {
+                let id = accepted.resource_id();
+                let no_delay = controller.socket_option(id, SocketOptionName::NoDelay).unwrap();
+                assert_eq!(no_delay, SocketOption::NoDelay(true));
+                was_accepted = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_accepted);
+    }
+
+    #[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+    #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+    #[cfg_attr(feature = "websocket", test_case(Transport::Ws))]
+    fn adopt_listener(transport: Transport) {
+        let (controller, mut processor) = self::split();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let listener_addr = listener.local_addr().unwrap();
+        let (listener_id, addr) = controller.adopt_listener(transport, listener).unwrap();
+        assert_eq!(addr, listener_addr);
+        controller.connect(transport, addr).unwrap();
+
+        let mut was_accepted = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => (),
+            NetEvent::Accepted(_, net_listener_id) => {
+                assert_eq!(listener_id, net_listener_id);
+                was_accepted = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_accepted);
+    }
+
+    #[cfg(feature = "udp")]
+    #[test]
+    fn adopt_udp_listener() {
+        let (controller, mut processor) = self::split();
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (listener_id, addr) = controller.adopt_listener(Transport::Udp, socket).unwrap();
+
+        let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&[42], addr).unwrap();
+
+        let mut was_received = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(endpoint.resource_id(), listener_id);
+                assert_eq!(endpoint.addr(), sender.local_addr().unwrap());
+                assert_eq!(data, [42]);
+                was_received = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_received);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn adopt_stream() {
+        use std::io::{Read};
+
+        let (controller, mut processor) = self::split();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut peer, peer_addr) = listener.accept().unwrap();
+        let (endpoint, local_addr) = controller.adopt_stream(Transport::Tcp, stream).unwrap();
+        assert_eq!(endpoint.addr(), listener.local_addr().unwrap());
+        assert_eq!(local_addr, peer_addr);
+
+        let mut was_connected = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(net_endpoint, status) => {
+                assert!(status);
+                assert_eq!(endpoint, net_endpoint);
+                assert_eq!(controller.send(endpoint, &[42]), SendStatus::Sent);
+                was_connected = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_connected);
+
+        let mut data = [0];
+        peer.read_exact(&mut data).unwrap();
+        assert_eq!(data, [42]);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_adopt_stream() {
+        let (controller, mut processor) = self::split();
+        let (_, addr) = controller.listen(Transport::Ws, "127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let (endpoint, _) = controller.adopt_stream(Transport::Ws, stream).unwrap();
+        assert_eq!(endpoint.addr(), addr);
+
+        let mut message = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, established) => {
+                assert!(established);
+                assert_eq!(controller.send(endpoint, &[42]), SendStatus::Sent);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(_, data) => message = Some(data.to_vec()),
+            _ => unreachable!(),
+        });
+        assert_eq!(message, Some(vec![42]));
+    }
+
+    #[cfg(all(unix, feature = "websocket"))]
+    #[test]
+    fn ws_unix_socket() {
+        use std::os::unix::net::{UnixListener};
+
+        let unspecified_addr = "0.0.0.0:0".parse().unwrap();
+        let path = std::env::temp_dir().join(format!("message-io-ws-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (controller, mut processor) = self::split();
+        let listener = UnixListener::bind(&path).unwrap();
+        let (listener_id, addr) = controller.adopt_listener(Transport::Ws, listener).unwrap();
+        assert_eq!(addr, unspecified_addr);
+
+        let stream = UnixStream::connect(&path).unwrap();
+        let (endpoint, local_addr) = controller.adopt_stream(Transport::Ws, stream).unwrap();
+        assert_eq!(endpoint.addr(), unspecified_addr);
+        assert_eq!(local_addr, unspecified_addr);
+
+        let mut messages = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, established) => {
+                assert!(established);
+                assert_eq!(controller.send_text(endpoint, "request"), SendStatus::Sent);
+            }
+            NetEvent::Accepted(accepted_endpoint, accepted_listener_id) => {
+                assert_eq!(accepted_endpoint.addr(), unspecified_addr);
+                assert_eq!(accepted_listener_id, listener_id);
+            }
+            NetEvent::Message(message_endpoint, data) => {
+                messages.push(data.to_vec());
+                if message_endpoint != endpoint {
+                    assert_eq!(controller.send_text(message_endpoint, "answer"), SendStatus::Sent);
+                }
+            }
+            _ => unreachable!(),
+        });
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(messages, [b"request".to_vec(), b"answer".to_vec()]);
+    }
+
+    #[cfg(all(unix, feature = "tcp", feature = "udp"))]
+    #[test]
+    fn listener_handover() {
+        let (old_stream, new_stream) = UnixStream::pair().unwrap();
+
+        let (old_controller, _old_processor) = self::split();
+        let (tcp_id, tcp_addr) =
+            old_controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let (udp_id, udp_addr) = old_controller.listen(Transport::Udp, "127.0.0.1:0").unwrap();
+        old_controller.connect(Transport::FramedTcp, tcp_addr).unwrap();
+
+        let handed = old_controller.hand_over_listeners(&old_stream).unwrap();
+        assert_eq!(handed.len(), 2);
+        assert!(handed.contains(&tcp_id) && handed.contains(&udp_id));
+
+        let (new_controller, mut new_processor) = self::split();
+        let listeners = new_controller.take_over_listeners(&new_stream).unwrap();
+        let addrs = listeners.iter().map(|(_, addr)| *addr).collect::<Vec<_>>();
+        assert_eq!(addrs.len(), 2);
+        assert!(addrs.contains(&tcp_addr) && addrs.contains(&udp_addr));
+        let transports =
+            listeners.iter().map(|(id, _)| Transport::from(id.adapter_id())).collect::<Vec<_>>();
+        assert!(transports.contains(&Transport::FramedTcp) && transports.contains(&Transport::Udp));
+
+        // The ports remain open after the old listeners are removed.
+        handed.into_iter().for_each(|id| assert!(old_controller.remove(id)));
+        let (endpoint, _) = new_controller.connect(Transport::FramedTcp, tcp_addr).unwrap();
+
+        let mut was_accepted = false;
+        new_processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(net_endpoint, status) => {
+                assert!(status);
+                assert_eq!(endpoint, net_endpoint);
+            }
+            NetEvent::Accepted(_, listener_id) => {
+                assert!(listeners.iter().any(|(id, _)| *id == listener_id));
+                was_accepted = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(was_accepted);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    #[allow(clippy::result_large_err)] // The error response is given by tungstenite
+    fn ws_request_config() {
+        use crate::adapters::ws::{WsConnectConfig};
+        use tungstenite::handshake::server::{Request, Response};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = NamespacedThread::spawn("test-ws-server", move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut uri = String::new();
+            let mut token = None;
+            let callback = |request: &Request, mut response: Response| {
+                uri = request.uri().to_string();
+                token = request.headers().get("x-token").cloned();
+                response.headers_mut().insert("Sec-WebSocket-Protocol", "chat".parse().unwrap());
+                Ok(response)
+            };
+            let _web_socket = tungstenite::accept_hdr(stream, callback).unwrap();
+            (uri, token.unwrap())
+        });
+
+        let (controller, mut processor) = self::split();
+        let config = WsConnectConfig::default()
+            .with_path("/room?id=1")
+            .with_header("x-token", "secret")
+            .with_subprotocol("chat");
+        let (endpoint, _) = controller.connect_with(TransportConnect::Ws(config), addr).unwrap();
+
+        let mut was_connected = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(net_endpoint, status) => {
+                assert!(status);
+                assert_eq!(endpoint, net_endpoint);
+                was_connected = true;
+            }
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        assert!(was_connected);
+
+        let (uri, token) = server.join();
+        assert_eq!(uri, "/room?id=1");
+        assert_eq!(token, "secret");
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_invalid_header() {
+        use crate::adapters::ws::{WsConnectConfig};
+
+        let (controller, _) = self::split();
+        let config = WsConnectConfig::default().with_header("invalid header", "value");
+        let addr = "127.0.0.1:80".parse::<SocketAddr>().unwrap();
+        let err = controller.connect_with(TransportConnect::Ws(config), addr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test_case(&["b", "a"], true)]
+    #[test_case(&["c"], false)]
+    fn ws_subprotocols(requested: &[&str], success: bool) {
+        use crate::adapters::ws::{WsConnectConfig, WsListenConfig};
+
+        let (controller, mut processor) = self::split();
+        let config = WsListenConfig::default().with_subprotocol("a").with_subprotocol("b");
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+
+        let config = requested.iter().fold(WsConnectConfig::default(), |config, protocol| {
+            config.with_subprotocol(*protocol)
+        });
+        let (endpoint, _) = controller.connect_with(TransportConnect::Ws(config), addr).unwrap();
+
+        let mut connection_status = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(net_endpoint, status) => {
+                assert_eq!(endpoint, net_endpoint);
+                connection_status = Some(status);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        assert_eq!(connection_status, Some(success));
+    }
+
+    #[cfg(all(unix, feature = "websocket"))]
+    #[test]
+    fn ws_listener_handover_with_config() {
+        use crate::adapters::ws::{WsListenConfig};
+
+        let (old_stream, new_stream) = UnixStream::pair().unwrap();
+        let reject_all = || WsListenConfig::default().with_accept_callback(|_| Err(403));
+
+        let (old_controller, _old_processor) = self::split();
+        let (ws_id, addr) =
+            old_controller.listen_with(TransportListen::Ws(reject_all()), "127.0.0.1:0").unwrap();
+        old_controller.hand_over_listeners(&old_stream).unwrap();
+        assert!(old_controller.remove(ws_id));
+
+        let (controller, mut processor) = self::split();
+        controller
+            .take_over_listeners_with(&new_stream, |transport| match transport {
+                Transport::Ws => TransportListen::Ws(reject_all()),
+                transport => transport.into(),
+            })
+            .unwrap();
+
+        controller.connect(Transport::Ws, addr).unwrap();
+        let mut connection_status = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, status) => connection_status = Some(status),
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        assert_eq!(connection_status, Some(false));
+    }
+
+    #[cfg(all(unix, feature = "tcp"))]
+    #[test]
+    fn listener_handover_with_wrong_config() {
+        let (old_stream, new_stream) = UnixStream::pair().unwrap();
+        let (old_controller, _old_processor) = self::split();
+        old_controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        old_controller.hand_over_listeners(&old_stream).unwrap();
+
+        let (controller, _processor) = self::split();
+        let err = controller
+            .take_over_listeners_with(&new_stream, |_| TransportListen::from(Transport::Tcp))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test_case("token=good", true)]
+    #[test_case("token=bad", false)]
+    fn ws_accept_callback(query: &str, success: bool) {
+        use crate::adapters::ws::{WsConnectConfig, WsListenConfig, WsRequest};
+
+        let (controller, mut processor) = self::split();
+        let config =
+            WsListenConfig::default().with_subprotocol("chat").with_accept_callback(|request| {
+                match request.query.as_deref() {
+                    Some("token=good") => Ok(()),
+                    _ => Err(401),
+                }
+            });
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+
+        let config = WsConnectConfig::default()
+            .with_path(format!("/chat?{query}"))
+            .with_header("X-Client", "test")
+            .with_subprotocol("chat");
+        let (endpoint, _) = controller.connect_with(TransportConnect::Ws(config), addr).unwrap();
+        assert!(controller.metadata::<WsRequest>(endpoint.resource_id()).is_none());
+
+        let mut connection_status = None;
+        let mut accepted_request = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, status) => connection_status = Some(status),
+            NetEvent::Accepted(accepted, _) => {
+                accepted_request = controller.metadata::<WsRequest>(accepted.resource_id());
+            }
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        assert_eq!(connection_status, Some(success));
+
+        if success {
+            let request = accepted_request.unwrap();
+            assert_eq!(request.path, "/chat");
+            assert_eq!(request.query.as_deref(), Some(query));
+            assert_eq!(request.header("x-client"), Some("test"));
+            assert_eq!(request.subprotocol.as_deref(), Some("chat"));
+        }
+        else {
+            assert!(accepted_request.is_none());
+        }
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test_case("GET /healthz HTTP/1.1\r\n\r\n", "HTTP/1.1 200 OK", "ok")]
+    #[test_case("HEAD /healthz HTTP/1.1\r\n\r\n", "HTTP/1.1 200 OK", "")]
+    #[test_case(
+        "POST /echo?a=1 HTTP/1.1\r\nContent-Length: 4\r\n\r\nbody",
+        "HTTP/1.1 200 OK",
+        "a=1 body"
+    )]
+    #[test_case("GET /unknown HTTP/1.1\r\n\r\n", "HTTP/1.1 404 Not Found", "")]
+    #[test_case("GET\r\n\r\n", "HTTP/1.1 400 Bad Request", "")]
+    fn ws_http_handler(request: &'static str, status_line: &str, body: &str) {
+        use crate::adapters::ws::{WsListenConfig, HttpResponse};
+        use std::io::{Read, Write};
+
+        let (controller, mut processor) = self::split();
+        let config =
+            WsListenConfig::default().with_http_handler(|request| match request.path.as_str() {
+                "/healthz" => HttpResponse::new(200).with_body("ok"),
+                "/echo" => {
+                    let query = request.query.as_deref().unwrap_or_default();
+                    let body = String::from_utf8_lossy(&request.body);
+                    HttpResponse::new(200).with_body(format!("{query} {body}"))
+                }
+                _ => HttpResponse::new(404),
+            });
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+
+        let mut client = NamespacedThread::spawn("test-http-client", move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            // The request is split to be received in several reads.
+            let (first, second) = request.split_at(request.len() / 2);
+            stream.write_all(first.as_bytes()).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            stream.write_all(second.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        // The WebSocket endpoint is served by the same listener.
+        let (endpoint, _) = controller.connect(Transport::Ws, addr).unwrap();
+        let mut message = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, established) => {
+                assert!(established);
+                assert_eq!(controller.send(endpoint, b"message"), SendStatus::Sent);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(_, data) => message = Some(data.to_vec()),
+            _ => unreachable!(),
+        });
+        assert_eq!(message.as_deref(), Some(&b"message"[..]));
+
+        let response = client.join();
+        let (head, response_body) = response.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        assert_eq!(lines.next(), Some(status_line));
+        assert!(lines.any(|line| line == "Connection: close"));
+        assert_eq!(response_body, body);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_text_messages() {
+        use tungstenite::{Message};
+
+        let (controller, mut processor) = self::split();
+        let (_, addr) = controller.listen(Transport::Ws, "127.0.0.1:0").unwrap();
+
+        let mut client = NamespacedThread::spawn("test-ws-client", move || {
+            let (mut web_socket, _) = tungstenite::connect(format!("ws://{addr}/")).unwrap();
+            web_socket.send(Message::Text("text".into())).unwrap();
+            web_socket.send(Message::Binary(vec![1, 2].into())).unwrap();
+            let answers = (0..2).map(|_| web_socket.read().unwrap()).collect::<Vec<_>>();
+            web_socket.close(None).unwrap();
+            answers
+        });
+
+        let mut messages = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(endpoint, data) => {
+                let kind = controller.message_kind(endpoint);
+                messages.push((kind, data.to_vec()));
+                let status = match kind {
+                    MessageKind::Text => controller.send_text(endpoint, "answer"),
+                    MessageKind::Binary => controller.send(endpoint, data),
+                };
+                assert_eq!(status, SendStatus::Sent);
+            }
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+
+        assert_eq!(
+            messages,
+            [(MessageKind::Text, b"text".to_vec()), (MessageKind::Binary, vec![1, 2])]
+        );
+        assert_eq!(
+            client.join(),
+            [Message::Text("answer".into()), Message::Binary(vec![1, 2].into())]
+        );
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn text_messages_without_kinds() {
+        let (controller, mut processor) = self::split();
+        let (_, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(Transport::FramedTcp, addr).unwrap();
+
+        let mut message = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => {
+                assert_eq!(controller.send_text(endpoint, "text"), SendStatus::Sent);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(endpoint, data) => {
+                message = Some((controller.message_kind(endpoint), data.to_vec()));
+            }
+            _ => unreachable!(),
+        });
+        assert_eq!(message, Some((MessageKind::Binary, b"text".to_vec())));
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_max_message_size() {
+        use crate::adapters::ws::{WsListenConfig};
+
+        let (controller, mut processor) = self::split();
+        let config = WsListenConfig::default().with_max_message_size(16);
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+        let (endpoint, _) = controller.connect(Transport::Ws, addr).unwrap();
+
+        let mut messages = Vec::new();
+        let mut was_disconnected = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(..) => {
+                assert_eq!(controller.send(endpoint, &[1; 16]), SendStatus::Sent);
+                assert_eq!(controller.send(endpoint, &[2; 17]), SendStatus::Sent);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(_, data) => messages.push(data.to_vec()),
+            NetEvent::Disconnected(_) => was_disconnected = true,
+        });
+        assert_eq!(messages, [vec![1; 16]]);
+        assert!(was_disconnected);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test_case(Some(WsDeflateConfig::default()), Some(WsDeflateConfig::default()))]
+    #[test_case(Some(WsDeflateConfig::default()), None)]
+    #[test_case(None, Some(WsDeflateConfig::default()))]
+    #[test_case(
+        Some(WsDeflateConfig::default().with_client_max_window_bits(9)),
+        Some(WsDeflateConfig::default().with_server_no_context_takeover())
+    )]
+    #[test_case(
+        Some(WsDeflateConfig::default().with_client_no_context_takeover()),
+        Some(WsDeflateConfig::default().with_server_max_window_bits(10))
+    )]
+    fn ws_deflate(client: Option<WsDeflateConfig>, server: Option<WsDeflateConfig>) {
+        use crate::adapters::ws::{WsConnectConfig, WsListenConfig};
+
+        const MESSAGES: usize = 5;
+
+        let (controller, mut processor) = self::split();
+        let mut listen_config = WsListenConfig::default();
+        if let Some(server) = server {
+            listen_config = listen_config.with_deflate(server);
+        }
+        let mut connect_config = WsConnectConfig::default();
+        if let Some(client) = client {
+            connect_config = connect_config.with_deflate(client);
+        }
+        let listen = TransportListen::Ws(listen_config);
+        let (_, addr) = controller.listen_with(listen, "127.0.0.1:0").unwrap();
+        let connect = TransportConnect::Ws(connect_config);
+        let (endpoint, _) = controller.connect_with(connect, addr).unwrap();
+
+        let message = |index: usize| format!("message {index} ").repeat(100 * (index + 1));
+        let mut answers = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, established) => {
+                assert!(established);
+                for index in 0..MESSAGES {
+                    assert_eq!(controller.send_text(endpoint, &message(index)), SendStatus::Sent);
+                }
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(message_endpoint, data) => {
+                if message_endpoint == endpoint {
+                    assert_eq!(controller.message_kind(endpoint), MessageKind::Text);
+                    answers.push(String::from_utf8(data.to_vec()).unwrap());
+                }
+                else {
+                    let text = std::str::from_utf8(data).unwrap();
+                    assert_eq!(controller.send_text(message_endpoint, text), SendStatus::Sent);
+                }
+            }
+            NetEvent::Disconnected(_) => unreachable!(),
+        });
+        assert_eq!(answers, (0..MESSAGES).map(message).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_deflate_frames() {
+        use crate::adapters::ws::{WsListenConfig};
+        use tungstenite::{Message, client::IntoClientRequest};
+        use tungstenite::protocol::frame::{Frame, FrameHeader};
+        use tungstenite::protocol::frame::coding::{OpCode, Data};
+        use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+        use std::io::{Read};
+
+        let (controller, mut processor) = self::split();
+        let config = WsListenConfig::default().with_deflate(WsDeflateConfig::default());
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+
+        let text = "compressed ".repeat(100);
+        let expected = text.clone();
+        let mut client = NamespacedThread::spawn("test-ws-client", move || {
+            let mut request = format!("ws://{addr}/").into_client_request().unwrap();
+            let extension = "permessage-deflate; client_max_window_bits".parse().unwrap();
+            request.headers_mut().insert("Sec-WebSocket-Extensions", extension);
+            let (mut web_socket, response) = tungstenite::connect(request).unwrap();
+            assert_eq!(response.headers()["Sec-WebSocket-Extensions"], "permessage-deflate");
+
+            // An uncompressed message, answered with a compressed one.
+            web_socket.send(Message::Text(text.clone().into())).unwrap();
+            let mut cursor = std::io::Cursor::new(Vec::new());
+            let header = loop {
+                let mut buffer = [0; 4096];
+                let size = web_socket.get_mut().read(&mut buffer).unwrap();
+                cursor.get_mut().extend_from_slice(&buffer[..size]);
+                cursor.set_position(0);
+                if let Some((header, length)) = FrameHeader::parse(&mut cursor).unwrap() {
+                    let start = cursor.position() as usize;
+                    if cursor.get_ref().len() >= start + length as usize {
+                        cursor.get_mut().drain(..start);
+                        break header
+                    }
+                }
+            };
+            assert!(header.rsv1);
+            assert_eq!(header.opcode, OpCode::Data(Data::Text));
+            assert!(cursor.get_ref().len() < text.len() / 10);
+
+            let mut payload = cursor.into_inner();
+            payload.extend_from_slice(&[0, 0, 0xff, 0xff]);
+            let mut answer = Vec::with_capacity(text.len());
+            Decompress::new(false)
+                .decompress_vec(&payload, &mut answer, FlushDecompress::Sync)
+                .unwrap();
+            assert_eq!(answer, text.as_bytes());
+
+            // A compressed message.
+            let mut payload = Vec::with_capacity(text.len());
+            Compress::new(Compression::default(), false)
+                .compress_vec(text.as_bytes(), &mut payload, FlushCompress::Sync)
+                .unwrap();
+            payload.truncate(payload.len() - 4);
+            let mut header = FrameHeader { opcode: OpCode::Data(Data::Text), ..Default::default() };
+            header.rsv1 = true;
+            web_socket.send(Message::Frame(Frame::from_payload(header, payload.into()))).unwrap();
+            web_socket.close(None).unwrap();
+            while web_socket.read().is_ok() {}
+        });
+
+        let mut messages = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(endpoint, data) => {
+                let text = std::str::from_utf8(data).unwrap();
+                assert_eq!(controller.send_text(endpoint, text), SendStatus::Sent);
+                messages.push(text.to_owned());
+            }
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        client.join();
+        assert_eq!(messages, [expected.clone(), expected]);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test_case(None)]
+    #[test_case(Some(WsDeflateConfig::default()))]
+    fn ws_message_fragments(deflate: Option<WsDeflateConfig>) {
+        use crate::adapters::ws::{WsConnectConfig, WsListenConfig};
+
+        const CHUNK_SIZE: usize = 16 * 1024;
+
+        let (controller, mut processor) = self::split();
+        let mut listen_config = WsListenConfig::default().with_message_fragments();
+        let mut connect_config = WsConnectConfig::default();
+        if let Some(deflate) = deflate {
+            listen_config = listen_config.with_deflate(deflate);
+            connect_config = connect_config.with_deflate(deflate);
+        }
+        let listen = TransportListen::Ws(listen_config);
+        let (_, addr) = controller.listen_with(listen, "127.0.0.1:0").unwrap();
+        let connect = TransportConnect::Ws(connect_config);
+        let (endpoint, _) = controller.connect_with(connect, addr).unwrap();
+
+        let message = (0..256 * 1024).map(|index| (index % 251) as u8).collect::<Vec<_>>();
+        let mut fragments = Vec::new();
+        let mut answer = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(_, established) => {
+                assert!(established);
+                let mut chunks = message.chunks(CHUNK_SIZE).peekable();
+                while let Some(chunk) = chunks.next() {
+                    let last = chunks.peek().is_none();
+                    assert_eq!(controller.send_fragment(endpoint, chunk, last), SendStatus::Sent);
+                    if !last {
+                        let status = controller.send(endpoint, b"interleaved");
+                        assert_eq!(status, SendStatus::ResourceNotAvailable);
+                    }
+                }
+                assert_eq!(controller.send_text(endpoint, "text"), SendStatus::Sent);
+            }
+            NetEvent::Accepted(..) => (),
+            NetEvent::Message(message_endpoint, data) => {
+                if message_endpoint == endpoint {
+                    answer = Some(data.to_vec());
+                }
+                else {
+                    let complete = controller.is_message_complete(message_endpoint);
+                    let kind = controller.message_kind(message_endpoint);
+                    fragments.push((data.to_vec(), kind, complete));
+                    if complete && kind == MessageKind::Binary {
+                        let size = fragments.iter().map(|(data, ..)| data.len()).sum::<usize>();
+                        let answer = size.to_string();
+                        assert_eq!(
+                            controller.send(message_endpoint, answer.as_bytes()),
+                            SendStatus::Sent
+                        );
+                    }
+                }
+            }
+            NetEvent::Disconnected(_) => unreachable!(),
+        });
+        assert_eq!(answer, Some(message.len().to_string().into_bytes()));
+
+        let (text, kind, complete) = fragments.pop().unwrap();
+        assert_eq!((text.as_slice(), kind, complete), (&b"text"[..], MessageKind::Text, true));
+        assert_eq!(fragments.len(), message.len() / CHUNK_SIZE);
+        assert!(fragments.iter().all(|(_, kind, _)| *kind == MessageKind::Binary));
+        let (last, others) = fragments.split_last().unwrap();
+        assert!(last.2 && others.iter().all(|(_, _, complete)| !complete));
+        let data = fragments.into_iter().flat_map(|(data, ..)| data).collect::<Vec<_>>();
+        assert_eq!(data, message);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_send_fragments_to_web_socket() {
+        use tungstenite::{Message};
+
+        let (controller, mut processor) = self::split();
+        let (_, addr) = controller.listen(Transport::Ws, "127.0.0.1:0").unwrap();
+
+        let mut client = NamespacedThread::spawn("test-ws-client", move || {
+            let (mut web_socket, _) = tungstenite::connect(format!("ws://{addr}/")).unwrap();
+            let message = web_socket.read().unwrap();
+            web_socket.close(None).unwrap();
+            while web_socket.read().is_ok() {}
+            message
+        });
+
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Accepted(endpoint, _) => {
+                for (fragment, last) in [("frag", false), ("men", false), ("ted", true)] {
+                    let status = controller.send_fragment(endpoint, fragment.as_bytes(), last);
+                    assert_eq!(status, SendStatus::Sent);
+                }
+            }
+            NetEvent::Disconnected(_) => (),
+            _ => unreachable!(),
+        });
+        assert_eq!(client.join(), Message::Binary("fragmented".into()));
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_ping_keepalive() {
+        use crate::adapters::ws::{WsConnectConfig, WsListenConfig};
+
+        let (controller, mut processor) = self::split();
+        let interval = Duration::from_millis(50);
+        let config = WsListenConfig::default().with_ping(interval, 2);
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+        let config = WsConnectConfig::default().with_ping(interval, 2);
+        let (endpoint, _) = controller.connect_with(TransportConnect::Ws(config), addr).unwrap();
+
+        let mut accepted = None;
+        let deadline = Instant::now() + *TIMEOUT;
+        while Instant::now() < deadline {
+            processor.process_poll_event(Some(*TIMEOUT / 10), |net_event| match net_event {
+                NetEvent::Connected(_, established) => assert!(established),
+                NetEvent::Accepted(endpoint, _) => accepted = Some(endpoint),
+                _ => unreachable!(),
+            });
+        }
+
+        // Both sides have been kept alive measuring the round trip time.
+        let accepted = accepted.unwrap();
+        assert!(controller.round_trip_time(endpoint.resource_id()).is_some());
+        assert!(controller.round_trip_time(accepted.resource_id()).is_some());
+        assert_eq!(controller.is_ready(accepted.resource_id()), Some(true));
+        assert_eq!(controller.disconnect_reason(accepted.resource_id()), None);
+    }
+
+    #[cfg(feature = "websocket")]
+    #[test]
+    fn ws_ping_timeout() {
+        use crate::adapters::ws::{WsListenConfig};
+
+        let (controller, mut processor) = self::split();
+        let config = WsListenConfig::default().with_ping(Duration::from_millis(50), 2);
+        let (_, addr) = controller.listen_with(TransportListen::Ws(config), "127.0.0.1:0").unwrap();
+
+        // The client never reads, so it never answers the pings.
+        let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+        let mut client = NamespacedThread::spawn("test-ws-client", move || {
+            let _web_socket = tungstenite::connect(format!("ws://{addr}/")).unwrap();
+            done_receiver.recv().ok();
+        });
+
+        let mut reason = None;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Accepted(..) => (),
+            NetEvent::Disconnected(endpoint) => {
+                reason = controller.disconnect_reason(endpoint.resource_id());
+                assert_eq!(controller.round_trip_time(endpoint.resource_id()), None);
+            }
+            _ => unreachable!(),
+        });
+        drop(done_sender);
+        client.join();
+
+        assert_eq!(reason, Some(DisconnectReason::TimedOut));
+    }
+
+    #[test]
+    fn sharded_connections() {
+        const SHARDS: usize = 4;
+        const CONNECTIONS: usize = 8;
+
+        let (controller, mut processors) = self::split_sharded(SHARDS, ShardBalance::RoundRobin);
+        let (_, addr) = controller.listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        let endpoints = (0..CONNECTIONS)
+            .map(|_| controller.connect(Transport::FramedTcp, addr).unwrap().0)
+            .collect::<Vec<_>>();
+
+        // The listener takes the first shard, the connections the following ones.
+        let shards = endpoints.iter().map(|e| e.resource_id().shard()).collect::<Vec<_>>();
+        assert_eq!(shards, [1, 2, 3, 0, 1, 2, 3, 0]);
+
+        let mut connected = 0;
+        let mut accepted = 0;
+        for (shard, processor) in processors.iter_mut().enumerate() {
+            processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+                NetEvent::Connected(endpoint, status) => {
+                    assert!(status);
+                    assert_eq!(endpoint.resource_id().shard(), shard);
+                    connected += 1;
+                }
+                NetEvent::Accepted(endpoint, _) => {
+                    assert_eq!(endpoint.resource_id().shard(), shard);
+                    accepted += 1;
+                }
+                _ => unreachable!(),
+            });
+        }
+        assert_eq!(connected, CONNECTIONS);
+        assert_eq!(accepted, CONNECTIONS);
+    }
+
+    #[cfg(unix)]
+    #[cfg_attr(feature = "tcp", test_case(Transport::Tcp))]
+    #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp))]
+    fn listen_group(transport: Transport) {
+        const SHARDS: usize = 4;
+        const CONNECTIONS: usize = 32;
+
+        let (controller, mut processors) = self::split_sharded(SHARDS, ShardBalance::RoundRobin);
+        let (listener_ids, addr) = controller.listen_group(transport, "127.0.0.1:0").unwrap();
+        let shards = listener_ids.iter().map(|id| id.shard()).collect::<Vec<_>>();
+        assert_eq!(shards, [0, 1, 2, 3]);
+
+        for _ in 0..CONNECTIONS {
+            controller.connect(transport, addr).unwrap();
+        }
+
+        let mut accepted_by = std::collections::HashSet::new();
+        let mut accepted = 0;
+        for (shard, processor) in processors.iter_mut().enumerate() {
+            processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| {
+                if let NetEvent::Accepted(endpoint, listener_id) = net_event {
+                    // The connection is kept in the shard of the listener that accepted it.
+                    assert_eq!(endpoint.resource_id().shard(), shard);
+                    assert_eq!(listener_id.shard(), shard);
+                    accepted_by.insert(listener_id);
+                    accepted += 1;
+                }
+            });
+        }
+        assert_eq!(accepted, CONNECTIONS);
+        assert!(accepted_by.len() > 1);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "udp"))]
+    #[test]
+    fn udp_multicast_ipv6() {
+        use crate::adapters::udp::{UdpListenConfig};
+        use nix::ifaddrs::{getifaddrs};
+        use nix::net::if_::{if_nametoindex, InterfaceFlags};
+        use std::net::{SocketAddrV6, Ipv6Addr};
+
+        // An interface with IPv6 that supports multicast, e.g. the loopback one.
+        let flags = InterfaceFlags::IFF_UP | InterfaceFlags::IFF_MULTICAST;
+        let interface = getifaddrs().unwrap().find_map(|ifaddr| {
+            let ipv6 = ifaddr.address.is_some_and(|address| address.as_sockaddr_in6().is_some());
+            match ipv6 && ifaddr.flags.contains(flags) {
+                true => Some(if_nametoindex(ifaddr.interface_name.as_str()).unwrap()),
+                false => None,
+            }
+        });
+        let interface = match interface {
+            Some(interface) => interface,
+            None => return log::warn!("No interface with IPv6 multicast, test skipped"),
+        };
+
+        let (controller, mut processor) = self::split();
+        let group = "ff02::1:3".parse().unwrap();
+        let config = UdpListenConfig::default().with_multicast_interface_v6(interface);
+        let listen = TransportListen::Udp(config);
+        let addr = SocketAddr::V6(SocketAddrV6::new(group, 0, 0, 0));
+        let (listener_id, local_addr) = controller.listen_with(listen, addr).unwrap();
+        assert_eq!(local_addr.ip(), Ipv6Addr::UNSPECIFIED);
+
+        let addr = SocketAddrV6::new(group, local_addr.port(), 0, interface);
+        controller.connect(Transport::Udp, addr).unwrap();
+
+        // An IPv4 datagram to the same port is not received.
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(&[0], ("127.0.0.1", local_addr.port())).unwrap();
+
+        let mut messages = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(endpoint, established) => {
+                assert!(established);
+                assert_eq!(controller.send(endpoint, &[42]), SendStatus::Sent);
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(endpoint.resource_id(), listener_id);
+                messages.push(data.to_vec());
+            }
+            _ => unreachable!(),
+        });
+        assert_eq!(messages, [vec![42]]);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "udp"))]
+    #[test]
+    fn udp_multicast_config() {
+        use crate::adapters::udp::{UdpConnectConfig, UdpListenConfig};
+        use nix::ifaddrs::{getifaddrs};
+        use nix::net::if_::{InterfaceFlags};
+        use std::net::{Ipv4Addr};
+
+        // The address of an interface that supports multicast.
+        let flags = InterfaceFlags::IFF_UP | InterfaceFlags::IFF_MULTICAST;
+        let interface = getifaddrs().unwrap().find_map(|ifaddr| {
+            let address = ifaddr.address?.as_sockaddr_in()?.ip();
+            ifaddr.flags.contains(flags).then(|| Ipv4Addr::from(address))
+        });
+        let interface = match interface {
+            Some(interface) => interface,
+            None => return log::warn!("No interface with IPv4 multicast, test skipped"),
+        };
+
+        let (controller, mut processor) = self::split();
+        let groups: [Ipv4Addr; 4] = [
+            "239.255.0.21".parse().unwrap(),
+            "239.255.0.22".parse().unwrap(),
+            "232.1.1.21".parse().unwrap(),
+            "232.1.1.22".parse().unwrap(), // Only from other source, not received.
+        ];
+        let config = UdpListenConfig::default()
+            .with_multicast_interface_v4(interface)
+            .with_multicast_group(groups[0].into())
+            .with_multicast_group(groups[1].into())
+            .with_source_multicast_group(groups[2], interface)
+            .with_source_multicast_group(groups[3], Ipv4Addr::new(203, 0, 113, 1));
+        let listen = TransportListen::Udp(config);
+        let (listener_id, local_addr) = controller.listen_with(listen, "0.0.0.0:0").unwrap();
+
+        let config = UdpConnectConfig::default()
+            .with_multicast_interface_v4(interface)
+            .with_multicast_ttl(2);
+        let mut endpoints = Vec::new();
+        for group in groups {
+            let connect = TransportConnect::Udp(config.clone());
+            let addr = SocketAddr::new(group.into(), local_addr.port());
+            endpoints.push(controller.connect_with(connect, addr).unwrap().0);
+        }
+        // The datagrams are not looped back to the listeners of the host.
+        let connect = TransportConnect::Udp(config.with_multicast_loop(false));
+        let addr = SocketAddr::new(groups[0].into(), local_addr.port());
+        endpoints.push(controller.connect_with(connect, addr).unwrap().0);
+
+        let mut messages = Vec::new();
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(endpoint, established) => {
+                assert!(established);
+                let id = endpoint.resource_id();
+                let ttl = controller.socket_option(id, SocketOptionName::MulticastTtl).unwrap();
+                assert_eq!(ttl, SocketOption::MulticastTtl(2));
+                let index = endpoints.iter().position(|other| *other == endpoint).unwrap();
+                assert_eq!(controller.send(endpoint, &[index as u8]), SendStatus::Sent);
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(endpoint.resource_id(), listener_id);
+                messages.push(data[0]);
+            }
+            _ => unreachable!(),
+        });
+        messages.sort();
+        assert_eq!(messages, [0, 1, 2]);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "udp"))]
+    #[test]
+    fn udp_multicast_listener_handover() {
+        use crate::adapters::udp::{UdpConnectConfig, UdpListenConfig};
+        use nix::ifaddrs::{getifaddrs};
+        use nix::net::if_::{InterfaceFlags};
+        use std::net::{Ipv4Addr};
+
+        // The address of an interface that supports multicast.
+        let flags = InterfaceFlags::IFF_UP | InterfaceFlags::IFF_MULTICAST;
+        let interface = getifaddrs().unwrap().find_map(|ifaddr| {
+            let address = ifaddr.address?.as_sockaddr_in()?.ip();
+            ifaddr.flags.contains(flags).then(|| Ipv4Addr::from(address))
+        });
+        let interface = match interface {
+            Some(interface) => interface,
+            None => return log::warn!("No interface with IPv4 multicast, test skipped"),
+        };
+
+        let (old_stream, new_stream) = UnixStream::pair().unwrap();
+        let group: Ipv4Addr = "239.255.0.23".parse().unwrap();
+
+        let (old_controller, old_processor) = self::split();
+        let config = UdpListenConfig::default()
+            .with_multicast_interface_v4(interface)
+            .with_multicast_group(group.into());
+        let listen = TransportListen::Udp(config);
+        let (old_id, local_addr) = old_controller.listen_with(listen, "0.0.0.0:0").unwrap();
+        old_controller.hand_over_listeners(&old_stream).unwrap();
+
+        let (controller, mut processor) = self::split();
+        let listeners = controller.take_over_listeners(&new_stream).unwrap();
+
+        // The membership of the group remains after the old process stops listening.
+        assert!(old_controller.remove(old_id));
+        drop((old_controller, old_processor));
+
+        let config = UdpConnectConfig::default().with_multicast_interface_v4(interface);
+        let connect = TransportConnect::Udp(config);
+        let addr = SocketAddr::new(group.into(), local_addr.port());
+        controller.connect_with(connect, addr).unwrap();
+
+        let mut received = false;
+        processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| match net_event {
+            NetEvent::Connected(endpoint, established) => {
+                assert!(established);
+                assert_eq!(controller.send(endpoint, &[42]), SendStatus::Sent);
+            }
+            NetEvent::Message(endpoint, data) => {
+                assert_eq!(endpoint.resource_id(), listeners[0].0);
+                assert_eq!(data, [42]);
+                received = true;
+            }
+            _ => unreachable!(),
+        });
+        assert!(received);
+    }
+
+    #[cfg(all(unix, feature = "udp"))]
+    #[test]
+    fn listen_group_udp() {
+        let (controller, mut processors) = self::split_sharded(2, ShardBalance::RoundRobin);
+        let (listener_ids, addr) = controller.listen_group(Transport::Udp, "127.0.0.1:0").unwrap();
+        assert_eq!(listener_ids.len(), 2);
+
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(&[42], addr).unwrap();
+
+        let mut received = 0;
+        for processor in processors.iter_mut() {
+            processor.process_poll_events_until_timeout(*TIMEOUT, |net_event| {
+                if let NetEvent::Message(endpoint, data) = net_event {
+                    assert!(listener_ids.contains(&endpoint.resource_id()));
+                    assert_eq!(data, [42]);
+                    received += 1;
+                }
+            });
+        }
+        assert_eq!(received, 1);
+    }
 }
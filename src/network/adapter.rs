@@ -5,8 +5,13 @@ use super::poll::{Readiness};
 
 use mio::event::{Source};
 
+use socket2::{Socket};
+
 use std::net::{SocketAddr};
 use std::io::{self};
+use std::any::{Any};
+use std::sync::{Arc};
+use std::time::{Duration};
 
 /// High level trait to represent an adapter for a transport protocol.
 /// The adapter is only used to identify the resources of your adapter.
@@ -31,6 +36,14 @@ pub trait Resource: Send + Sync {
     /// one of them as a base for your non-blocking transport.
     /// See [`Source`].
     fn source(&mut self) -> &mut dyn Source;
+
+    /// Calls `action` with the OS socket of the resource in order to get or set its options.
+    /// See [`crate::network::NetworkController::set_socket_option()`].
+    /// By default, the resource is considered to have no socket and an
+    /// [`std::io::ErrorKind::Unsupported`] error is returned.
+    fn with_socket(&self, _action: &mut dyn FnMut(&Socket) -> io::Result<()>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "The resource has no socket"))
+    }
 }
 
 /// Plain struct used as a returned value of [`Remote::connect_with()`]
@@ -79,6 +92,19 @@ pub enum SendStatus {
     ResourceNotAvailable,
 }
 
+/// Kind of a message, for the transports that distinguish them as WebSocket.
+/// See [`crate::network::NetworkController::message_kind()`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageKind {
+    /// Raw bytes.
+    /// The messages of transports that do not distinguish kinds are always binary.
+    Binary,
+
+    /// UTF-8 text, as the messages sent by
+    /// [`crate::network::NetworkController::send_text()`].
+    Text,
+}
+
 /// Returned as a result of [`Remote::receive()`]
 #[derive(Debug)]
 pub enum ReadStatus {
@@ -130,6 +156,17 @@ pub trait Remote: Resource + Sized {
         remote_addr: RemoteAddr,
     ) -> io::Result<ConnectionInfo<Self>>;
 
+    /// Called when the user adopts an already connected socket
+    /// (see [`crate::network::NetworkController::adopt_stream()`]).
+    /// The **implementator** is in change of creating the corresponding remote resource
+    /// over the given socket.
+    /// Like a connected resource, [`Remote::pending()`] will be called until it is ready.
+    /// By default, the adapter does not support it and an
+    /// [`std::io::ErrorKind::Unsupported`] error is returned.
+    fn adopt_with(_config: TransportConnect, _socket: Socket) -> io::Result<ConnectionInfo<Self>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "The transport can not adopt streams"))
+    }
+
     /// Called when a remote resource received an event.
     /// The resource must be *ready* to receive this call.
     /// It means that it has available data to read,
@@ -149,6 +186,35 @@ pub trait Remote: Resource + Sized {
     /// The [`SendStatus`] will contain the status of this attempt.
     fn send(&self, data: &[u8]) -> SendStatus;
 
+    /// Sends a text message from a resource.
+    /// By default, the transport has no text messages and the bytes of the text are sent
+    /// as in [`Remote::send()`].
+    fn send_text(&self, text: &str) -> SendStatus {
+        self.send(text.as_bytes())
+    }
+
+    /// Sends a fragment of a message from a resource, the `last` one completes the message.
+    /// By default, the transport has no fragmented messages and each fragment is sent
+    /// as in [`Remote::send()`].
+    fn send_fragment(&self, data: &[u8], _last: bool) -> SendStatus {
+        self.send(data)
+    }
+
+    /// Returns the kind of the message that is being processed by the `process_data` callback
+    /// of [`Remote::receive()`].
+    /// By default, all the messages are binary.
+    fn message_kind(&self) -> MessageKind {
+        MessageKind::Binary
+    }
+
+    /// Returns `true` if the data that is being processed by the `process_data` callback
+    /// of [`Remote::receive()`] completes its message, or `false` if it is a fragment
+    /// followed by others.
+    /// By default, the messages are processed entirely.
+    fn message_complete(&self) -> bool {
+        true
+    }
+
     /// Called when a `Remote` is created (explicity of by a listener)
     /// and it is not consider ready yet.
     /// A remote resource **is considered ready** when it is totally connected
@@ -162,6 +228,37 @@ pub trait Remote: Resource + Sized {
     /// returns a `PendingStatus::Ready` or `PendingStatus::Disconnected`.
     fn pending(&self, readiness: Readiness) -> PendingStatus;
 
+    /// Returns the metadata of the remote given by the adapter,
+    /// e.g. the request received while performing a handshake.
+    /// See [`crate::network::NetworkController::metadata()`].
+    /// By default, the remote has no metadata.
+    fn metadata(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        None
+    }
+
+    /// Interval in which [`Remote::keepalive()`] is called once the remote is ready.
+    /// By default, the remote has no keepalive and it is never called.
+    fn keepalive_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called each [`Remote::keepalive_interval()`] from the thread that processes the network.
+    /// Here the **implementator** can send a keepalive probe and check that the peer
+    /// answered the previous ones.
+    /// If the method returns `false`, the peer is considered dead: the resource will be removed
+    /// and a [`crate::network::NetEvent::Disconnected`] will be generated with
+    /// [`crate::network::DisconnectReason::TimedOut`] as reason.
+    fn keepalive(&self) -> bool {
+        true
+    }
+
+    /// Returns the last round trip time measured by the keepalive probes.
+    /// See [`crate::network::NetworkController::round_trip_time()`].
+    /// By default, the remote does not measure it.
+    fn round_trip_time(&self) -> Option<Duration> {
+        None
+    }
+
     /// The resource is available to write.
     /// It must be *ready* to receive this call.
     /// Here the **implementator** optionally can try to write any pending data.
@@ -206,6 +303,16 @@ pub trait Local: Resource + Sized {
     /// variants can be safely ignored.
     fn listen_with(config: TransportListen, addr: SocketAddr) -> io::Result<ListeningInfo<Self>>;
 
+    /// Called when the user adopts an already listening socket
+    /// (see [`crate::network::NetworkController::adopt_listener()`]).
+    /// The **implementator** is in change of creating the corresponding local resource
+    /// over the given socket.
+    /// By default, the adapter does not support it and an
+    /// [`std::io::ErrorKind::Unsupported`] error is returned.
+    fn adopt_with(_config: TransportListen, _socket: Socket) -> io::Result<ListeningInfo<Self>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "The transport can not adopt listeners"))
+    }
+
     /// Called when a local resource received an event.
     /// It means that some resource have tried to connect.
     /// The **implementator** is in charge of accepting this connection.
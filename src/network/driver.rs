@@ -2,19 +2,33 @@ use super::endpoint::{Endpoint};
 use super::resource_id::{ResourceId, ResourceType};
 use super::poll::{Poll, Readiness};
 use super::registry::{ResourceRegistry, Register};
+use super::shard::{ShardSelector};
 use super::remote_addr::{RemoteAddr};
-use super::adapter::{Adapter, Remote, Local, SendStatus, AcceptedType, ReadStatus, PendingStatus};
-use super::transport::{TransportConnect, TransportListen};
+use super::adapter::{
+    Adapter, Resource, Remote, Local, SendStatus, MessageKind, AcceptedType, ReadStatus,
+    PendingStatus, ConnectionInfo, ListeningInfo,
+};
+use super::transport::{Transport, TransportConnect, TransportListen};
+use super::socket_option::{SocketOption, SocketOptionName};
+
+use crate::util::thread::{OTHER_THREAD_ERR};
 
 use std::net::{SocketAddr};
+use std::collections::{BinaryHeap};
+use std::cmp::{Reverse};
 use std::sync::{
-    Arc,
+    Arc, Mutex,
     atomic::{AtomicBool, Ordering},
 };
 use std::io::{self};
+use std::any::{Any};
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use std::task::{Waker};
 
-#[cfg(doctest)]
-use super::transport::{Transport};
+/// Data attached by the user to a resource.
+/// See [`crate::network::NetworkController::set_user_data()`].
+pub type UserData = Arc<dyn Any + Send + Sync>;
 
 /// Enum used to describe a network event that an internal transport adapter has produced.
 pub enum NetEvent<'a> {
@@ -49,6 +63,7 @@ pub enum NetEvent<'a> {
     /// Remove explicitely a resource will NOT generate the event.
     /// When this event is received, the resource is considered already removed,
     /// the user do not need to remove it after this event.
+    /// Its user data is still available while this event is processed.
     /// A [`NetEvent::Message`] event will never be generated after this event from this endpoint.
     ///
     /// Note that this event will only be generated by connection-oriented transports as *TCP*.
@@ -56,6 +71,44 @@ pub enum NetEvent<'a> {
     Disconnected(Endpoint),
 }
 
+/// Snapshot of the state of a network resource.
+/// See [`crate::network::NetworkController::resources()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceInfo {
+    /// Id of the resource.
+    pub id: ResourceId,
+
+    /// Transport used by the resource.
+    pub transport: Transport,
+
+    /// Local address of the resource.
+    pub local_addr: SocketAddr,
+
+    /// Address of the peer. It is `None` for the listeners.
+    pub peer_addr: Option<SocketAddr>,
+
+    /// Listener that accepted the connection, if any.
+    pub listener: Option<ResourceId>,
+
+    /// Tells if the resource is ready, see [`crate::network::NetworkController::is_ready()`].
+    pub ready: bool,
+
+    /// Time since the resource was created.
+    pub age: Duration,
+}
+
+/// Reason of a lost connection.
+/// See [`crate::network::NetworkController::disconnect_reason()`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisconnectReason {
+    /// The connection has been closed by the peer or lost by an error.
+    Closed,
+
+    /// The peer has not answered the keepalive probes in time,
+    /// e.g. the WebSocket pings (see [`crate::adapters::ws::WsConnectConfig::with_ping()`]).
+    TimedOut,
+}
+
 impl std::fmt::Debug for NetEvent<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
@@ -79,24 +132,74 @@ pub trait ActionController: Send + Sync {
         config: TransportListen,
         addr: SocketAddr,
     ) -> io::Result<(ResourceId, SocketAddr)>;
+    fn listen_group_with(
+        &self,
+        config: TransportListen,
+        addr: SocketAddr,
+    ) -> io::Result<(Vec<ResourceId>, SocketAddr)>;
+    fn adopt_stream_with(
+        &self,
+        config: TransportConnect,
+        socket: socket2::Socket,
+    ) -> io::Result<(Endpoint, SocketAddr)>;
+    fn adopt_listener_with(
+        &self,
+        config: TransportListen,
+        socket: socket2::Socket,
+    ) -> io::Result<(ResourceId, SocketAddr)>;
     fn send(&self, endpoint: Endpoint, data: &[u8]) -> SendStatus;
+    fn send_text(&self, endpoint: Endpoint, text: &str) -> SendStatus;
+    fn send_fragment(&self, endpoint: Endpoint, data: &[u8], last: bool) -> SendStatus;
+    fn message_kind(&self, id: ResourceId) -> MessageKind;
+    fn is_message_complete(&self, id: ResourceId) -> bool;
     fn remove(&self, id: ResourceId) -> bool;
     fn is_ready(&self, id: ResourceId) -> Option<bool>;
+    fn set_user_data(&self, id: ResourceId, data: Option<UserData>) -> bool;
+    fn user_data(&self, id: ResourceId) -> Option<UserData>;
+    fn metadata(&self, id: ResourceId) -> Option<Arc<dyn Any + Send + Sync>>;
+    fn round_trip_time(&self, id: ResourceId) -> Option<Duration>;
+    fn disconnect_reason(&self, id: ResourceId) -> Option<DisconnectReason>;
+    fn resources(&self, callback: &mut dyn FnMut(ResourceInfo));
+    fn set_socket_option(&self, id: ResourceId, option: SocketOption) -> io::Result<()>;
+    fn socket_option(&self, id: ResourceId, name: SocketOptionName) -> io::Result<SocketOption>;
+    fn try_clone_socket(&self, id: ResourceId) -> io::Result<socket2::Socket>;
+    #[cfg(feature = "async")]
+    fn register_ready_waker(&self, id: ResourceId, waker: &Waker) -> Option<bool>;
 }
 
 pub trait EventProcessor: Send + Sync {
     fn process(&self, id: ResourceId, readiness: Readiness, callback: &mut dyn FnMut(NetEvent<'_>));
+    fn next_keepalive(&self, shard: usize) -> Option<Instant>;
+    fn process_keepalives(&self, shard: usize, callback: &mut dyn FnMut(NetEvent<'_>));
 }
 
 struct RemoteProperties {
     peer_addr: SocketAddr,
+    local_addr: SocketAddr,
     local: Option<ResourceId>,
+    created: Instant,
     ready: AtomicBool,
+    closed: AtomicBool,
+    disconnect_reason: Mutex<Option<DisconnectReason>>,
+    user_data: Mutex<Option<UserData>>,
+    #[cfg(feature = "async")]
+    ready_wakers: Mutex<Vec<Waker>>,
 }
 
 impl RemoteProperties {
-    fn new(peer_addr: SocketAddr, local: Option<ResourceId>) -> Self {
-        Self { peer_addr, local, ready: AtomicBool::new(false) }
+    fn new(peer_addr: SocketAddr, local_addr: SocketAddr, local: Option<ResourceId>) -> Self {
+        Self {
+            peer_addr,
+            local_addr,
+            local,
+            created: Instant::now(),
+            ready: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            disconnect_reason: Mutex::new(None),
+            user_data: Mutex::new(None),
+            #[cfg(feature = "async")]
+            ready_wakers: Mutex::new(Vec::new()),
+        }
     }
 
     pub fn is_ready(&self) -> bool {
@@ -105,41 +208,208 @@ impl RemoteProperties {
 
     pub fn mark_as_ready(&self) {
         self.ready.store(true, Ordering::Relaxed);
+
+        #[cfg(feature = "async")]
+        self.wake_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    /// Marks the remote as closed.
+    /// Returns `false` if it was already closed.
+    pub fn close(&self) -> bool {
+        !self.closed.swap(true, Ordering::SeqCst)
+    }
+
+    /// Registers a waker to be woken once the remote is ready or removed.
+    /// Returns the readiness of the remote, the waker is only registered if it is not ready.
+    #[cfg(feature = "async")]
+    pub fn register_ready_waker(&self, waker: &Waker) -> bool {
+        let mut wakers = self.ready_wakers.lock().expect(OTHER_THREAD_ERR);
+        if self.is_ready() {
+            return true;
+        }
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+        false
+    }
+
+    #[cfg(feature = "async")]
+    fn wake_all(&self) {
+        let wakers = std::mem::take(&mut *self.ready_wakers.lock().expect(OTHER_THREAD_ERR));
+        wakers.into_iter().for_each(Waker::wake);
     }
 }
 
-struct LocalProperties;
+#[cfg(feature = "async")]
+impl Drop for RemoteProperties {
+    fn drop(&mut self) {
+        // The remote has been removed, the waiting futures must be notified.
+        self.wake_all();
+    }
+}
 
+struct LocalProperties {
+    /// The listener belongs to a listen group:
+    /// the accepted connections are kept in the shard of the listener.
+    in_group: bool,
+    local_addr: SocketAddr,
+    created: Instant,
+    user_data: Mutex<Option<UserData>>,
+}
+
+impl LocalProperties {
+    fn new(in_group: bool, local_addr: SocketAddr) -> Self {
+        Self { in_group, local_addr, created: Instant::now(), user_data: Mutex::new(None) }
+    }
+}
+
+/// Remotes waiting for their next keepalive, by its time and the raw value of its id.
+type KeepaliveQueue = Mutex<BinaryHeap<Reverse<(Instant, usize)>>>;
+
+/// The driver of an adapter.
+/// It has a registry by each shard (poll) of the network,
+/// the shard of each resource is encoded in its [`ResourceId`].
 pub struct Driver<R: Remote, L: Local> {
-    remote_registry: Arc<ResourceRegistry<R, RemoteProperties>>,
-    local_registry: Arc<ResourceRegistry<L, LocalProperties>>,
+    remote_registries: Arc<Vec<ResourceRegistry<R, RemoteProperties>>>,
+    local_registries: Arc<Vec<ResourceRegistry<L, LocalProperties>>>,
+    keepalive_queues: Arc<Vec<KeepaliveQueue>>,
+    selector: Arc<ShardSelector>,
 }
 
 impl<R: Remote, L: Local> Driver<R, L> {
     pub fn new(
         _: impl Adapter<Remote = R, Local = L>,
         adapter_id: u8,
-        poll: &mut Poll,
+        polls: &mut [Poll],
+        selector: Arc<ShardSelector>,
     ) -> Driver<R, L> {
-        let remote_poll_registry = poll.create_registry(adapter_id, ResourceType::Remote);
-        let local_poll_registry = poll.create_registry(adapter_id, ResourceType::Local);
+        let remote_registries = polls
+            .iter_mut()
+            .map(|poll| {
+                ResourceRegistry::new(poll.create_registry(adapter_id, ResourceType::Remote))
+            })
+            .collect();
+
+        let local_registries = polls
+            .iter_mut()
+            .map(|poll| {
+                ResourceRegistry::new(poll.create_registry(adapter_id, ResourceType::Local))
+            })
+            .collect();
+
+        let keepalive_queues = polls.iter().map(|_| KeepaliveQueue::default()).collect();
 
         Driver {
-            remote_registry: Arc::new(ResourceRegistry::<R, RemoteProperties>::new(
-                remote_poll_registry,
-            )),
-            local_registry: Arc::new(ResourceRegistry::<L, LocalProperties>::new(
-                local_poll_registry,
-            )),
+            remote_registries: Arc::new(remote_registries),
+            local_registries: Arc::new(local_registries),
+            keepalive_queues: Arc::new(keepalive_queues),
+            selector,
+        }
+    }
+
+    fn remote_registry(&self, id: ResourceId) -> &ResourceRegistry<R, RemoteProperties> {
+        &self.remote_registries[id.shard()]
+    }
+
+    fn local_registry(&self, id: ResourceId) -> &ResourceRegistry<L, LocalProperties> {
+        &self.local_registries[id.shard()]
+    }
+
+    fn register_remote(&self, info: ConnectionInfo<R>) -> io::Result<(Endpoint, SocketAddr)> {
+        let shard = self.selector.select(info.peer_addr);
+        let id = self.remote_registries[shard].register(
+            info.remote,
+            RemoteProperties::new(info.peer_addr, info.local_addr, None),
+            true,
+        )?;
+        Ok((Endpoint::new(id, info.peer_addr), info.local_addr))
+    }
+
+    fn register_local(&self, info: ListeningInfo<L>) -> io::Result<(ResourceId, SocketAddr)> {
+        let shard = self.selector.select(info.local_addr);
+        let properties = LocalProperties::new(false, info.local_addr);
+        let id = self.local_registries[shard].register(info.local, properties, false)?;
+        Ok((id, info.local_addr))
+    }
+
+    /// Sends through the remote with `send_remote` once it is ready,
+    /// or the `data` to the endpoint address if the resource is a local one.
+    fn send_by(
+        &self,
+        endpoint: Endpoint,
+        data: &[u8],
+        send_remote: impl FnOnce(&R) -> SendStatus,
+    ) -> SendStatus {
+        let id = endpoint.resource_id();
+        match id.resource_type() {
+            ResourceType::Remote => match self.remote(id) {
+                Some(remote) => match remote.properties.is_ready() {
+                    true => send_remote(&remote.resource),
+                    false => SendStatus::ResourceNotAvailable,
+                },
+                None => SendStatus::ResourceNotFound,
+            },
+            ResourceType::Local => match self.local_registry(id).get(id) {
+                Some(remote) => remote.resource.send_to(endpoint.addr(), data),
+                None => SendStatus::ResourceNotFound,
+            },
         }
     }
+
+    /// Returns the remote if it is not closed.
+    fn remote(&self, id: ResourceId) -> Option<Arc<Register<R, RemoteProperties>>> {
+        self.remote_registry(id).get(id).filter(|remote| !remote.properties.is_closed())
+    }
+
+    /// Calls `action` with the socket of the resource.
+    fn with_socket(
+        &self,
+        id: ResourceId,
+        action: &mut dyn FnMut(&socket2::Socket) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let result = match id.resource_type() {
+            ResourceType::Remote => self.remote(id).map(|r| r.resource.with_socket(action)),
+            ResourceType::Local => {
+                self.local_registry(id).get(id).map(|l| l.resource.with_socket(action))
+            }
+        };
+        result.unwrap_or_else(|| Err(io::Error::new(io::ErrorKind::NotFound, "Resource not found")))
+    }
+
+    /// Closes the remote and removes it from the registry after calling `on_close`.
+    /// While `on_close` is called, the remote is considered removed
+    /// but its user data and the `reason` are still available.
+    /// Returns `false` if the remote was already closed.
+    fn close_remote(
+        &self,
+        remote: &Register<R, RemoteProperties>,
+        id: ResourceId,
+        reason: DisconnectReason,
+        on_close: impl FnOnce(),
+    ) -> bool {
+        if !remote.properties.close() {
+            return false
+        }
+        *remote.properties.disconnect_reason.lock().expect(OTHER_THREAD_ERR) = Some(reason);
+        on_close();
+        self.remote_registry(id).deregister(id);
+        // The register could be alive out of the registry while it is processed.
+        remote.properties.user_data.lock().expect(OTHER_THREAD_ERR).take();
+        true
+    }
 }
 
 impl<R: Remote, L: Local> Clone for Driver<R, L> {
     fn clone(&self) -> Driver<R, L> {
         Driver {
-            remote_registry: self.remote_registry.clone(),
-            local_registry: self.local_registry.clone(),
+            remote_registries: self.remote_registries.clone(),
+            local_registries: self.local_registries.clone(),
+            keepalive_queues: self.keepalive_queues.clone(),
+            selector: self.selector.clone(),
         }
     }
 }
@@ -150,14 +420,7 @@ impl<R: Remote, L: Local> ActionController for Driver<R, L> {
         config: TransportConnect,
         addr: RemoteAddr,
     ) -> io::Result<(Endpoint, SocketAddr)> {
-        R::connect_with(config, addr).map(|info| {
-            let id = self.remote_registry.register(
-                info.remote,
-                RemoteProperties::new(info.peer_addr, None),
-                true,
-            );
-            (Endpoint::new(id, info.peer_addr), info.local_addr)
-        })
+        R::connect_with(config, addr).and_then(|info| self.register_remote(info))
     }
 
     fn listen_with(
@@ -165,39 +428,208 @@ impl<R: Remote, L: Local> ActionController for Driver<R, L> {
         config: TransportListen,
         addr: SocketAddr,
     ) -> io::Result<(ResourceId, SocketAddr)> {
-        L::listen_with(config, addr).map(|info| {
-            let id = self.local_registry.register(info.local, LocalProperties, false);
-            (id, info.local_addr)
-        })
+        L::listen_with(config, addr).and_then(|info| self.register_local(info))
+    }
+
+    fn listen_group_with(
+        &self,
+        config: TransportListen,
+        mut addr: SocketAddr,
+    ) -> io::Result<(Vec<ResourceId>, SocketAddr)> {
+        let mut ids = Vec::with_capacity(self.local_registries.len());
+        for registry in self.local_registries.iter() {
+            let registered = L::listen_with(config.clone(), addr).and_then(|info| {
+                // The next listeners must use the port chosen by the first one.
+                addr = info.local_addr;
+                let properties = LocalProperties::new(true, info.local_addr);
+                registry.register(info.local, properties, false)
+            });
+            match registered {
+                Ok(id) => ids.push(id),
+                Err(err) => {
+                    ids.iter().for_each(|&id| {
+                        self.local_registry(id).deregister(id);
+                    });
+                    return Err(err)
+                }
+            }
+        }
+        Ok((ids, addr))
+    }
+
+    fn adopt_stream_with(
+        &self,
+        config: TransportConnect,
+        socket: socket2::Socket,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        R::adopt_with(config, socket).and_then(|info| self.register_remote(info))
+    }
+
+    fn adopt_listener_with(
+        &self,
+        config: TransportListen,
+        socket: socket2::Socket,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        L::adopt_with(config, socket).and_then(|info| self.register_local(info))
     }
 
     fn send(&self, endpoint: Endpoint, data: &[u8]) -> SendStatus {
-        match endpoint.resource_id().resource_type() {
-            ResourceType::Remote => match self.remote_registry.get(endpoint.resource_id()) {
-                Some(remote) => match remote.properties.is_ready() {
-                    true => remote.resource.send(data),
-                    false => SendStatus::ResourceNotAvailable,
-                },
-                None => SendStatus::ResourceNotFound,
-            },
-            ResourceType::Local => match self.local_registry.get(endpoint.resource_id()) {
-                Some(remote) => remote.resource.send_to(endpoint.addr(), data),
-                None => SendStatus::ResourceNotFound,
-            },
+        self.send_by(endpoint, data, |remote| remote.send(data))
+    }
+
+    fn send_text(&self, endpoint: Endpoint, text: &str) -> SendStatus {
+        self.send_by(endpoint, text.as_bytes(), |remote| remote.send_text(text))
+    }
+
+    fn send_fragment(&self, endpoint: Endpoint, data: &[u8], last: bool) -> SendStatus {
+        self.send_by(endpoint, data, |remote| remote.send_fragment(data, last))
+    }
+
+    fn message_kind(&self, id: ResourceId) -> MessageKind {
+        match id.resource_type() {
+            ResourceType::Remote => self.remote(id).map(|r| r.resource.message_kind()),
+            ResourceType::Local => None,
+        }
+        .unwrap_or(MessageKind::Binary)
+    }
+
+    fn is_message_complete(&self, id: ResourceId) -> bool {
+        match id.resource_type() {
+            ResourceType::Remote => self.remote(id).map(|r| r.resource.message_complete()),
+            ResourceType::Local => None,
         }
+        .unwrap_or(true)
     }
 
     fn remove(&self, id: ResourceId) -> bool {
         match id.resource_type() {
-            ResourceType::Remote => self.remote_registry.deregister(id),
-            ResourceType::Local => self.local_registry.deregister(id),
+            ResourceType::Remote => match self.remote_registry(id).get(id) {
+                Some(remote) => self.close_remote(&remote, id, DisconnectReason::Closed, || ()),
+                None => false,
+            },
+            ResourceType::Local => self.local_registry(id).deregister(id),
         }
     }
 
     fn is_ready(&self, id: ResourceId) -> Option<bool> {
         match id.resource_type() {
-            ResourceType::Remote => self.remote_registry.get(id).map(|r| r.properties.is_ready()),
-            ResourceType::Local => self.local_registry.get(id).map(|_| true),
+            ResourceType::Remote => self.remote(id).map(|r| r.properties.is_ready()),
+            ResourceType::Local => self.local_registry(id).get(id).map(|_| true),
+        }
+    }
+
+    fn set_user_data(&self, id: ResourceId, data: Option<UserData>) -> bool {
+        let user_data = |stored: &Mutex<Option<UserData>>| {
+            *stored.lock().expect(OTHER_THREAD_ERR) = data;
+        };
+        match id.resource_type() {
+            ResourceType::Remote => {
+                self.remote_registry(id).get(id).map(|r| user_data(&r.properties.user_data))
+            }
+            ResourceType::Local => {
+                self.local_registry(id).get(id).map(|l| user_data(&l.properties.user_data))
+            }
+        }
+        .is_some()
+    }
+
+    fn user_data(&self, id: ResourceId) -> Option<UserData> {
+        let user_data =
+            |stored: &Mutex<Option<UserData>>| stored.lock().expect(OTHER_THREAD_ERR).clone();
+        match id.resource_type() {
+            ResourceType::Remote => {
+                self.remote_registry(id).get(id).and_then(|r| user_data(&r.properties.user_data))
+            }
+            ResourceType::Local => {
+                self.local_registry(id).get(id).and_then(|l| user_data(&l.properties.user_data))
+            }
+        }
+    }
+
+    fn metadata(&self, id: ResourceId) -> Option<Arc<dyn Any + Send + Sync>> {
+        match id.resource_type() {
+            ResourceType::Remote => self.remote(id).and_then(|r| r.resource.metadata()),
+            ResourceType::Local => None,
+        }
+    }
+
+    fn round_trip_time(&self, id: ResourceId) -> Option<Duration> {
+        match id.resource_type() {
+            ResourceType::Remote => self.remote(id).and_then(|r| r.resource.round_trip_time()),
+            ResourceType::Local => None,
+        }
+    }
+
+    fn disconnect_reason(&self, id: ResourceId) -> Option<DisconnectReason> {
+        match id.resource_type() {
+            ResourceType::Remote => self
+                .remote_registry(id)
+                .get(id)
+                .and_then(|r| *r.properties.disconnect_reason.lock().expect(OTHER_THREAD_ERR)),
+            ResourceType::Local => None,
+        }
+    }
+
+    fn resources(&self, callback: &mut dyn FnMut(ResourceInfo)) {
+        for registry in self.local_registries.iter() {
+            registry.for_each(|id, local| {
+                callback(ResourceInfo {
+                    id,
+                    transport: Transport::from(id.adapter_id()),
+                    local_addr: local.properties.local_addr,
+                    peer_addr: None,
+                    listener: None,
+                    ready: true,
+                    age: local.properties.created.elapsed(),
+                })
+            });
+        }
+        for registry in self.remote_registries.iter() {
+            registry.for_each(|id, remote| {
+                if !remote.properties.is_closed() {
+                    callback(ResourceInfo {
+                        id,
+                        transport: Transport::from(id.adapter_id()),
+                        local_addr: remote.properties.local_addr,
+                        peer_addr: Some(remote.properties.peer_addr),
+                        listener: remote.properties.local,
+                        ready: remote.properties.is_ready(),
+                        age: remote.properties.created.elapsed(),
+                    })
+                }
+            });
+        }
+    }
+
+    fn set_socket_option(&self, id: ResourceId, option: SocketOption) -> io::Result<()> {
+        self.with_socket(id, &mut |socket| option.apply(socket))
+    }
+
+    fn socket_option(&self, id: ResourceId, name: SocketOptionName) -> io::Result<SocketOption> {
+        let mut value = None;
+        self.with_socket(id, &mut |socket| {
+            value = Some(name.read(socket)?);
+            Ok(())
+        })?;
+        Ok(value.expect("The socket action has been called"))
+    }
+
+    fn try_clone_socket(&self, id: ResourceId) -> io::Result<socket2::Socket> {
+        let mut clone = None;
+        self.with_socket(id, &mut |socket| {
+            clone = Some(socket.try_clone()?);
+            Ok(())
+        })?;
+        Ok(clone.expect("The socket action has been called"))
+    }
+
+    #[cfg(feature = "async")]
+    fn register_ready_waker(&self, id: ResourceId, waker: &Waker) -> Option<bool> {
+        match id.resource_type() {
+            ResourceType::Remote => {
+                self.remote(id).map(|r| r.properties.register_ready_waker(waker))
+            }
+            ResourceType::Local => self.local_registry(id).get(id).map(|_| true),
         }
     }
 }
@@ -211,7 +643,7 @@ impl<R: Remote, L: Local<Remote = R>> EventProcessor for Driver<R, L> {
     ) {
         match id.resource_type() {
             ResourceType::Remote => {
-                if let Some(remote) = self.remote_registry.get(id) {
+                if let Some(remote) = self.remote(id) {
                     let endpoint = Endpoint::new(id, remote.properties.peer_addr);
                     log::trace!("Processed remote for {}", endpoint);
 
@@ -233,7 +665,7 @@ impl<R: Remote, L: Local<Remote = R>> EventProcessor for Driver<R, L> {
                 }
             }
             ResourceType::Local => {
-                if let Some(local) = self.local_registry.get(id) {
+                if let Some(local) = self.local_registry(id).get(id) {
                     log::trace!("Processed local for {}", id);
                     match readiness {
                         Readiness::Write => (),
@@ -243,9 +675,53 @@ impl<R: Remote, L: Local<Remote = R>> EventProcessor for Driver<R, L> {
             }
         }
     }
+
+    fn next_keepalive(&self, shard: usize) -> Option<Instant> {
+        let queue = self.keepalive_queues[shard].lock().expect(OTHER_THREAD_ERR);
+        queue.peek().map(|Reverse((time, _))| *time)
+    }
+
+    fn process_keepalives(&self, shard: usize, event_callback: &mut dyn FnMut(NetEvent<'_>)) {
+        let now = Instant::now();
+        loop {
+            // The queue is not locked while the events are processed.
+            let id = {
+                let mut queue = self.keepalive_queues[shard].lock().expect(OTHER_THREAD_ERR);
+                match queue.peek() {
+                    Some(Reverse((time, _))) if *time <= now => {
+                        let Reverse((_, raw_id)) = queue.pop().unwrap();
+                        ResourceId::from(raw_id)
+                    }
+                    _ => break,
+                }
+            };
+
+            // The remote could have been removed since it was scheduled.
+            if let Some(remote) = self.remote(id) {
+                if remote.resource.keepalive() {
+                    self.schedule_keepalive(&remote, id);
+                }
+                else {
+                    let endpoint = Endpoint::new(id, remote.properties.peer_addr);
+                    log::trace!("Keepalive timeout for {}", endpoint);
+                    self.close_remote(&remote, id, DisconnectReason::TimedOut, || {
+                        event_callback(NetEvent::Disconnected(endpoint))
+                    });
+                }
+            }
+        }
+    }
 }
 
 impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
+    /// Schedules the next keepalive of the remote, if it has keepalive.
+    fn schedule_keepalive(&self, remote: &Register<R, RemoteProperties>, id: ResourceId) {
+        if let Some(interval) = remote.resource.keepalive_interval() {
+            let mut queue = self.keepalive_queues[id.shard()].lock().expect(OTHER_THREAD_ERR);
+            queue.push(Reverse((Instant::now() + interval, id.raw())));
+        }
+    }
+
     fn resolve_pending_remote(
         &self,
         remote: &Arc<Register<R, RemoteProperties>>,
@@ -263,13 +739,15 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
                     None => event_callback(NetEvent::Connected(endpoint, true)),
                 }
                 remote.resource.ready_to_write();
+                self.schedule_keepalive(remote, endpoint.resource_id());
             }
             PendingStatus::Incomplete => (),
             PendingStatus::Disconnected => {
-                self.remote_registry.deregister(endpoint.resource_id());
-                if remote.properties.local.is_none() {
-                    event_callback(NetEvent::Connected(endpoint, false));
-                }
+                self.close_remote(remote, endpoint.resource_id(), DisconnectReason::Closed, || {
+                    if remote.properties.local.is_none() {
+                        event_callback(NetEvent::Connected(endpoint, false));
+                    }
+                });
             }
         }
     }
@@ -281,7 +759,9 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
         mut event_callback: impl FnMut(NetEvent<'_>),
     ) {
         if !remote.resource.ready_to_write() {
-            event_callback(NetEvent::Disconnected(endpoint));
+            self.close_remote(remote, endpoint.resource_id(), DisconnectReason::Closed, || {
+                event_callback(NetEvent::Disconnected(endpoint))
+            });
         }
     }
 
@@ -295,10 +775,10 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
             remote.resource.receive(|data| event_callback(NetEvent::Message(endpoint, data)));
         log::trace!("Receive status: {:?}", status);
         if let ReadStatus::Disconnected = status {
-            // Checked because, the user in the callback could have removed the same resource.
-            if self.remote_registry.deregister(endpoint.resource_id()) {
-                event_callback(NetEvent::Disconnected(endpoint));
-            }
+            // The user in the callback could have removed the same resource.
+            self.close_remote(remote, endpoint.resource_id(), DisconnectReason::Closed, || {
+                event_callback(NetEvent::Disconnected(endpoint))
+            });
         }
     }
 
@@ -312,11 +792,18 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
             log::trace!("Accepted type: {}", accepted);
             match accepted {
                 AcceptedType::Remote(addr, remote) => {
-                    self.remote_registry.register(
-                        remote,
-                        RemoteProperties::new(addr, Some(id)),
-                        true,
-                    );
+                    let shard = match local.properties.in_group {
+                        true => id.shard(),
+                        false => self.selector.select(addr),
+                    };
+                    // The listener could be bound to an unspecified address.
+                    let local_addr =
+                        socket_local_addr(&remote).unwrap_or(local.properties.local_addr);
+                    let properties = RemoteProperties::new(addr, local_addr, Some(id));
+                    let registry = &self.remote_registries[shard];
+                    if let Err(err) = registry.register(remote, properties, true) {
+                        log::error!("Connection from {} rejected by {}: {}", addr, id, err);
+                    }
                 }
                 AcceptedType::Data(addr, data) => {
                     let endpoint = Endpoint::new(id, addr);
@@ -327,6 +814,18 @@ impl<R: Remote, L: Local<Remote = R>> Driver<R, L> {
     }
 }
 
+/// Returns the local address of the socket of the resource, if it has an internet socket.
+fn socket_local_addr(resource: &impl Resource) -> Option<SocketAddr> {
+    let mut local_addr = None;
+    resource
+        .with_socket(&mut |socket| {
+            local_addr = socket.local_addr()?.as_socket();
+            Ok(())
+        })
+        .ok()?;
+    local_addr
+}
+
 impl<R> std::fmt::Display for AcceptedType<'_, R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match self {
@@ -39,6 +39,7 @@ impl Endpoint {
     /// let (mut msg_1, mut msg_2) = (0, 0);
     /// listener.for_each(|event| match event {
     ///     NodeEvent::Signal(_) => handler.stop(),
+    ///     NodeEvent::Source(..) => (),
     ///     NodeEvent::Network(net_event) => match net_event {
     ///         NetEvent::Message(endpoint, message) => match endpoint.resource_id() {
     ///             id if id == receiver_id_1 => msg_1 = message[0],
@@ -93,7 +94,7 @@ mod tests {
     #[test]
     fn from_local_non_connection_oriented() {
         let addr = "0.0.0.0:0".parse().unwrap();
-        let generator = ResourceIdGenerator::new(Transport::Udp.id(), ResourceType::Local);
-        Endpoint::from_listener(generator.generate(), addr);
+        let generator = ResourceIdGenerator::new(Transport::Udp.id(), ResourceType::Local, 0);
+        Endpoint::from_listener(generator.generate().unwrap(), addr);
     }
 }
@@ -0,0 +1,81 @@
+use super::{NetworkController};
+use super::endpoint::{Endpoint};
+use super::adapter::{SendStatus};
+
+use std::future::{Future};
+use std::pin::{Pin};
+use std::task::{Context, Poll};
+use std::net::{SocketAddr};
+use std::io::{self};
+
+/// Future returned by [`NetworkController::connect_async()`].
+/// It resolves once the connection is established or has failed.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ConnectFuture<'a> {
+    controller: &'a NetworkController,
+    connection: Option<io::Result<(Endpoint, SocketAddr)>>,
+}
+
+impl<'a> ConnectFuture<'a> {
+    pub(crate) fn new(
+        controller: &'a NetworkController,
+        connection: io::Result<(Endpoint, SocketAddr)>,
+    ) -> Self {
+        Self { controller, connection: Some(connection) }
+    }
+}
+
+impl Future for ConnectFuture<'_> {
+    type Output = io::Result<(Endpoint, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (endpoint, addr) = match self.connection.take().expect("Future polled after completion")
+        {
+            Ok(connection) => connection,
+            Err(err) => return Poll::Ready(Err(err)),
+        };
+
+        match self.controller.register_ready_waker(endpoint.resource_id(), cx.waker()) {
+            Some(true) => Poll::Ready(Ok((endpoint, addr))),
+            Some(false) => {
+                self.connection = Some(Ok((endpoint, addr)));
+                Poll::Pending
+            }
+            None => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "Connection refused",
+            ))),
+        }
+    }
+}
+
+/// Future returned by [`NetworkController::send_async()`].
+/// It resolves once the data has been sent.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendFuture<'a> {
+    controller: &'a NetworkController,
+    endpoint: Endpoint,
+    data: &'a [u8],
+}
+
+impl<'a> SendFuture<'a> {
+    pub(crate) fn new(
+        controller: &'a NetworkController,
+        endpoint: Endpoint,
+        data: &'a [u8],
+    ) -> Self {
+        Self { controller, endpoint, data }
+    }
+}
+
+impl Future for SendFuture<'_> {
+    type Output = SendStatus;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.controller.register_ready_waker(self.endpoint.resource_id(), cx.waker()) {
+            Some(true) => Poll::Ready(self.controller.send(self.endpoint, self.data)),
+            Some(false) => Poll::Pending,
+            None => Poll::Ready(SendStatus::ResourceNotFound),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::{self};
+use std::mem::{self};
+use std::ptr::{self};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::{UnixStream};
+
+/// Max number of listeners that can be handed over at once.
+/// It is the max number of file descriptors that Linux accepts in a message (`SCM_MAX_FD`).
+/// See [`crate::network::NetworkController::hand_over_listeners()`].
+pub const MAX_HANDED_LISTENERS: usize = 253;
+
+/// Control buffer aligned to contain `cmsghdr`s.
+fn control_buffer(fds: usize) -> Vec<u64> {
+    let size = unsafe { libc::CMSG_SPACE((fds * mem::size_of::<RawFd>()) as u32) } as usize;
+    vec![0; size.div_ceil(mem::size_of::<u64>())]
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed listener handover message")
+}
+
+/// Sends the `sockets` through the `stream` as `SCM_RIGHTS` in only one message.
+/// Each socket is tagged by a byte.
+/// The message payload is the number of sockets followed by their tags.
+pub(crate) fn send_sockets(stream: &UnixStream, sockets: &[(u8, RawFd)]) -> io::Result<()> {
+    if sockets.len() > MAX_HANDED_LISTENERS {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Too many sockets to hand over"))
+    }
+
+    let mut payload = Vec::with_capacity(sockets.len() + 1);
+    payload.push(sockets.len() as u8);
+    payload.extend(sockets.iter().map(|(tag, _)| tag));
+
+    let mut iov =
+        libc::iovec { iov_base: payload.as_mut_ptr() as *mut libc::c_void, iov_len: payload.len() };
+    let mut control = control_buffer(sockets.len());
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+
+    if !sockets.is_empty() {
+        let fds_size = (sockets.len() * mem::size_of::<RawFd>()) as u32;
+        message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        message.msg_controllen = unsafe { libc::CMSG_SPACE(fds_size) } as _;
+        unsafe {
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_RIGHTS;
+            (*header).cmsg_len = libc::CMSG_LEN(fds_size) as _;
+            let data = libc::CMSG_DATA(header) as *mut RawFd;
+            for (index, (_, fd)) in sockets.iter().enumerate() {
+                ptr::write_unaligned(data.add(index), *fd);
+            }
+        }
+    }
+
+    loop {
+        let sent = unsafe { libc::sendmsg(stream.as_raw_fd(), &message, 0) };
+        if sent < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue
+            }
+            return Err(err)
+        }
+        if sent as usize != payload.len() {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "Handover message not sent"))
+        }
+        return Ok(())
+    }
+}
+
+/// Receives the sockets sent by [`send_sockets()`] along with their tags.
+pub(crate) fn receive_sockets(stream: &UnixStream) -> io::Result<Vec<(u8, OwnedFd)>> {
+    let mut payload = [0; MAX_HANDED_LISTENERS + 1];
+    let mut iov =
+        libc::iovec { iov_base: payload.as_mut_ptr() as *mut libc::c_void, iov_len: payload.len() };
+    let mut control = control_buffer(MAX_HANDED_LISTENERS);
+    let mut message: libc::msghdr = unsafe { mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    message.msg_controllen = (control.len() * mem::size_of::<u64>()) as _;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = 0;
+
+    let received = loop {
+        let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut message, flags) };
+        if received < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue
+            }
+            return Err(err)
+        }
+        break received as usize
+    };
+
+    // The file descriptors are owned first, so they are closed if the message is not valid.
+    let mut fds = Vec::new();
+    unsafe {
+        let mut header = libc::CMSG_FIRSTHDR(&message);
+        while !header.is_null() {
+            if (*header).cmsg_level == libc::SOL_SOCKET && (*header).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(header) as *const RawFd;
+                let data_size = (*header).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                for index in 0..data_size / mem::size_of::<RawFd>() {
+                    fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(data.add(index))));
+                }
+            }
+            header = libc::CMSG_NXTHDR(&message, header);
+        }
+    }
+
+    if received == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    if message.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(malformed())
+    }
+    let count = payload[0] as usize;
+    if received != count + 1 || fds.len() != count {
+        return Err(malformed())
+    }
+    Ok(payload[1..received].iter().copied().zip(fds).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_and_receive() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (other_sender, _other_receiver) = UnixStream::pair().unwrap();
+        send_sockets(&sender, &[(2, socket.as_raw_fd()), (5, other_sender.as_raw_fd())]).unwrap();
+
+        let sockets = receive_sockets(&receiver).unwrap();
+        assert_eq!(sockets.iter().map(|(tag, _)| *tag).collect::<Vec<_>>(), [2, 5]);
+
+        // The received socket is a new descriptor of the same socket.
+        let (_, fd) = sockets.into_iter().next().unwrap();
+        assert_ne!(fd.as_raw_fd(), socket.as_raw_fd());
+        let received_socket = std::net::UdpSocket::from(fd);
+        assert_eq!(received_socket.local_addr().unwrap(), socket.local_addr().unwrap());
+    }
+
+    #[test]
+    fn send_and_receive_nothing() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        send_sockets(&sender, &[]).unwrap();
+        assert!(receive_sockets(&receiver).unwrap().is_empty());
+    }
+
+    #[test]
+    fn closed_stream() {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        drop(sender);
+        let err = receive_sockets(&receiver).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -4,12 +4,21 @@ use super::endpoint::{Endpoint};
 use super::resource_id::{ResourceId};
 use super::poll::{Poll, Readiness};
 use super::remote_addr::{RemoteAddr};
-use super::driver::{NetEvent, Driver, ActionController, EventProcessor};
-use super::adapter::{Adapter, SendStatus};
+use super::driver::{
+    NetEvent, Driver, ActionController, EventProcessor, UserData, ResourceInfo, DisconnectReason,
+};
+use super::adapter::{Adapter, SendStatus, MessageKind};
+use super::shard::{ShardBalance, ShardSelector};
+use super::socket_option::{SocketOption, SocketOptionName};
 
 use std::net::{SocketAddr};
 use std::io::{self};
 use std::panic::{UnwindSafe};
+use std::sync::{Arc};
+use std::any::{Any};
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use std::task::{Waker};
 
 type Controller = Box<dyn ActionController + Send + UnwindSafe>;
 type Processor = Box<dyn EventProcessor + Send + UnwindSafe>;
@@ -19,15 +28,24 @@ pub type EventProcessorList = Vec<Processor>;
 
 /// Used to configured the engine
 pub struct DriverLoader {
-    poll: Poll,
+    polls: Vec<Poll>,
+    selector: Arc<ShardSelector>,
     controllers: ActionControllerList,
     processors: EventProcessorList,
 }
 
 impl Default for DriverLoader {
     fn default() -> DriverLoader {
+        Self::new(1, ShardBalance::default())
+    }
+}
+
+impl DriverLoader {
+    /// Creates a loader whose drivers distribute their resources among `shards` polls.
+    pub fn new(shards: usize, balance: ShardBalance) -> DriverLoader {
         Self {
-            poll: Poll::default(),
+            polls: (0..shards).map(Poll::new).collect(),
+            selector: Arc::new(ShardSelector::new(balance, shards)),
             controllers: (0..ResourceId::MAX_ADAPTERS)
                 .map(|_| Box::new(UnimplementedDriver) as Controller)
                 .collect::<Vec<_>>(),
@@ -36,22 +54,20 @@ impl Default for DriverLoader {
                 .collect(),
         }
     }
-}
 
-impl DriverLoader {
     /// Mount an adapter to create its driver associating it with an id.
     pub fn mount(&mut self, adapter_id: u8, adapter: impl Adapter + 'static) {
         let index = adapter_id as usize;
 
-        let driver = Driver::new(adapter, adapter_id, &mut self.poll);
+        let driver = Driver::new(adapter, adapter_id, &mut self.polls, self.selector.clone());
 
         self.controllers[index] = Box::new(driver.clone()) as Controller;
         self.processors[index] = Box::new(driver) as Processor;
     }
 
     /// Consume this instance to obtain the driver handles.
-    pub fn take(self) -> (Poll, ActionControllerList, EventProcessorList) {
-        (self.poll, self.controllers, self.processors)
+    pub fn take(self) -> (Vec<Poll>, ActionControllerList, EventProcessorList) {
+        (self.polls, self.controllers, self.processors)
     }
 }
 
@@ -80,10 +96,50 @@ impl ActionController for UnimplementedDriver {
         panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
     }
 
+    fn listen_group_with(
+        &self,
+        _: TransportListen,
+        _: SocketAddr,
+    ) -> io::Result<(Vec<ResourceId>, SocketAddr)> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn adopt_stream_with(
+        &self,
+        _: TransportConnect,
+        _: socket2::Socket,
+    ) -> io::Result<(Endpoint, SocketAddr)> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn adopt_listener_with(
+        &self,
+        _: TransportListen,
+        _: socket2::Socket,
+    ) -> io::Result<(ResourceId, SocketAddr)> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
     fn send(&self, _: Endpoint, _: &[u8]) -> SendStatus {
         panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
     }
 
+    fn send_text(&self, _: Endpoint, _: &str) -> SendStatus {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn send_fragment(&self, _: Endpoint, _: &[u8], _: bool) -> SendStatus {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn message_kind(&self, _: ResourceId) -> MessageKind {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn is_message_complete(&self, _: ResourceId) -> bool {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
     fn remove(&self, _: ResourceId) -> bool {
         panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
     }
@@ -91,10 +147,58 @@ impl ActionController for UnimplementedDriver {
     fn is_ready(&self, _: ResourceId) -> Option<bool> {
         panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
     }
+
+    fn set_user_data(&self, _: ResourceId, _: Option<UserData>) -> bool {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn user_data(&self, _: ResourceId) -> Option<UserData> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn metadata(&self, _: ResourceId) -> Option<Arc<dyn Any + Send + Sync>> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn round_trip_time(&self, _: ResourceId) -> Option<Duration> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn disconnect_reason(&self, _: ResourceId) -> Option<DisconnectReason> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn resources(&self, _: &mut dyn FnMut(ResourceInfo)) {
+        // A not mounted adapter has no resources.
+    }
+
+    fn set_socket_option(&self, _: ResourceId, _: SocketOption) -> io::Result<()> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn socket_option(&self, _: ResourceId, _: SocketOptionName) -> io::Result<SocketOption> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    fn try_clone_socket(&self, _: ResourceId) -> io::Result<socket2::Socket> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
+
+    #[cfg(feature = "async")]
+    fn register_ready_waker(&self, _: ResourceId, _: &Waker) -> Option<bool> {
+        panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
+    }
 }
 
 impl EventProcessor for UnimplementedDriver {
     fn process(&self, _: ResourceId, _: Readiness, _: &mut dyn FnMut(NetEvent<'_>)) {
         panic!("{}", UNIMPLEMENTED_DRIVER_ERR);
     }
+
+    fn next_keepalive(&self, _: usize) -> Option<Instant> {
+        // A not mounted adapter has no resources to keep alive.
+        None
+    }
+
+    fn process_keepalives(&self, _: usize, _: &mut dyn FnMut(NetEvent<'_>)) {}
 }
@@ -4,8 +4,13 @@ use mio::{Poll as MioPoll, Interest, Token, Events, Registry, Waker};
 use mio::event::{Source};
 
 use std::time::{Duration};
-use std::sync::{Arc};
-use std::io::{ErrorKind};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::io::{self, ErrorKind};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Used for the adapter implementation.
@@ -18,11 +23,45 @@ pub enum Readiness {
     Read,
 }
 
+/// Identifier of a user source registered in the network poll.
+/// See [`crate::network::NetworkController::register_source()`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct SourceId(usize);
+
+impl SourceId {
+    /// Returns the internal representation of this id
+    pub fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[S.{}]", self.0)
+    }
+}
+
 pub enum PollEvent {
     Network(ResourceId, Readiness),
+    Source(SourceId, Readiness),
     Waker,
 }
 
+// Network resources use the tokens with the lowest bit set,
+// user sources use the rest of them, except the token 0 that is reserved to the waker.
+
+impl From<Token> for SourceId {
+    fn from(token: Token) -> Self {
+        SourceId(token.0 >> Poll::RESERVED_BITS)
+    }
+}
+
+impl From<SourceId> for Token {
+    fn from(id: SourceId) -> Self {
+        Token(id.0 << Poll::RESERVED_BITS)
+    }
+}
+
 impl From<Token> for ResourceId {
     fn from(token: Token) -> Self {
         (token.0 >> Poll::RESERVED_BITS).into()
@@ -38,25 +77,37 @@ impl From<ResourceId> for Token {
 pub struct Poll {
     mio_poll: MioPoll,
     events: Events,
+    shard: usize,
     #[allow(dead_code)] //TODO: remove it with poll native event support
     waker: Arc<Waker>,
 }
 
 impl Default for Poll {
     fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Poll {
+    const EVENTS_SIZE: usize = 1024;
+    const RESERVED_BITS: usize = 1;
+    const WAKER_TOKEN: Token = Token(0);
+
+    /// Creates a poll whose resources belong to the `shard`.
+    pub fn new(shard: usize) -> Self {
         let mio_poll = MioPoll::new().unwrap();
         Self {
             waker: Arc::new(Waker::new(mio_poll.registry(), Self::WAKER_TOKEN).unwrap()),
             mio_poll,
             events: Events::with_capacity(Self::EVENTS_SIZE),
+            shard,
         }
     }
-}
 
-impl Poll {
-    const EVENTS_SIZE: usize = 1024;
-    const RESERVED_BITS: usize = 1;
-    const WAKER_TOKEN: Token = Token(0);
+    /// Shard whose resources belong to this poll.
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
 
     pub fn process_event<C>(&mut self, timeout: Option<Duration>, mut event_callback: C)
     where C: FnMut(PollEvent) {
@@ -68,6 +119,17 @@ impl Poll {
                             log::trace!("POLL WAKER EVENT");
                             event_callback(PollEvent::Waker);
                         }
+                        else if mio_event.token().0 & 1 == 0 {
+                            let id = SourceId::from(mio_event.token());
+                            if mio_event.is_readable() {
+                                log::trace!("POLL SOURCE EVENT (R): {}", id);
+                                event_callback(PollEvent::Source(id, Readiness::Read));
+                            }
+                            if mio_event.is_writable() {
+                                log::trace!("POLL SOURCE EVENT (W): {}", id);
+                                event_callback(PollEvent::Source(id, Readiness::Write));
+                            }
+                        }
                         else {
                             let id = ResourceId::from(mio_event.token());
                             if mio_event.is_readable() {
@@ -88,8 +150,26 @@ impl Poll {
         }
     }
 
+    /// Process all the events that are already ready without blocking.
+    /// The poll is read until it has no more events to offer.
+    pub fn process_ready_events<C>(&mut self, mut event_callback: C)
+    where C: FnMut(PollEvent) {
+        loop {
+            self.process_event(Some(Duration::ZERO), &mut event_callback);
+            // If the events buffer was not filled, there were no more ready events in the poll.
+            if self.events.iter().count() < Self::EVENTS_SIZE {
+                break;
+            }
+        }
+    }
+
     pub fn create_registry(&mut self, adapter_id: u8, resource_type: ResourceType) -> PollRegistry {
-        PollRegistry::new(adapter_id, resource_type, self.mio_poll.registry().try_clone().unwrap())
+        let registry = self.mio_poll.registry().try_clone().unwrap();
+        PollRegistry::new(adapter_id, resource_type, self.shard, registry)
+    }
+
+    pub fn create_source_registry(&mut self) -> SourceRegistry {
+        SourceRegistry::new(self.mio_poll.registry().try_clone().unwrap())
     }
 
     #[allow(dead_code)] //TODO: remove it with poll native event support
@@ -98,27 +178,40 @@ impl Poll {
     }
 }
 
+#[cfg(unix)]
+impl AsRawFd for Poll {
+    fn as_raw_fd(&self) -> RawFd {
+        self.mio_poll.as_raw_fd()
+    }
+}
+
 pub struct PollRegistry {
     id_generator: Arc<ResourceIdGenerator>,
     registry: Registry,
 }
 
 impl PollRegistry {
-    fn new(adapter_id: u8, resource_type: ResourceType, registry: Registry) -> Self {
-        Self {
-            id_generator: Arc::new(ResourceIdGenerator::new(adapter_id, resource_type)),
-            registry,
-        }
+    fn new(adapter_id: u8, resource_type: ResourceType, shard: usize, registry: Registry) -> Self {
+        let id_generator = ResourceIdGenerator::new(adapter_id, resource_type, shard);
+        Self { id_generator: Arc::new(id_generator), registry }
+    }
+
+    /// Generates the id of a resource that will be added to the poll.
+    pub fn generate_id(&self) -> io::Result<ResourceId> {
+        self.id_generator.generate()
     }
 
-    pub fn add(&self, source: &mut dyn Source, write_readiness: bool) -> ResourceId {
-        let id = self.id_generator.generate();
+    pub fn add(&self, source: &mut dyn Source, id: ResourceId, write_readiness: bool) {
         let interest = match write_readiness {
             true => Interest::READABLE | Interest::WRITABLE,
             false => Interest::READABLE,
         };
         self.registry.register(source, id.into(), interest).unwrap();
-        id
+    }
+
+    /// Allows the id of a removed resource to be reused with a new generation.
+    pub fn release_id(&self, id: ResourceId) {
+        self.id_generator.release(id)
     }
 
     pub fn remove(&self, source: &mut dyn Source) {
@@ -135,6 +228,28 @@ impl Clone for PollRegistry {
     }
 }
 
+pub struct SourceRegistry {
+    last_id: AtomicUsize,
+    registry: Registry,
+}
+
+impl SourceRegistry {
+    fn new(registry: Registry) -> Self {
+        // The id 0 is not used to not collide with the waker token.
+        Self { last_id: AtomicUsize::new(0), registry }
+    }
+
+    pub fn add(&self, source: &mut dyn Source, interest: Interest) -> io::Result<SourceId> {
+        let id = SourceId(self.last_id.fetch_add(1, Ordering::SeqCst) + 1);
+        self.registry.register(source, id.into(), interest)?;
+        Ok(id)
+    }
+
+    pub fn remove(&self, source: &mut dyn Source) -> io::Result<()> {
+        self.registry.deregister(source)
+    }
+}
+
 #[allow(dead_code)] //TODO: remove it with poll native event support
 pub struct PollWaker {
     waker: Arc<Waker>,
@@ -6,6 +6,11 @@ use crate::util::thread::{OTHER_THREAD_ERR};
 
 use std::collections::{HashMap};
 use std::sync::{Arc, RwLock};
+use std::io::{self};
+
+/// Number of independent locks of the registry.
+/// It must be a power of two.
+const STRIPES: usize = 64;
 
 pub struct Register<S: Resource, P> {
     pub resource: S,
@@ -26,28 +31,46 @@ impl<S: Resource, P> Drop for Register<S, P> {
     }
 }
 
+type Stripe<S, P> = RwLock<HashMap<ResourceId, Arc<Register<S, P>>>>;
+
+/// Registry of resources.
+/// The resources are spread among several maps, each one with its own lock,
+/// so operations over different resources rarely compete for the same lock.
 pub struct ResourceRegistry<S: Resource, P> {
-    resources: RwLock<HashMap<ResourceId, Arc<Register<S, P>>>>,
+    stripes: Box<[Stripe<S, P>]>,
     poll_registry: Arc<PollRegistry>,
 }
 
 impl<S: Resource, P> ResourceRegistry<S, P> {
     pub fn new(poll_registry: PollRegistry) -> Self {
         ResourceRegistry {
-            resources: RwLock::new(HashMap::new()),
+            stripes: (0..STRIPES).map(|_| RwLock::new(HashMap::new())).collect(),
             poll_registry: Arc::new(poll_registry),
         }
     }
 
+    /// The slots are generated consecutively, so they are distributed uniformly among the stripes.
+    fn stripe(&self, id: ResourceId) -> &Stripe<S, P> {
+        &self.stripes[id.slot() & (STRIPES - 1)]
+    }
+
     /// Add a resource into the registry.
-    pub fn register(&self, mut resource: S, properties: P, write_readiness: bool) -> ResourceId {
-        // The registry must be locked for the entire implementation to avoid the poll
+    /// If there is no room for more resources, an error is returned and the resource is dropped.
+    pub fn register(
+        &self,
+        mut resource: S,
+        properties: P,
+        write_readiness: bool,
+    ) -> io::Result<ResourceId> {
+        let id = self.poll_registry.generate_id()?;
+
+        // The stripe must be locked for the entire implementation to avoid the poll
         // to generate events over not yet registered resources.
-        let mut registry = self.resources.write().expect(OTHER_THREAD_ERR);
-        let id = self.poll_registry.add(resource.source(), write_readiness);
+        let mut stripe = self.stripe(id).write().expect(OTHER_THREAD_ERR);
+        self.poll_registry.add(resource.source(), id, write_readiness);
         let register = Register::new(resource, properties, self.poll_registry.clone());
-        registry.insert(id, Arc::new(register));
-        id
+        stripe.insert(id, Arc::new(register));
+        Ok(id)
     }
 
     /// Remove a register from the registry.
@@ -55,11 +78,26 @@ impl<S: Resource, P> ResourceRegistry<S, P> {
     /// but not the destruction of the resource itself.
     /// Because the resource is shared, the destruction will be delayed until the last reference.
     pub fn deregister(&self, id: ResourceId) -> bool {
-        self.resources.write().expect(OTHER_THREAD_ERR).remove(&id).is_some()
+        // The register is dropped out of the lock.
+        let register = self.stripe(id).write().expect(OTHER_THREAD_ERR).remove(&id);
+        if register.is_some() {
+            // Any other reference to this id will not be found because it has an old generation.
+            self.poll_registry.release_id(id);
+        }
+        register.is_some()
+    }
+
+    /// Iterates over all the registers.
+    /// Each stripe is locked while it is iterated.
+    pub fn for_each(&self, mut callback: impl FnMut(ResourceId, &Register<S, P>)) {
+        for stripe in self.stripes.iter() {
+            let stripe = stripe.read().expect(OTHER_THREAD_ERR);
+            stripe.iter().for_each(|(&id, register)| callback(id, register));
+        }
     }
 
     /// Returned a shared reference of the register.
     pub fn get(&self, id: ResourceId) -> Option<Arc<Register<S, P>>> {
-        self.resources.read().expect(OTHER_THREAD_ERR).get(&id).cloned()
+        self.stripe(id).read().expect(OTHER_THREAD_ERR).get(&id).cloned()
     }
 }
@@ -1,6 +1,9 @@
+use crossbeam_channel::{self, Sender, Receiver};
+
 use std::sync::{
     atomic::{Ordering, AtomicUsize},
 };
+use std::io::{self};
 
 /// Information about the type of resource
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -10,10 +13,20 @@ pub enum ResourceType {
 }
 
 /// Unique identifier of a network resource in your system.
-/// The identifier wrap 3 values,
+/// The identifier wrap 4 values,
 /// - The type, that can be a value of [ResourceType].
 /// - The adapter id, that represents the adapter that creates this id
+/// - The shard: the poll that processes the resource (see [`crate::network::split_sharded()`]).
 /// - The base value: that is an unique identifier of the resource inside of its adapter.
+///   It is composed by a slot and a generation.
+///   The slot of a removed resource can be reused by a new one, but with a different generation.
+///   A slot whose generation reaches [`ResourceId::MAX_GENERATION`] is never reused again.
+///   This way, an id of a removed resource never identifies a new resource.
+///
+/// In 32 bits targets the shard field is reduced to leave enough room to the slot and the
+/// generation, so fewer shards are available (see [`ResourceId::MAX_SHARDS`]).
+/// Even so, once all the slots are retired (about 2 million resources of the same adapter,
+/// type and shard), creating a new resource returns an error.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ResourceId {
     id: usize,
@@ -22,26 +35,54 @@ pub struct ResourceId {
 impl ResourceId {
     const ADAPTER_ID_POS: usize = 0;
     const RESOURCE_TYPE_POS: usize = 7;
-    const BASE_VALUE_POS: usize = 8;
+    const SHARD_POS: usize = 8;
+    #[cfg(target_pointer_width = "64")]
+    const SHARD_BITS: usize = 8;
+    #[cfg(not(target_pointer_width = "64"))]
+    const SHARD_BITS: usize = 2;
+    const BASE_VALUE_POS: usize = Self::SHARD_POS + Self::SHARD_BITS;
+    const SLOT_POS: usize = Self::BASE_VALUE_POS;
+    #[cfg(target_pointer_width = "64")]
+    const GENERATION_POS: usize = 40; // 24 bits of slot and 23 bits of generation
+    #[cfg(not(target_pointer_width = "64"))]
+    const GENERATION_POS: usize = 25; // 15 bits of slot and 6 bits of generation
+
+    const ADAPTER_ID_MASK: u8 = 0b01111111; // 7 bits
+    const SHARD_MASK: usize = ((1 << Self::SHARD_BITS) - 1) << Self::SHARD_POS;
 
-    const ADAPTER_ID_MASK: u8 = 0b01111111; // 5 bits
-    const BASE_VALUE_MASK: usize = 0xFFFFFFFFFFFFFF00_u64 as usize; // 7 bytes
+    // The highest bit is not used because it is lost in the poll token.
+    const BASE_VALUE_MASK: usize = (usize::MAX >> 1) & !((1 << Self::BASE_VALUE_POS) - 1);
+    const SLOT_MASK: usize = Self::BASE_VALUE_MASK & ((1 << Self::GENERATION_POS) - 1);
+    const GENERATION_MASK: usize = Self::BASE_VALUE_MASK & !Self::SLOT_MASK;
 
     pub const MAX_BASE_VALUE: usize = (Self::BASE_VALUE_MASK >> Self::BASE_VALUE_POS);
+    pub const MAX_SLOT: usize = (Self::SLOT_MASK >> Self::SLOT_POS);
+    pub const MAX_GENERATION: usize = (Self::GENERATION_MASK >> Self::GENERATION_POS);
     pub const MAX_ADAPTER_ID: u8 = (Self::ADAPTER_ID_MASK >> Self::ADAPTER_ID_POS);
     pub const MAX_ADAPTERS: usize = Self::MAX_ADAPTER_ID as usize + 1;
+    pub const MAX_SHARDS: usize = (Self::SHARD_MASK >> Self::SHARD_POS) + 1;
 
-    fn new(adapter_id: u8, resource_type: ResourceType, base_value: usize) -> Self {
+    fn new(
+        adapter_id: u8,
+        resource_type: ResourceType,
+        shard: usize,
+        slot: usize,
+        generation: usize,
+    ) -> Self {
         debug_assert!(
             adapter_id <= Self::MAX_ADAPTER_ID,
             "The adapter_id must be less than {}",
             Self::MAX_ADAPTER_ID + 1,
         );
 
+        debug_assert!(shard < Self::MAX_SHARDS, "The shard must be less than {}", Self::MAX_SHARDS);
+
+        debug_assert!(slot <= Self::MAX_SLOT, "The slot must be less than {}", Self::MAX_SLOT + 1);
+
         debug_assert!(
-            base_value <= Self::MAX_BASE_VALUE,
-            "The base_value must be less than {}",
-            Self::MAX_BASE_VALUE + 1,
+            generation <= Self::MAX_GENERATION,
+            "The generation must be less than {}",
+            Self::MAX_GENERATION + 1,
         );
 
         let resource_type = match resource_type {
@@ -52,7 +93,9 @@ impl ResourceId {
         Self {
             id: ((adapter_id as usize) << Self::ADAPTER_ID_POS)
                 | resource_type
-                | (base_value << Self::BASE_VALUE_POS),
+                | (shard << Self::SHARD_POS)
+                | (slot << Self::SLOT_POS)
+                | (generation << Self::GENERATION_POS),
         }
     }
 
@@ -88,10 +131,30 @@ impl ResourceId {
         ((self.id & Self::ADAPTER_ID_MASK as usize) >> Self::ADAPTER_ID_POS) as u8
     }
 
+    /// Returns the shard that processes this resource.
+    /// It is always `0` if the network is not sharded.
+    pub fn shard(&self) -> usize {
+        (self.id & Self::SHARD_MASK) >> Self::SHARD_POS
+    }
+
     /// Returns the unique resource identifier inside the associated adapter.
+    /// It is the composition of the [`ResourceId::slot()`] and the [`ResourceId::generation()`].
     pub fn base_value(&self) -> usize {
         (self.id & Self::BASE_VALUE_MASK) >> Self::BASE_VALUE_POS
     }
+
+    /// Returns the slot of the resource.
+    /// Two resources alive at the same time in the same adapter and shard never share a slot,
+    /// but the slot of a removed resource can be reused.
+    pub fn slot(&self) -> usize {
+        (self.id & Self::SLOT_MASK) >> Self::SLOT_POS
+    }
+
+    /// Returns the generation of the slot when the resource was created.
+    /// It is increased each time the slot is reused.
+    pub fn generation(&self) -> usize {
+        (self.id & Self::GENERATION_MASK) >> Self::GENERATION_POS
+    }
 }
 
 impl From<usize> for ResourceId {
@@ -106,7 +169,14 @@ impl std::fmt::Display for ResourceId {
             ResourceType::Local => "L",
             ResourceType::Remote => "R",
         };
-        write!(f, "[{}.{}.{}]", self.adapter_id(), resource_type, self.base_value())
+        write!(f, "[{}.{}.{}", self.adapter_id(), resource_type, self.slot())?;
+        if self.generation() != 0 {
+            write!(f, ":{}", self.generation())?;
+        }
+        if self.shard() != 0 {
+            write!(f, "/{}", self.shard())?;
+        }
+        write!(f, "]")
     }
 }
 
@@ -118,21 +188,54 @@ impl std::fmt::Debug for ResourceId {
 
 /// Used by the adapters in order to create unique ids for their resources.
 pub struct ResourceIdGenerator {
-    last: AtomicUsize,
+    next_slot: AtomicUsize,
+    // Released slots with the generation they will have when reused.
+    released_sender: Sender<(usize, usize)>,
+    released_receiver: Receiver<(usize, usize)>,
     adapter_id: u8,
     resource_type: ResourceType,
+    shard: usize,
 }
 
 impl ResourceIdGenerator {
-    pub fn new(adapter_id: u8, resource_type: ResourceType) -> Self {
-        Self { last: AtomicUsize::new(0), adapter_id, resource_type }
+    pub fn new(adapter_id: u8, resource_type: ResourceType, shard: usize) -> Self {
+        let (released_sender, released_receiver) = crossbeam_channel::unbounded();
+        Self {
+            next_slot: AtomicUsize::new(0),
+            released_sender,
+            released_receiver,
+            adapter_id,
+            resource_type,
+            shard,
+        }
     }
 
     /// Generates a new id.
     /// This id will contain information about the [`ResourceType`] and the associated adapter.
-    pub fn generate(&self) -> ResourceId {
-        let last = self.last.fetch_add(1, Ordering::SeqCst);
-        ResourceId::new(self.adapter_id, self.resource_type, last)
+    /// Released slots are reused before creating new ones.
+    /// An error is returned if all the slots are in use.
+    pub fn generate(&self) -> io::Result<ResourceId> {
+        let (slot, generation) = match self.released_receiver.try_recv() {
+            Ok(free_slot) => free_slot,
+            Err(_) => {
+                let slot = self
+                    .next_slot
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |slot| {
+                        (slot <= ResourceId::MAX_SLOT).then_some(slot + 1)
+                    })
+                    .map_err(|_| io::Error::other("No more resource slots available"))?;
+                (slot, 0)
+            }
+        };
+        Ok(ResourceId::new(self.adapter_id, self.resource_type, self.shard, slot, generation))
+    }
+
+    /// Releases the slot of an id that is no longer used, so it can be reused by a new id.
+    /// A slot that has exhausted its generations is retired and never reused again.
+    pub fn release(&self, id: ResourceId) {
+        if id.generation() < ResourceId::MAX_GENERATION {
+            self.released_sender.send((id.slot(), id.generation() + 1)).ok();
+        }
     }
 }
 
@@ -142,24 +245,25 @@ mod tests {
 
     #[test]
     fn base_value() {
-        let low_base_value = 0;
+        let resource_id = ResourceId::new(1, ResourceType::Local, 0, 0, 0);
+        assert_eq!(0, resource_id.base_value());
 
-        let resource_id = ResourceId::new(1, ResourceType::Local, low_base_value);
-        assert_eq!(low_base_value, resource_id.base_value());
+        let max_slot = ResourceId::MAX_SLOT;
+        let max_generation = ResourceId::MAX_GENERATION;
 
-        let high_base_value = ResourceId::MAX_BASE_VALUE;
-
-        let resource_id = ResourceId::new(1, ResourceType::Local, high_base_value);
-        assert_eq!(high_base_value, resource_id.base_value());
+        let resource_id = ResourceId::new(1, ResourceType::Local, 0, max_slot, max_generation);
+        assert_eq!(ResourceId::MAX_BASE_VALUE, resource_id.base_value());
+        assert_eq!(max_slot, resource_id.slot());
+        assert_eq!(max_generation, resource_id.generation());
     }
 
     #[test]
     fn resource_type() {
-        let resource_id = ResourceId::new(0, ResourceType::Local, 0);
+        let resource_id = ResourceId::new(0, ResourceType::Local, 0, 0, 0);
         assert_eq!(ResourceType::Local, resource_id.resource_type());
         assert_eq!(0, resource_id.adapter_id());
 
-        let resource_id = ResourceId::new(0, ResourceType::Remote, 0);
+        let resource_id = ResourceId::new(0, ResourceType::Remote, 0, 0, 0);
         assert_eq!(ResourceType::Remote, resource_id.resource_type());
         assert_eq!(0, resource_id.adapter_id());
     }
@@ -168,12 +272,77 @@ mod tests {
     fn adapter_id() {
         let adapter_id = ResourceId::MAX_ADAPTER_ID;
 
-        let resource_id = ResourceId::new(adapter_id, ResourceType::Local, 0);
+        let resource_id = ResourceId::new(adapter_id, ResourceType::Local, 0, 0, 0);
         assert_eq!(adapter_id, resource_id.adapter_id());
         assert_eq!(ResourceType::Local, resource_id.resource_type());
 
-        let resource_id = ResourceId::new(adapter_id, ResourceType::Remote, 0);
+        let resource_id = ResourceId::new(adapter_id, ResourceType::Remote, 0, 0, 0);
         assert_eq!(adapter_id, resource_id.adapter_id());
         assert_eq!(ResourceType::Remote, resource_id.resource_type());
     }
+
+    #[test]
+    fn shard() {
+        let shard = ResourceId::MAX_SHARDS - 1;
+        let base_value = ResourceId::MAX_BASE_VALUE;
+
+        let resource_id = ResourceId::new(
+            3,
+            ResourceType::Remote,
+            shard,
+            ResourceId::MAX_SLOT,
+            ResourceId::MAX_GENERATION,
+        );
+        assert_eq!(shard, resource_id.shard());
+        assert_eq!(base_value, resource_id.base_value());
+        assert_eq!(3, resource_id.adapter_id());
+        assert_eq!(ResourceType::Remote, resource_id.resource_type());
+    }
+
+    #[test]
+    fn reuse_slots() {
+        let generator = ResourceIdGenerator::new(0, ResourceType::Remote, 0);
+        let first = generator.generate().unwrap();
+        let second = generator.generate().unwrap();
+        assert_eq!((0, 0), (first.slot(), first.generation()));
+        assert_eq!((1, 0), (second.slot(), second.generation()));
+
+        generator.release(first);
+        let reused = generator.generate().unwrap();
+        assert_eq!((0, 1), (reused.slot(), reused.generation()));
+        assert_ne!(first, reused);
+
+        let new = generator.generate().unwrap();
+        assert_eq!((2, 0), (new.slot(), new.generation()));
+    }
+
+    #[test]
+    fn exhausted_generations() {
+        let generator = ResourceIdGenerator::new(0, ResourceType::Remote, 0);
+        let mut id = generator.generate().unwrap();
+        while id.generation() < ResourceId::MAX_GENERATION {
+            generator.release(id);
+            id = generator.generate().unwrap();
+            assert_eq!(0, id.slot());
+        }
+
+        // The slot is retired.
+        generator.release(id);
+        assert_eq!(1, generator.generate().unwrap().slot());
+        assert_eq!(2, generator.generate().unwrap().slot());
+    }
+
+    #[test]
+    fn exhausted_slots() {
+        let generator = ResourceIdGenerator::new(0, ResourceType::Remote, 0);
+        generator.next_slot.store(ResourceId::MAX_SLOT, Ordering::SeqCst);
+        let last = generator.generate().unwrap();
+        assert_eq!(ResourceId::MAX_SLOT, last.slot());
+        assert!(generator.generate().is_err());
+        assert!(generator.generate().is_err());
+
+        generator.release(last);
+        assert_eq!(ResourceId::MAX_SLOT, generator.generate().unwrap().slot());
+        assert!(generator.generate().is_err());
+    }
 }
@@ -0,0 +1,68 @@
+use std::net::{SocketAddr};
+use std::collections::hash_map::{DefaultHasher};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Strategy used to distribute the resources among the shards of a sharded network.
+/// See [`crate::network::split_sharded()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ShardBalance {
+    /// Each new resource is placed in the next shard.
+    #[default]
+    RoundRobin,
+
+    /// The shard is computed from a hash of the peer address.
+    /// The same address is always placed in the same shard.
+    /// Note that the WebSocket connections over Unix domain sockets share the address
+    /// `0.0.0.0:0`, so all of them are placed in the same shard.
+    PeerHash,
+}
+
+/// Chooses the shard of the new resources.
+pub struct ShardSelector {
+    balance: ShardBalance,
+    shards: usize,
+    next: AtomicUsize,
+}
+
+impl ShardSelector {
+    pub fn new(balance: ShardBalance, shards: usize) -> Self {
+        Self { balance, shards, next: AtomicUsize::new(0) }
+    }
+
+    pub fn select(&self, addr: SocketAddr) -> usize {
+        if self.shards == 1 {
+            return 0
+        }
+        match self.balance {
+            ShardBalance::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % self.shards,
+            ShardBalance::PeerHash => {
+                let mut hasher = DefaultHasher::new();
+                addr.hash(&mut hasher);
+                (hasher.finish() % self.shards as u64) as usize
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let selector = ShardSelector::new(ShardBalance::RoundRobin, 3);
+        let addr = "127.0.0.1:80".parse().unwrap();
+        let shards = (0..6).map(|_| selector.select(addr)).collect::<Vec<_>>();
+        assert_eq!(shards, [0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn peer_hash() {
+        let selector = ShardSelector::new(ShardBalance::PeerHash, 4);
+        let addr = "127.0.0.1:80".parse().unwrap();
+        let shard = selector.select(addr);
+        assert!(shard < 4);
+        assert!((0..10).all(|_| selector.select(addr) == shard));
+    }
+}
@@ -0,0 +1,264 @@
+use socket2::{Socket, SockAddr, TcpKeepalive};
+
+use std::net::{SocketAddr};
+
+use std::io::{self};
+use std::mem::{ManuallyDrop};
+use std::time::{Duration};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, FromRawSocket};
+
+/// Option of the OS socket of a resource.
+/// See [`crate::network::NetworkController::set_socket_option()`].
+/// They can also be set in the configurations used to connect or listen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SocketOption {
+    /// `TCP_NODELAY`: disables the Nagle algorithm, sending the data as soon as possible.
+    NoDelay(bool),
+
+    /// `SO_SNDBUF`: size of the OS send buffer.
+    /// Note that some systems (as Linux) report the double of the size set.
+    SendBufferSize(usize),
+
+    /// `SO_RCVBUF`: size of the OS receive buffer.
+    /// Note that some systems (as Linux) report the double of the size set.
+    RecvBufferSize(usize),
+
+    /// `IP_TOS` (`IPV6_TCLASS` for IPv6 sockets): type of service of the outgoing packets.
+    /// The DSCP value is placed in its 6 upper bits (`dscp << 2`).
+    Tos(u32),
+
+    /// `IP_TTL` (`IPV6_UNICAST_HOPS` for IPv6 sockets): time to live of the outgoing packets.
+    Ttl(u32),
+
+    /// `SO_KEEPALIVE`: enables the TCP keepalive with the time (`TCP_KEEPIDLE`) that the
+    /// connection must be idle before sending the first probe, or disables it with `None`.
+    Keepalive(Option<Duration>),
+
+    /// `IP_MULTICAST_TTL` (`IPV6_MULTICAST_HOPS` for IPv6 sockets): time to live of the outgoing
+    /// multicast packets. By default `1`, they do not leave the local network.
+    MulticastTtl(u32),
+
+    /// `IP_MULTICAST_LOOP` (`IPV6_MULTICAST_LOOP` for IPv6 sockets): whether the outgoing
+    /// multicast packets are also delivered to the sockets of the same host that joined the group.
+    /// Enabled by default.
+    MulticastLoop(bool),
+}
+
+/// Name of a [`SocketOption`], used to get its value.
+/// See [`crate::network::NetworkController::socket_option()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SocketOptionName {
+    NoDelay,
+    SendBufferSize,
+    RecvBufferSize,
+    Tos,
+    Ttl,
+    Keepalive,
+    MulticastTtl,
+    MulticastLoop,
+}
+
+impl SocketOption {
+    /// Returns the name of the option.
+    pub fn name(&self) -> SocketOptionName {
+        match self {
+            SocketOption::NoDelay(_) => SocketOptionName::NoDelay,
+            SocketOption::SendBufferSize(_) => SocketOptionName::SendBufferSize,
+            SocketOption::RecvBufferSize(_) => SocketOptionName::RecvBufferSize,
+            SocketOption::Tos(_) => SocketOptionName::Tos,
+            SocketOption::Ttl(_) => SocketOptionName::Ttl,
+            SocketOption::Keepalive(_) => SocketOptionName::Keepalive,
+            SocketOption::MulticastTtl(_) => SocketOptionName::MulticastTtl,
+            SocketOption::MulticastLoop(_) => SocketOptionName::MulticastLoop,
+        }
+    }
+
+    pub(crate) fn apply(&self, socket: &Socket) -> io::Result<()> {
+        match *self {
+            SocketOption::NoDelay(value) => socket.set_nodelay(value),
+            SocketOption::SendBufferSize(size) => socket.set_send_buffer_size(size),
+            SocketOption::RecvBufferSize(size) => socket.set_recv_buffer_size(size),
+            SocketOption::Tos(tos) => set_tos(socket, tos),
+            SocketOption::Ttl(ttl) => match is_ipv6(socket)? {
+                true => socket.set_unicast_hops_v6(ttl),
+                false => socket.set_ttl(ttl),
+            },
+            SocketOption::Keepalive(Some(time)) => {
+                socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(time))
+            }
+            SocketOption::Keepalive(None) => socket.set_keepalive(false),
+            SocketOption::MulticastTtl(ttl) => match is_ipv6(socket)? {
+                true => socket.set_multicast_hops_v6(ttl),
+                false => socket.set_multicast_ttl_v4(ttl),
+            },
+            SocketOption::MulticastLoop(value) => match is_ipv6(socket)? {
+                true => socket.set_multicast_loop_v6(value),
+                false => socket.set_multicast_loop_v4(value),
+            },
+        }
+    }
+}
+
+impl SocketOptionName {
+    pub(crate) fn read(self, socket: &Socket) -> io::Result<SocketOption> {
+        Ok(match self {
+            SocketOptionName::NoDelay => SocketOption::NoDelay(socket.nodelay()?),
+            SocketOptionName::SendBufferSize => {
+                SocketOption::SendBufferSize(socket.send_buffer_size()?)
+            }
+            SocketOptionName::RecvBufferSize => {
+                SocketOption::RecvBufferSize(socket.recv_buffer_size()?)
+            }
+            SocketOptionName::Tos => SocketOption::Tos(tos(socket)?),
+            SocketOptionName::Ttl => SocketOption::Ttl(match is_ipv6(socket)? {
+                true => socket.unicast_hops_v6()?,
+                false => socket.ttl()?,
+            }),
+            SocketOptionName::Keepalive => SocketOption::Keepalive(match socket.keepalive()? {
+                true => Some(keepalive_time(socket)?),
+                false => None,
+            }),
+            SocketOptionName::MulticastTtl => SocketOption::MulticastTtl(match is_ipv6(socket)? {
+                true => socket.multicast_hops_v6()?,
+                false => socket.multicast_ttl_v4()?,
+            }),
+            SocketOptionName::MulticastLoop => {
+                SocketOption::MulticastLoop(match is_ipv6(socket)? {
+                    true => socket.multicast_loop_v6()?,
+                    false => socket.multicast_loop_v4()?,
+                })
+            }
+        })
+    }
+}
+
+/// Sets the `options` in the socket.
+pub(crate) fn set_socket_options(socket: &Socket, options: &[SocketOption]) -> io::Result<()> {
+    options.iter().try_for_each(|option| option.apply(socket))
+}
+
+/// Creates a [`Socket`] over the OS socket of `source` without taking its ownership.
+#[cfg(unix)]
+pub(crate) fn borrow_socket(source: &impl AsRawFd) -> ManuallyDrop<Socket> {
+    // The socket is never dropped, so the OS socket will not be closed.
+    ManuallyDrop::new(unsafe { Socket::from_raw_fd(source.as_raw_fd()) })
+}
+
+/// Creates a [`Socket`] over the OS socket of `source` without taking its ownership.
+#[cfg(windows)]
+pub(crate) fn borrow_socket(source: &impl AsRawSocket) -> ManuallyDrop<Socket> {
+    // The socket is never dropped, so the OS socket will not be closed.
+    ManuallyDrop::new(unsafe { Socket::from_raw_socket(source.as_raw_socket()) })
+}
+
+/// Prepares a socket created outside the network to be registered in the poll,
+/// setting the non-blocking mode and the `options`.
+/// Returns the local address of the socket.
+pub(crate) fn adopt_socket(socket: &Socket, options: &[SocketOption]) -> io::Result<SocketAddr> {
+    socket.set_nonblocking(true)?;
+    set_socket_options(socket, options)?;
+    inet_addr(socket.local_addr()?)
+}
+
+/// Returns the address of an adopted socket as an IP address.
+pub(crate) fn inet_addr(addr: SockAddr) -> io::Result<SocketAddr> {
+    addr.as_socket().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "The socket is not an IP socket")
+    })
+}
+
+fn is_ipv6(socket: &Socket) -> io::Result<bool> {
+    Ok(socket.local_addr()?.is_ipv6())
+}
+
+#[allow(dead_code)] // Not all the systems have unsupported options
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "Socket option not supported in this system")
+}
+
+#[cfg(not(any(target_os = "solaris", target_os = "illumos", target_os = "haiku")))]
+fn set_tos(socket: &Socket, tos: u32) -> io::Result<()> {
+    match is_ipv6(socket)? {
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+        true => socket.set_tclass_v6(tos),
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+        true => Err(unsupported()),
+        false => socket.set_tos(tos),
+    }
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos", target_os = "haiku"))]
+fn set_tos(_: &Socket, _: u32) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(any(target_os = "solaris", target_os = "illumos", target_os = "haiku")))]
+fn tos(socket: &Socket) -> io::Result<u32> {
+    match is_ipv6(socket)? {
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+        true => socket.tclass_v6(),
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+        true => Err(unsupported()),
+        false => socket.tos(),
+    }
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos", target_os = "haiku"))]
+fn tos(_: &Socket) -> io::Result<u32> {
+    Err(unsupported())
+}
+
+#[cfg(not(any(windows, target_os = "haiku", target_os = "openbsd")))]
+fn keepalive_time(socket: &Socket) -> io::Result<Duration> {
+    socket.keepalive_time()
+}
+
+#[cfg(any(windows, target_os = "haiku", target_os = "openbsd"))]
+fn keepalive_time(_: &Socket) -> io::Result<Duration> {
+    Err(unsupported())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use socket2::{Domain, Type, Protocol};
+
+    #[test]
+    fn apply_and_read() {
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap();
+        socket.bind(&"127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap().into()).unwrap();
+
+        let options = [
+            SocketOption::NoDelay(true),
+            SocketOption::Tos(46 << 2),
+            SocketOption::Ttl(12),
+            SocketOption::Keepalive(Some(Duration::from_secs(30))),
+        ];
+        set_socket_options(&socket, &options).unwrap();
+        for option in options {
+            assert_eq!(option.name().read(&socket).unwrap(), option);
+        }
+
+        SocketOption::Keepalive(None).apply(&socket).unwrap();
+        let keepalive = SocketOptionName::Keepalive.read(&socket).unwrap();
+        assert_eq!(keepalive, SocketOption::Keepalive(None));
+    }
+
+    #[test]
+    fn multicast_options() {
+        for (domain, addr) in [(Domain::IPV4, "127.0.0.1:0"), (Domain::IPV6, "[::1]:0")] {
+            let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP)).unwrap();
+            socket.bind(&addr.parse::<std::net::SocketAddr>().unwrap().into()).unwrap();
+
+            let options = [SocketOption::MulticastTtl(5), SocketOption::MulticastLoop(false)];
+            set_socket_options(&socket, &options).unwrap();
+            for option in options {
+                assert_eq!(option.name().read(&socket).unwrap(), option);
+            }
+        }
+    }
+}
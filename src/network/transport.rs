@@ -7,7 +7,7 @@ use crate::adapters::framed_tcp::{FramedTcpAdapter, FramedTcpConnectConfig, Fram
 #[cfg(feature = "udp")]
 use crate::adapters::udp::{self, UdpAdapter, UdpConnectConfig, UdpListenConfig};
 #[cfg(feature = "websocket")]
-use crate::adapters::ws::{self, WsAdapter};
+use crate::adapters::ws::{self, WsAdapter, WsConnectConfig, WsListenConfig};
 
 use serde::{Serialize, Deserialize};
 
@@ -36,8 +36,9 @@ pub enum Transport {
     /// UDP protocol (available through the *udp* feature).
     /// Take into account that UDP is not connection oriented and a packet can be lost
     /// or received disordered.
-    /// If it is specified in the listener and the address is a Ipv4 in the range of multicast ips
-    /// (from `224.0.0.0` to `239.255.255.255`), the listener will be configured in multicast mode.
+    /// If it is specified in the listener and the address is in the range of multicast ips
+    /// (from `224.0.0.0` to `239.255.255.255` for Ipv4, or `ff00::/8` for Ipv6),
+    /// the listener will be configured in multicast mode.
     #[cfg(feature = "udp")]
     Udp,
 
@@ -166,7 +167,7 @@ pub enum TransportConnect {
     #[cfg(feature = "udp")]
     Udp(UdpConnectConfig),
     #[cfg(feature = "websocket")]
-    Ws,
+    Ws(WsConnectConfig),
 }
 
 impl TransportConnect {
@@ -179,7 +180,7 @@ impl TransportConnect {
             #[cfg(feature = "udp")]
             Self::Udp(_) => Transport::Udp,
             #[cfg(feature = "websocket")]
-            Self::Ws => Transport::Ws,
+            Self::Ws(_) => Transport::Ws,
         };
 
         transport.id()
@@ -196,12 +197,12 @@ impl From<Transport> for TransportConnect {
             #[cfg(feature = "udp")]
             Transport::Udp => Self::Udp(UdpConnectConfig::default()),
             #[cfg(feature = "websocket")]
-            Transport::Ws => Self::Ws,
+            Transport::Ws => Self::Ws(WsConnectConfig::default()),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum TransportListen {
     #[cfg(feature = "tcp")]
     Tcp(TcpListenConfig),
@@ -210,7 +211,7 @@ pub enum TransportListen {
     #[cfg(feature = "udp")]
     Udp(UdpListenConfig),
     #[cfg(feature = "websocket")]
-    Ws,
+    Ws(WsListenConfig),
 }
 
 impl TransportListen {
@@ -223,11 +224,25 @@ impl TransportListen {
             #[cfg(feature = "udp")]
             Self::Udp(_) => Transport::Udp,
             #[cfg(feature = "websocket")]
-            Self::Ws => Transport::Ws,
+            Self::Ws(_) => Transport::Ws,
         };
 
         transport.id()
     }
+
+    /// Enables the `SO_REUSEPORT` option in the config.
+    pub(crate) fn with_reuse_port(self) -> Self {
+        match self {
+            #[cfg(feature = "tcp")]
+            Self::Tcp(config) => Self::Tcp(config.with_reuse_port()),
+            #[cfg(feature = "tcp")]
+            Self::FramedTcp(config) => Self::FramedTcp(config.with_reuse_port()),
+            #[cfg(feature = "udp")]
+            Self::Udp(config) => Self::Udp(config.with_reuse_port()),
+            #[cfg(feature = "websocket")]
+            Self::Ws(config) => Self::Ws(config.with_reuse_port()),
+        }
+    }
 }
 
 impl From<Transport> for TransportListen {
@@ -240,7 +255,7 @@ impl From<Transport> for TransportListen {
             #[cfg(feature = "udp")]
             Transport::Udp => Self::Udp(UdpListenConfig::default()),
             #[cfg(feature = "websocket")]
-            Transport::Ws => Self::Ws,
+            Transport::Ws => Self::Ws(WsListenConfig::default()),
         }
     }
 }
@@ -1,5 +1,20 @@
-use crate::network::{self, NetworkController, NetworkProcessor, NetEvent, Endpoint, ResourceId};
-use crate::events::{self, EventSender, EventReceiver};
+#[cfg(feature = "async")]
+mod stream;
+
+#[cfg(feature = "async")]
+pub use stream::{EventStream};
+
+#[cfg(all(unix, feature = "os-signals"))]
+mod os_signals;
+
+#[cfg(all(unix, feature = "os-signals"))]
+pub use os_signals::{OsSignal};
+
+use crate::network::{
+    self, NetworkController, NetworkProcessor, NetEvent, Endpoint, ResourceId, Readiness, SourceId,
+    ShardBalance,
+};
+use crate::events::{self, EventSender, EventReceiver, TrySendError};
 use crate::util::thread::{NamespacedThread, OTHER_THREAD_ERR};
 
 use std::sync::{
@@ -8,6 +23,7 @@ use std::sync::{
 };
 use std::time::{Duration};
 use std::collections::{VecDeque};
+use std::cell::{RefCell};
 
 lazy_static::lazy_static! {
     static ref SAMPLING_TIMEOUT: Duration = Duration::from_millis(50);
@@ -25,6 +41,11 @@ pub enum NodeEvent<'a, S> {
     /// You can send signals with timers or priority.
     /// See [`EventSender`] to know about how to send signals.
     Signal(S),
+
+    /// The `NodeEvent` comes from a user source registered with
+    /// [`NetworkController::register_source()`].
+    /// The source is ready to perform the operation given by [`Readiness`].
+    Source(SourceId, Readiness),
 }
 
 impl<S: std::fmt::Debug> std::fmt::Debug for NodeEvent<'_, S> {
@@ -32,6 +53,9 @@ impl<S: std::fmt::Debug> std::fmt::Debug for NodeEvent<'_, S> {
         match self {
             NodeEvent::Network(net_event) => write!(f, "NodeEvent::Network({net_event:?})"),
             NodeEvent::Signal(signal) => write!(f, "NodeEvent::Signal({signal:?})"),
+            NodeEvent::Source(id, readiness) => {
+                write!(f, "NodeEvent::Source({id}, {readiness:?})")
+            }
         }
     }
 }
@@ -41,15 +65,15 @@ impl<'a, S> NodeEvent<'a, S> {
     pub fn network(self) -> NetEvent<'a> {
         match self {
             NodeEvent::Network(net_event) => net_event,
-            NodeEvent::Signal(..) => panic!("NodeEvent must be a NetEvent"),
+            _ => panic!("NodeEvent must be a NetEvent"),
         }
     }
 
     /// Assume the event is a [`NodeEvent::Signal`], panics if not.
     pub fn signal(self) -> S {
         match self {
-            NodeEvent::Network(..) => panic!("NodeEvent must be a Signal"),
             NodeEvent::Signal(signal) => signal,
+            _ => panic!("NodeEvent must be a Signal"),
         }
     }
 }
@@ -68,6 +92,10 @@ pub enum StoredNodeEvent<S> {
     /// You can send signals with timers or priority.
     /// See [`EventSender`] to know about how to send signals.
     Signal(S),
+
+    /// The `StoredNodeEvent` comes from a user source registered with
+    /// [`NetworkController::register_source()`].
+    Source(SourceId, Readiness),
 }
 
 impl<S: std::fmt::Debug> std::fmt::Debug for StoredNodeEvent<S> {
@@ -75,6 +103,9 @@ impl<S: std::fmt::Debug> std::fmt::Debug for StoredNodeEvent<S> {
         match self {
             StoredNodeEvent::Network(net_event) => write!(f, "NodeEvent::Network({net_event:?})"),
             StoredNodeEvent::Signal(signal) => write!(f, "NodeEvent::Signal({signal:?})"),
+            StoredNodeEvent::Source(id, readiness) => {
+                write!(f, "NodeEvent::Source({id}, {readiness:?})")
+            }
         }
     }
 }
@@ -84,15 +115,15 @@ impl<S> StoredNodeEvent<S> {
     pub fn network(self) -> StoredNetEvent {
         match self {
             StoredNodeEvent::Network(net_event) => net_event,
-            StoredNodeEvent::Signal(..) => panic!("NodeEvent must be a NetEvent"),
+            _ => panic!("NodeEvent must be a NetEvent"),
         }
     }
 
     /// Assume the event is a [`StoredNodeEvent::Signal`], panics if not.
     pub fn signal(self) -> S {
         match self {
-            StoredNodeEvent::Network(..) => panic!("NodeEvent must be a Signal"),
             StoredNodeEvent::Signal(signal) => signal,
+            _ => panic!("NodeEvent must be a Signal"),
         }
     }
 }
@@ -102,6 +133,7 @@ impl<S> From<NodeEvent<'_, S>> for StoredNodeEvent<S> {
         match node_event {
             NodeEvent::Network(net_event) => StoredNodeEvent::Network(net_event.into()),
             NodeEvent::Signal(signal) => StoredNodeEvent::Signal(signal),
+            NodeEvent::Source(id, readiness) => StoredNodeEvent::Source(id, readiness),
         }
     }
 }
@@ -168,6 +200,7 @@ impl StoredNetEvent {
 ///         Signal::Close => handler.stop(), //Received after 1 sec
 ///         Signal::Tick => { /* ... */ },
 ///     },
+///     NodeEvent::Source(..) => (),
 /// });
 /// ```
 ///
@@ -178,7 +211,30 @@ impl StoredNetEvent {
 /// let (handler, listener) = node::split::<()>();
 /// ```
 pub fn split<S: Send>() -> (NodeHandler<S>, NodeListener<S>) {
-    let (network_controller, network_processor) = network::split();
+    split_sharded(1, ShardBalance::default())
+}
+
+/// Creates a node already working whose network is distributed among several `shards`,
+/// each one processed by its own thread.
+/// It allows to process the network with several cores when there are many connections.
+/// See [`network::split_sharded()`] to know how the resources are distributed.
+///
+/// By default, the events of all shards are funneled into the callback of
+/// [`NodeListener::for_each()`] or [`NodeListener::for_each_async()`].
+/// Use [`NodeListener::for_each_shard_async()`] to have a callback by shard.
+///
+/// # Example
+/// ```rust
+/// use message_io::node::{self, NodeEvent};
+/// use message_io::network::{ShardBalance};
+///
+/// let (handler, listener) = node::split_sharded::<()>(4, ShardBalance::RoundRobin);
+/// ```
+pub fn split_sharded<S: Send>(
+    shards: usize,
+    balance: ShardBalance,
+) -> (NodeHandler<S>, NodeListener<S>) {
+    let (network_controller, network_processors) = network::split_sharded(shards, balance);
     let (signal_sender, signal_receiver) = events::split();
     let running = AtomicBool::new(true);
 
@@ -186,17 +242,24 @@ pub fn split<S: Send>() -> (NodeHandler<S>, NodeListener<S>) {
         network: network_controller,
         signals: signal_sender,
         running,
+        #[cfg(all(unix, feature = "os-signals"))]
+        os_signals: Mutex::new(None),
     }));
 
-    let listener = NodeListener::new(network_processor, signal_receiver, handler.clone());
+    let listener = NodeListener::new(network_processors, signal_receiver, handler.clone());
 
     (handler, listener)
 }
 
+#[cfg(all(unix, feature = "os-signals"))]
+type OsSignalMapper<S> = Box<dyn FnMut(OsSignal) -> S + Send>;
+
 struct NodeHandlerImpl<S> {
     network: NetworkController,
     signals: EventSender<S>,
     running: AtomicBool,
+    #[cfg(all(unix, feature = "os-signals"))]
+    os_signals: Mutex<Option<(os_signals::OsSignalCatcher, OsSignalMapper<S>)>>,
 }
 
 /// A shareable and clonable entity that allows to deal with
@@ -232,45 +295,208 @@ impl<S> NodeHandler<S> {
     }
 }
 
+impl<S: Send + 'static> NodeHandler<S> {
+    /// Catches the given OS `signals` (e.g. ctrl-c) and sends them to the node as signals.
+    /// Each caught [`OsSignal`] is converted by the `mapper` into a signal of the node,
+    /// that will be received as a [`NodeEvent::Signal`] like any other signal.
+    ///
+    /// The signals are caught with a self-pipe registered in the node poll,
+    /// so the default action of these signals (usually finish the process) will not be performed
+    /// until [`NodeHandler::release_os_signals()`] is called or the node is dropped.
+    /// Calling this function again replaces the previous signals and mapper.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::node::{self, NodeEvent, OsSignal};
+    ///
+    /// enum Signal {
+    ///     Shutdown,
+    ///     // Other signals here.
+    /// }
+    ///
+    /// let (handler, listener) = node::split();
+    /// handler
+    ///     .catch_os_signals(&[OsSignal::Interrupt, OsSignal::Terminate], |_| Signal::Shutdown)
+    ///     .unwrap();
+    ///
+    /// # handler.signals().send(Signal::Shutdown);
+    /// listener.for_each(move |event| match event {
+    ///     NodeEvent::Network(_) => { /* Your logic here */ },
+    ///     NodeEvent::Signal(Signal::Shutdown) => handler.stop(), // Graceful shutdown
+    ///     NodeEvent::Source(..) => (),
+    /// });
+    /// ```
+    #[cfg(all(unix, feature = "os-signals"))]
+    pub fn catch_os_signals(
+        &self,
+        signals: &[OsSignal],
+        mapper: impl FnMut(OsSignal) -> S + Send + 'static,
+    ) -> std::io::Result<()> {
+        self.release_os_signals();
+        let catcher = os_signals::OsSignalCatcher::new(&self.0.network, signals)?;
+        *self.0.os_signals.lock().expect(OTHER_THREAD_ERR) = Some((catcher, Box::new(mapper)));
+        Ok(())
+    }
+
+    /// Stops catching the OS signals set by [`NodeHandler::catch_os_signals()`],
+    /// restoring their previous action.
+    #[cfg(all(unix, feature = "os-signals"))]
+    pub fn release_os_signals(&self) {
+        if let Some((catcher, _)) = self.0.os_signals.lock().expect(OTHER_THREAD_ERR).take() {
+            catcher.release(&self.0.network);
+        }
+    }
+
+    /// Process the event of a source if it is an internal one.
+    /// Returns `false` if the source is a user source.
+    fn process_internal_source(&self, _id: SourceId) -> bool {
+        #[cfg(all(unix, feature = "os-signals"))]
+        if let Some((catcher, mapper)) = &mut *self.0.os_signals.lock().expect(OTHER_THREAD_ERR) {
+            if catcher.source_id() == _id {
+                catcher.read(|os_signal| {
+                    log::trace!("Caught OS signal {:?}", os_signal);
+                    self.0.signals.send(mapper(os_signal));
+                });
+                return true
+            }
+        }
+        false
+    }
+}
+
 impl<S: Send + 'static> Clone for NodeHandler<S> {
     fn clone(&self) -> Self {
         Self(self.0.clone())
     }
 }
 
+/// Event generated by the network before calling [`NodeListener::for_each()`].
+#[derive(Debug)]
+enum CachedEvent {
+    Network(StoredNetEvent),
+    Source(SourceId, Readiness),
+}
+
+impl CachedEvent {
+    fn borrow<S>(&self) -> NodeEvent<'_, S> {
+        match self {
+            CachedEvent::Network(net_event) => NodeEvent::Network(net_event.borrow()),
+            CachedEvent::Source(id, readiness) => NodeEvent::Source(*id, *readiness),
+        }
+    }
+}
+
 /// Listen events for network and signal events.
 pub struct NodeListener<S: Send + 'static> {
-    network_cache_thread: NamespacedThread<(NetworkProcessor, VecDeque<StoredNetEvent>)>,
+    network_cache_threads: Vec<NamespacedThread<(NetworkProcessor, VecDeque<CachedEvent>)>>,
     cache_running: Arc<AtomicBool>,
     signal_receiver: EventReceiver<S>,
     handler: NodeHandler<S>,
 }
 
+type EventCallback<'a, S> = Mutex<dyn FnMut(NodeEvent<S>) + Send + 'a>;
+type SharedEventCallback<'a, S> = Arc<EventCallback<'a, S>>;
+
 impl<S: Send + 'static> NodeListener<S> {
     fn new(
-        mut network_processor: NetworkProcessor,
+        network_processors: Vec<NetworkProcessor>,
         signal_receiver: EventReceiver<S>,
         handler: NodeHandler<S>,
     ) -> NodeListener<S> {
-        // Spawn the network thread to be able to perform correctly any network action before
+        // Spawn the network threads to be able to perform correctly any network action before
         // for_each() call. Any generated event would be cached and offered to the user when they
         // call for_each().
         let cache_running = Arc::new(AtomicBool::new(true));
-        let network_cache_thread = {
-            let cache_running = cache_running.clone();
-            let mut cache = VecDeque::new();
-            NamespacedThread::spawn("node-network-cache-thread", move || {
-                while cache_running.load(Ordering::Relaxed) {
-                    network_processor.process_poll_event(Some(*SAMPLING_TIMEOUT), |net_event| {
-                        log::trace!("Cached {:?}", net_event);
-                        cache.push_back(net_event.into());
-                    });
-                }
-                (network_processor, cache)
+        let network_cache_threads = network_processors
+            .into_iter()
+            .enumerate()
+            .map(|(shard, mut network_processor)| {
+                let cache_running = cache_running.clone();
+                let handler = handler.clone();
+                let cache = RefCell::new(VecDeque::new());
+                let name = Self::thread_name("node-network-cache-thread", shard);
+                NamespacedThread::spawn(&name, move || {
+                    while cache_running.load(Ordering::Relaxed) {
+                        network_processor.process_poll_event_with_sources(
+                            Some(*SAMPLING_TIMEOUT),
+                            |net_event| {
+                                log::trace!("Cached {:?}", net_event);
+                                let event = CachedEvent::Network(net_event.into());
+                                cache.borrow_mut().push_back(event);
+                            },
+                            |id, readiness| {
+                                if !handler.process_internal_source(id) {
+                                    log::trace!("Cached source {} {:?}", id, readiness);
+                                    let event = CachedEvent::Source(id, readiness);
+                                    cache.borrow_mut().push_back(event);
+                                }
+                            },
+                        );
+                    }
+                    (network_processor, cache.into_inner())
+                })
             })
-        };
+            .collect();
 
-        NodeListener { network_cache_thread, cache_running, signal_receiver, handler }
+        NodeListener { network_cache_threads, cache_running, signal_receiver, handler }
+    }
+
+    /// The first shard keeps the thread name of a non-sharded node.
+    fn thread_name(name: &str, shard: usize) -> String {
+        match shard {
+            0 => name.into(),
+            shard => format!("{name}-{shard}"),
+        }
+    }
+
+    /// Stops the cache threads, returning the processors with their cached events.
+    fn stop_cache(&mut self) -> Vec<(NetworkProcessor, VecDeque<CachedEvent>)> {
+        self.cache_running.store(false, Ordering::Relaxed);
+        self.network_cache_threads.iter_mut().map(|thread| thread.join()).collect()
+    }
+
+    /// Process the network of a shard until the node is stopped.
+    fn process_network(
+        network_processor: &mut NetworkProcessor,
+        handler: &NodeHandler<S>,
+        event_callback: &EventCallback<'_, S>,
+    ) {
+        while handler.is_running() {
+            network_processor.process_poll_event_with_sources(
+                Some(*SAMPLING_TIMEOUT),
+                |net_event| {
+                    let mut event_callback = event_callback.lock().expect(OTHER_THREAD_ERR);
+                    if handler.is_running() {
+                        event_callback(NodeEvent::Network(net_event));
+                    }
+                },
+                |id, readiness| {
+                    if handler.process_internal_source(id) {
+                        return
+                    }
+                    let mut event_callback = event_callback.lock().expect(OTHER_THREAD_ERR);
+                    if handler.is_running() {
+                        event_callback(NodeEvent::Source(id, readiness));
+                    }
+                },
+            );
+        }
+    }
+
+    /// Process the signals until the node is stopped.
+    fn process_signals(
+        signal_receiver: &mut EventReceiver<S>,
+        handler: &NodeHandler<S>,
+        event_callback: &EventCallback<'_, S>,
+    ) {
+        while handler.is_running() {
+            if let Some(signal) = signal_receiver.receive_timeout(*SAMPLING_TIMEOUT) {
+                let mut event_callback = event_callback.lock().expect(OTHER_THREAD_ERR);
+                if handler.is_running() {
+                    event_callback(NodeEvent::Signal(signal));
+                }
+            }
+        }
     }
 
     /// Iterate indefinitely over all generated `NetEvent`.
@@ -278,6 +504,9 @@ impl<S: Send + 'static> NodeListener<S> {
     ///
     /// Note that any events generated before calling this function (e.g. some connection was done)
     /// will be stored and offered once you call `for_each()`.
+    ///
+    /// In a sharded node (see [`split_sharded()`]), each shard is processed by its own thread
+    /// and all the events are funneled into this callback, that is never called concurrently.
     /// # Example
     /// ```
     /// use message_io::node::{self, NodeEvent};
@@ -290,71 +519,76 @@ impl<S: Send + 'static> NodeListener<S> {
     /// listener.for_each(move |event| match event {
     ///     NodeEvent::Network(net_event) => { /* Your logic here */ },
     ///     NodeEvent::Signal(_) => handler.stop(),
+    ///     NodeEvent::Source(..) => (),
     /// });
     /// // Blocked here until handler.stop() is called (1 sec).
     /// println!("Node is stopped");
     /// ```
-    pub fn for_each(mut self, mut event_callback: impl FnMut(NodeEvent<S>)) {
+    pub fn for_each(mut self, event_callback: impl FnMut(NodeEvent<S>)) {
+        // This struct is used to allow passing the no sendable event_callback
+        // into the signal and network threads.
+        // It is safe because the thread are scoped and the callback is managed by a lock,
+        // so only one call is performed at the same time.
+        // It implies that any object moved into the callback do not have
+        // any concurrence issues.
+        struct SendableEventCallback<F>(F);
+        #[allow(clippy::non_send_fields_in_send_ty)]
+        unsafe impl<F> Send for SendableEventCallback<F> {}
+
+        let mut event_callback = SendableEventCallback(event_callback);
+
         // Stop cache events
-        self.cache_running.store(false, Ordering::Relaxed);
-        let (mut network_processor, mut cache) = self.network_cache_thread.join();
+        let shards = self.stop_cache();
 
         // Dispatch the catched events first.
-        while let Some(event) = cache.pop_front() {
-            let net_event = event.borrow();
-            log::trace!("Read from cache {:?}", net_event);
-            event_callback(NodeEvent::Network(net_event));
-            if !self.handler.is_running() {
-                return;
+        let mut network_processors = Vec::new();
+        for (network_processor, mut cache) in shards {
+            while let Some(event) = cache.pop_front() {
+                log::trace!("Read from cache {:?}", event);
+                (event_callback.0)(event.borrow());
+                if !self.handler.is_running() {
+                    return;
+                }
             }
+            network_processors.push(network_processor);
         }
 
         crossbeam_utils::thread::scope(|scope| {
-            let multiplexed = Arc::new(Mutex::new(event_callback));
+            let multiplexed: SharedEventCallback<S> =
+                Arc::new(Mutex::new(move |event: NodeEvent<'_, S>| (event_callback.0)(event)));
 
             let _signal_thread = {
                 let mut signal_receiver = std::mem::take(&mut self.signal_receiver);
                 let handler = self.handler.clone();
-
-                // This struct is used to allow passing the no sendable event_callback
-                // into the signal thread.
-                // It is safe because the thread are scoped and the callback is managed by a lock,
-                // so only one call is performed at the same time.
-                // It implies that any object moved into the callback do not have
-                // any concurrence issues.
-                #[allow(clippy::type_complexity)]
-                struct SendableEventCallback<'a, S>(Arc<Mutex<dyn FnMut(NodeEvent<S>) + 'a>>);
-                #[allow(clippy::non_send_fields_in_send_ty)]
-                unsafe impl<S> Send for SendableEventCallback<'_, S> {}
-
-                let multiplexed = SendableEventCallback(multiplexed.clone());
+                let multiplexed = multiplexed.clone();
 
                 scope
                     .builder()
-                    .name(String::from("node-network-thread"))
-                    .spawn(move |_| {
-                        while handler.is_running() {
-                            if let Some(signal) = signal_receiver.receive_timeout(*SAMPLING_TIMEOUT)
-                            {
-                                let mut event_callback =
-                                    multiplexed.0.lock().expect(OTHER_THREAD_ERR);
-                                if handler.is_running() {
-                                    event_callback(NodeEvent::Signal(signal));
-                                }
-                            }
-                        }
-                    })
+                    .name(String::from("node-signal-thread"))
+                    .spawn(move |_| Self::process_signals(&mut signal_receiver, &handler, &*multiplexed))
                     .unwrap()
             };
 
-            while self.handler.is_running() {
-                network_processor.process_poll_event(Some(*SAMPLING_TIMEOUT), |net_event| {
-                    let mut event_callback = multiplexed.lock().expect(OTHER_THREAD_ERR);
-                    if self.handler.is_running() {
-                        event_callback(NodeEvent::Network(net_event));
-                    }
-                });
-            }
+            // The first shard is processed by the current thread.
+            let mut network_processors = network_processors.into_iter();
+            let mut network_processor = network_processors.next().unwrap();
+
+            let _shard_threads = network_processors
+                .enumerate()
+                .map(|(index, mut network_processor)| {
+                    let handler = self.handler.clone();
+                    let multiplexed = multiplexed.clone();
+                    scope
+                        .builder()
+                        .name(Self::thread_name("node-network-thread", index + 1))
+                        .spawn(move |_| {
+                            Self::process_network(&mut network_processor, &handler, &*multiplexed)
+                        })
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            Self::process_network(&mut network_processor, &self.handler, &*multiplexed);
         })
         .unwrap();
     }
@@ -380,6 +614,7 @@ impl<S: Send + 'static> NodeListener<S> {
     /// let task = listener.for_each_async(move |event| match event {
     ///      NodeEvent::Network(net_event) => { /* Your logic here */ },
     ///      NodeEvent::Signal(_) => handler.stop(),
+    ///      NodeEvent::Source(..) => (),
     /// });
     /// // for_each_async() will act asynchronous during 'task' lifetime.
     ///
@@ -393,63 +628,108 @@ impl<S: Send + 'static> NodeListener<S> {
     /// println!("Node is stopped");
     /// ```
     pub fn for_each_async(
-        mut self,
+        self,
         event_callback: impl FnMut(NodeEvent<S>) + Send + 'static,
     ) -> NodeTask {
-        // Stop cache events
-        self.cache_running.store(false, Ordering::Relaxed);
-        let (mut network_processor, mut cache) = self.network_cache_thread.join();
-
-        let multiplexed = Arc::new(Mutex::new(event_callback));
+        let multiplexed: SharedEventCallback<'static, S> = Arc::new(Mutex::new(event_callback));
+        let callbacks = vec![multiplexed; self.network_cache_threads.len()];
+        self.run_async(callbacks)
+    }
 
-        // To avoid processing stops while the node is configuring,
-        // the user callback locked until the function ends.
-        let _locked = multiplexed.lock().expect(OTHER_THREAD_ERR);
+    /// Similar to [`NodeListener::for_each_async()`] but with a different callback by shard
+    /// in a sharded node (see [`split_sharded()`]).
+    /// The callbacks are created by `create_callback`, that receives the shard index.
+    /// Each callback receives the events of its shard from the thread of that shard,
+    /// so the events of different shards are processed concurrently.
+    /// The signals are received by the callback of the shard `0`.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::node::{self, NodeEvent};
+    /// use message_io::network::{Transport, NetEvent, ShardBalance};
+    ///
+    /// let (handler, listener) = node::split_sharded(4, ShardBalance::RoundRobin);
+    /// handler.signals().send_with_timer((), std::time::Duration::from_secs(1));
+    /// handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    ///
+    /// let task = listener.for_each_shard_async(|shard| {
+    ///     let handler = handler.clone();
+    ///     move |event| match event {
+    ///         NodeEvent::Network(NetEvent::Message(endpoint, data)) => {
+    ///             assert_eq!(endpoint.resource_id().shard(), shard);
+    ///             handler.network().send(endpoint, data);
+    ///         }
+    ///         NodeEvent::Network(_) => (),
+    ///         NodeEvent::Signal(_) => handler.stop(),
+    ///         NodeEvent::Source(..) => (),
+    ///     }
+    /// });
+    /// ```
+    pub fn for_each_shard_async<C>(self, mut create_callback: impl FnMut(usize) -> C) -> NodeTask
+    where C: FnMut(NodeEvent<S>) + Send + 'static {
+        let callbacks = (0..self.network_cache_threads.len())
+            .map(|shard| {
+                let callback: SharedEventCallback<'static, S> =
+                    Arc::new(Mutex::new(create_callback(shard)));
+                callback
+            })
+            .collect();
+        self.run_async(callbacks)
+    }
 
-        let network_thread = {
-            let multiplexed = multiplexed.clone();
-            let handler = self.handler.clone();
+    /// Process asynchronously each shard with its callback.
+    /// The signals are processed with the callback of the first shard.
+    fn run_async(mut self, callbacks: Vec<SharedEventCallback<'static, S>>) -> NodeTask {
+        // Stop cache events
+        let shards = self.stop_cache();
 
-            NamespacedThread::spawn("node-network-thread", move || {
-                while let Some(event) = cache.pop_front() {
-                    let net_event = event.borrow();
-                    log::trace!("Read from cache {:?}", net_event);
-                    let mut event_callback = multiplexed.lock().expect(OTHER_THREAD_ERR);
-                    event_callback(NodeEvent::Network(net_event));
-                    if !handler.is_running() {
-                        return;
+        // To avoid processing stops while the node is configuring,
+        // the user callbacks are locked until the function ends.
+        let mut locked_callbacks: Vec<&SharedEventCallback<S>> = Vec::new();
+        for callback in &callbacks {
+            if !locked_callbacks.iter().any(|locked| Arc::ptr_eq(locked, callback)) {
+                locked_callbacks.push(callback);
+            }
+        }
+        let _locked = locked_callbacks
+            .iter()
+            .map(|callback| callback.lock().expect(OTHER_THREAD_ERR))
+            .collect::<Vec<_>>();
+
+        let network_threads = shards
+            .into_iter()
+            .zip(callbacks.iter().cloned())
+            .enumerate()
+            .map(|(shard, ((mut network_processor, mut cache), callback))| {
+                let handler = self.handler.clone();
+                let name = Self::thread_name("node-network-thread", shard);
+
+                NamespacedThread::spawn(&name, move || {
+                    while let Some(event) = cache.pop_front() {
+                        log::trace!("Read from cache {:?}", event);
+                        let mut event_callback = callback.lock().expect(OTHER_THREAD_ERR);
+                        event_callback(event.borrow());
+                        if !handler.is_running() {
+                            return;
+                        }
                     }
-                }
 
-                while handler.is_running() {
-                    network_processor.process_poll_event(Some(*SAMPLING_TIMEOUT), |net_event| {
-                        let mut event_callback = multiplexed.lock().expect(OTHER_THREAD_ERR);
-                        if handler.is_running() {
-                            event_callback(NodeEvent::Network(net_event));
-                        }
-                    });
-                }
+                    Self::process_network(&mut network_processor, &handler, &*callback);
+                })
             })
-        };
+            .collect();
 
         let signal_thread = {
-            let multiplexed = multiplexed.clone();
+            let callback = callbacks[0].clone();
             let mut signal_receiver = std::mem::take(&mut self.signal_receiver);
             let handler = self.handler.clone();
 
             NamespacedThread::spawn("node-signal-thread", move || {
-                while handler.is_running() {
-                    if let Some(signal) = signal_receiver.receive_timeout(*SAMPLING_TIMEOUT) {
-                        let mut event_callback = multiplexed.lock().expect(OTHER_THREAD_ERR);
-                        if handler.is_running() {
-                            event_callback(NodeEvent::Signal(signal));
-                        }
-                    }
-                }
+                Self::process_signals(&mut signal_receiver, &handler, &*callback);
             })
         };
 
-        NodeTask { network_thread, signal_thread }
+        NodeTask { network_threads, signal_thread }
     }
 
     /// Consumes the listener to create a `NodeTask` and an `EventReceiver` where the events
@@ -476,6 +756,7 @@ impl<S: Send + 'static> NodeListener<S> {
     ///     match receiver.receive() {
     ///         NodeEvent::Network(net_event) => { /* Your logic here */ },
     ///         NodeEvent::Signal(_) => break handler.stop(),
+    ///         NodeEvent::Source(..) => (),
     ///     }
     /// }
     /// ```
@@ -484,6 +765,97 @@ impl<S: Send + 'static> NodeListener<S> {
         let task = self.for_each_async(move |node_event| sender.send(node_event.into()));
         (task, receiver)
     }
+
+    /// Similar to [`NodeListener::enqueue()`] but the returned `EventReceiver` can hold
+    /// at most `capacity` events.
+    ///
+    /// When the queue is full, the node stops processing events until the user receives some
+    /// of them.
+    /// It implies that no more data is read from the network,
+    /// so the backpressure is propagated to the remote senders through the transport
+    /// (e.g. the TCP flow control).
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::node::{self, StoredNodeEvent as NodeEvent};
+    /// use message_io::network::Transport;
+    ///
+    /// let (handler, listener) = node::split();
+    /// handler.signals().send_with_timer((), std::time::Duration::from_secs(1));
+    /// let (id, addr) = handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    ///
+    /// let (task, mut receiver) = listener.enqueue_bounded(1024);
+    ///
+    /// loop {
+    ///     match receiver.receive() {
+    ///         NodeEvent::Network(net_event) => { /* Your logic here */ },
+    ///         NodeEvent::Signal(_) => break handler.stop(),
+    ///         NodeEvent::Source(..) => (),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn enqueue_bounded(
+        self,
+        capacity: usize,
+    ) -> (NodeTask, EventReceiver<StoredNodeEvent<S>>) {
+        let receiver = EventReceiver::<StoredNodeEvent<S>>::bounded(capacity);
+        let sender = receiver.sender().clone();
+        let handler = self.handler.clone();
+        let task = self.for_each_async(move |node_event| {
+            let mut event = node_event.into();
+            // The wait is sampled to not block the node forever if it is stopped.
+            while handler.is_running() {
+                match sender.send_timeout(event, *SAMPLING_TIMEOUT) {
+                    Ok(()) => break,
+                    Err(TrySendError::Full(rejected)) => event = rejected,
+                    Err(TrySendError::Disconnected(_)) => break,
+                }
+            }
+        });
+        (task, receiver)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: Send + 'static> NodeListener<S> {
+    /// Consumes the listener to create a `NodeTask` and an [`EventStream`] where the events
+    /// of this node will be sent (available through the *async* feature).
+    /// It is the asynchronous version of [`NodeListener::enqueue()`]:
+    /// the events can be awaited from any async runtime.
+    /// The stream finishes once [`NodeHandler::stop()`] is called and the pending
+    /// events have been consumed.
+    ///
+    /// # Example
+    /// ```
+    /// use message_io::node::{self, StoredNodeEvent as NodeEvent};
+    /// use message_io::network::Transport;
+    ///
+    /// use futures::stream::{StreamExt};
+    ///
+    /// let (handler, listener) = node::split();
+    /// handler.signals().send_with_timer((), std::time::Duration::from_secs(1));
+    /// let (id, addr) = handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+    ///
+    /// let (task, mut stream) = listener.into_stream();
+    ///
+    /// futures::executor::block_on(async {
+    ///     while let Some(event) = stream.next().await {
+    ///         match event {
+    ///             NodeEvent::Network(net_event) => { /* Your logic here */ },
+    ///             NodeEvent::Signal(_) => handler.stop(),
+    ///             NodeEvent::Source(..) => (),
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn into_stream(self) -> (NodeTask, EventStream<S>) {
+        let (sender, stream) = stream::channel();
+        let task = self.for_each_async(move |node_event| sender.send(node_event.into()));
+        (task, stream)
+    }
 }
 
 impl<S: Send + 'static> Drop for NodeListener<S> {
@@ -499,7 +871,7 @@ impl<S: Send + 'static> Drop for NodeListener<S> {
 /// [`NodeHandler::stop()`] before or from another thread.
 #[must_use = "The NodeTask must be used or the asynchronous task will be dropped in return"]
 pub struct NodeTask {
-    network_thread: NamespacedThread<()>,
+    network_threads: Vec<NamespacedThread<()>>,
     signal_thread: NamespacedThread<()>,
 }
 
@@ -509,7 +881,9 @@ impl NodeTask {
     /// To finalize the task call [`NodeHandler::stop()`].
     /// Calling `wait()` over an already finished task do not block.
     pub fn wait(&mut self) {
-        self.network_thread.try_join();
+        self.network_threads.iter_mut().for_each(|thread| {
+            thread.try_join();
+        });
         self.signal_thread.try_join();
     }
 }
@@ -518,6 +892,7 @@ impl NodeTask {
 mod tests {
     use super::*;
     use std::time::{Duration};
+    use std::collections::{HashSet};
 
     #[test]
     fn create_node_and_drop() {
@@ -577,6 +952,57 @@ mod tests {
         task.wait();
     }
 
+    #[test]
+    fn enqueue_bounded() {
+        let (handler, listener) = split();
+        for i in 0..3 {
+            handler.signals().send(i);
+        }
+
+        let (mut task, mut receiver) = listener.enqueue_bounded(1);
+        std::thread::sleep(Duration::from_millis(250));
+
+        // The node is waiting for space in the queue.
+        for i in 0..3 {
+            assert_eq!(receiver.receive_timeout(Duration::from_millis(2000)).unwrap().signal(), i);
+        }
+        assert!(receiver.try_receive().is_none());
+
+        handler.stop();
+        task.wait();
+    }
+
+    #[test]
+    fn stop_enqueue_bounded_while_full() {
+        let (handler, listener) = split();
+        handler.signals().send(());
+        handler.signals().send(());
+
+        let (mut task, _receiver) = listener.enqueue_bounded(1);
+        std::thread::sleep(Duration::from_millis(250));
+
+        handler.stop();
+        task.wait(); // Not blocked by the full queue
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn stream() {
+        use futures::stream::{StreamExt};
+
+        let (handler, listener) = split();
+        handler.signals().send_with_timer("first", Duration::from_millis(100));
+        handler.signals().send_with_timer("second", Duration::from_millis(200));
+
+        let (_task, mut stream) = listener.into_stream();
+        futures::executor::block_on(async {
+            assert_eq!(stream.next().await.unwrap().signal(), "first");
+            assert_eq!(stream.next().await.unwrap().signal(), "second");
+            handler.stop();
+            assert!(stream.next().await.is_none());
+        });
+    }
+
     #[test]
     fn wait_task() {
         let (handler, listener) = split();
@@ -603,4 +1029,176 @@ mod tests {
         task.wait();
         assert!(!handler.is_running());
     }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn sharded_node() {
+        use crate::network::{Transport};
+
+        const CONNECTIONS: usize = 8;
+
+        let (handler, listener) = split_sharded(4, ShardBalance::RoundRobin);
+        handler.signals().send_with_timer((), Duration::from_secs(5));
+
+        let (_, addr) = handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        for _ in 0..CONNECTIONS {
+            handler.network().connect(Transport::FramedTcp, addr).unwrap();
+        }
+
+        let mut shards = HashSet::new();
+        let mut received = 0;
+        let inner_handler = handler.clone();
+        listener.for_each(move |event| match event {
+            NodeEvent::Network(NetEvent::Connected(endpoint, status)) => {
+                assert!(status);
+                inner_handler.network().send(endpoint, &[42]);
+            }
+            NodeEvent::Network(NetEvent::Accepted(..)) => (),
+            NodeEvent::Network(NetEvent::Message(endpoint, data)) => {
+                assert_eq!(data, [42]);
+                shards.insert(endpoint.resource_id().shard());
+                received += 1;
+                if received == CONNECTIONS {
+                    assert!(shards.len() > 1);
+                    inner_handler.stop();
+                }
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        });
+
+        assert!(!handler.is_running());
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn sharded_node_callback_by_shard() {
+        use crate::network::{Transport};
+
+        const SHARDS: usize = 4;
+
+        let (handler, listener) = split_sharded::<()>(SHARDS, ShardBalance::RoundRobin);
+
+        let (_, addr) = handler.network().listen(Transport::FramedTcp, "127.0.0.1:0").unwrap();
+        for _ in 0..SHARDS {
+            handler.network().connect(Transport::FramedTcp, addr).unwrap();
+        }
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let _task = listener.for_each_shard_async(|shard| {
+            let sender = sender.clone();
+            move |event| {
+                if let NodeEvent::Network(NetEvent::Accepted(endpoint, _)) = event {
+                    assert_eq!(endpoint.resource_id().shard(), shard);
+                    sender.send((shard, std::thread::current().id())).unwrap();
+                }
+            }
+        });
+
+        let mut accepted = (0..SHARDS)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(1)).unwrap())
+            .collect::<Vec<_>>();
+        accepted.sort_by_key(|(shard, _)| *shard);
+        assert_eq!(accepted.iter().map(|(shard, _)| *shard).collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+        // Each shard is processed by its own thread.
+        let threads = accepted.iter().map(|(_, thread)| *thread).collect::<HashSet<_>>();
+        assert_eq!(threads.len(), SHARDS);
+
+        handler.stop();
+    }
+
+    #[cfg(all(unix, feature = "tcp"))]
+    #[test]
+    fn source_events() {
+        use mio::{Interest};
+        use mio::net::{UnixStream};
+        use std::io::{Read, Write};
+
+        let (handler, listener) = split::<()>();
+
+        let (mut writer, mut reader) = UnixStream::pair().unwrap();
+        let source_id = handler.network().register_source(&mut reader, Interest::READABLE).unwrap();
+
+        // Generated before for_each_async(), it must be cached.
+        writer.write_all(&[1]).unwrap();
+        std::thread::sleep(*SAMPLING_TIMEOUT * 2);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let inner_handler = handler.clone();
+        let _task = listener.for_each_async(move |event| match event {
+            NodeEvent::Source(id, Readiness::Read) => {
+                assert_eq!(id, source_id);
+                let mut data = [0; 16];
+                loop {
+                    match reader.read(&mut data) {
+                        Ok(size) => sender.send(data[..size].to_vec()).unwrap(),
+                        Err(ref err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                        Err(err) => panic!("{}", err),
+                    }
+                }
+                if data[0] == 2 {
+                    inner_handler.network().deregister_source(&mut reader).unwrap();
+                    inner_handler.stop();
+                }
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        });
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), vec![1]);
+        writer.write_all(&[2]).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), vec![2]);
+    }
+
+    #[cfg(all(unix, feature = "os-signals"))]
+    #[test]
+    fn catch_os_signals() {
+        let (handler, listener) = split();
+        handler
+            .catch_os_signals(&[OsSignal::Hangup, OsSignal::Terminate], |os_signal| os_signal)
+            .unwrap();
+
+        // Caught before for_each_async(), it is processed by the cache thread.
+        unsafe { libc::raise(libc::SIGHUP) };
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let inner_handler = handler.clone();
+        let _task = listener.for_each_async(move |event| match event {
+            NodeEvent::Signal(os_signal) => {
+                sender.send(os_signal).unwrap();
+                if os_signal == OsSignal::Terminate {
+                    inner_handler.release_os_signals();
+                    inner_handler.stop();
+                }
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        });
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), OsSignal::Hangup);
+        unsafe { libc::raise(libc::SIGTERM) };
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), OsSignal::Terminate);
+    }
+
+    #[cfg(all(unix, feature = "os-signals"))]
+    #[test]
+    fn catch_os_signals_again() {
+        let (handler, listener) = split();
+        handler.catch_os_signals(&[OsSignal::Interrupt], |os_signal| os_signal).unwrap();
+        handler.release_os_signals();
+        // The pipe of the released catcher is reused.
+        handler.catch_os_signals(&[OsSignal::Interrupt], |os_signal| os_signal).unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let inner_handler = handler.clone();
+        let _task = listener.for_each_async(move |event| match event {
+            NodeEvent::Signal(os_signal) => {
+                sender.send(os_signal).unwrap();
+                inner_handler.release_os_signals();
+                inner_handler.stop();
+            }
+            event => panic!("Unexpected event: {:?}", event),
+        });
+
+        unsafe { libc::raise(libc::SIGINT) };
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), OsSignal::Interrupt);
+    }
 }
@@ -0,0 +1,245 @@
+use crate::network::{NetworkController, SourceId};
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use mio::{Interest};
+use mio::unix::pipe::{self, Sender, Receiver};
+
+use std::collections::{HashMap};
+use std::io::{self, Read};
+use std::mem::{ManuallyDrop};
+use std::os::unix::io::{AsRawFd};
+use std::sync::{
+    Mutex,
+    atomic::{AtomicI32, Ordering},
+};
+
+/// Max number of nodes catching OS signals at the same time.
+const MAX_CATCHERS: usize = 32;
+
+#[allow(clippy::declare_interior_mutable_const)] // Only used to initialize PIPES
+const NO_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Write side of the pipes where the signal handler notifies the caught signals.
+/// It is only accessed with atomic operations to be safe from a signal handler.
+static PIPES: [AtomicI32; MAX_CATCHERS] = [NO_PIPE; MAX_CATCHERS];
+
+lazy_static::lazy_static! {
+    /// Installed handlers: number of catchers using it and the previous action to restore.
+    static ref INSTALLED: Mutex<HashMap<libc::c_int, (usize, libc::sigaction)>> =
+        Mutex::new(HashMap::new());
+
+    /// Pipes of the slots of `PIPES` not used by any catcher.
+    /// A pipe is never closed once created: a signal handler could have loaded its write side
+    /// just before the catcher was dropped, so it is kept to be reused by the next catcher of
+    /// the same slot.
+    static ref FREE_PIPES: Mutex<Vec<Option<(Sender, Receiver)>>> =
+        Mutex::new((0..MAX_CATCHERS).map(|_| None).collect());
+}
+
+/// OS signals that a node can catch.
+/// See [`crate::node::NodeHandler::catch_os_signals()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OsSignal {
+    /// `SIGINT`, usually sent by pressing ctrl-c in the terminal.
+    Interrupt,
+
+    /// `SIGTERM`, the polite way to ask a process to finish.
+    Terminate,
+
+    /// `SIGHUP`, sent when the terminal is closed.
+    /// Daemons often use it to reload their configuration.
+    Hangup,
+}
+
+impl OsSignal {
+    fn number(self) -> libc::c_int {
+        match self {
+            OsSignal::Interrupt => libc::SIGINT,
+            OsSignal::Terminate => libc::SIGTERM,
+            OsSignal::Hangup => libc::SIGHUP,
+        }
+    }
+
+    fn from_number(number: libc::c_int) -> Option<OsSignal> {
+        match number {
+            libc::SIGINT => Some(OsSignal::Interrupt),
+            libc::SIGTERM => Some(OsSignal::Terminate),
+            libc::SIGHUP => Some(OsSignal::Hangup),
+            _ => None,
+        }
+    }
+}
+
+/// Location of the `errno` of the current thread.
+unsafe fn errno() -> *mut libc::c_int {
+    #[cfg(any(target_os = "linux", target_os = "emscripten"))]
+    return libc::__errno_location();
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    return libc::__errno();
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    return libc::__error();
+}
+
+extern "C" fn handle_signal(number: libc::c_int) {
+    // Only async-signal-safe operations are allowed here.
+    // The write could change the errno seen by the interrupted code.
+    let saved_errno = unsafe { *errno() };
+    let byte = number as u8;
+    for pipe in PIPES.iter() {
+        let fd = pipe.load(Ordering::SeqCst);
+        if fd >= 0 {
+            // If the pipe is full the signal is already pending to be read, so it can be lost.
+            unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+        }
+    }
+    unsafe { *errno() = saved_errno };
+}
+
+fn install(number: libc::c_int) -> io::Result<()> {
+    let mut installed = INSTALLED.lock().expect(OTHER_THREAD_ERR);
+    if let Some((users, _)) = installed.get_mut(&number) {
+        *users += 1;
+        return Ok(())
+    }
+
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(number, &action, &mut previous) != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        installed.insert(number, (1, previous));
+    }
+    Ok(())
+}
+
+fn uninstall(number: libc::c_int) {
+    let mut installed = INSTALLED.lock().expect(OTHER_THREAD_ERR);
+    if let Some((users, previous)) = installed.get_mut(&number) {
+        *users -= 1;
+        if *users == 0 {
+            unsafe { libc::sigaction(number, &*previous, std::ptr::null_mut()) };
+            installed.remove(&number);
+        }
+    }
+}
+
+/// Catches OS signals and notifies them through a pipe registered as a source in the node poll.
+/// While it lives, the default action of the caught signals (e.g. finish the process)
+/// is replaced.
+pub(super) struct OsSignalCatcher {
+    slot: usize,
+    signals: Vec<OsSignal>,
+    source_id: SourceId,
+    // Never closed, they are given back to `FREE_PIPES` when the catcher is dropped.
+    receiver: ManuallyDrop<Receiver>,
+    sender: ManuallyDrop<Sender>,
+    registered: bool,
+}
+
+impl OsSignalCatcher {
+    pub fn new(network: &NetworkController, signals: &[OsSignal]) -> io::Result<OsSignalCatcher> {
+        let mut free_pipes = FREE_PIPES.lock().expect(OTHER_THREAD_ERR);
+        let slot = PIPES
+            .iter()
+            .position(|pipe| pipe.load(Ordering::SeqCst) < 0)
+            .ok_or_else(|| io::Error::other("Too many nodes catching OS signals"))?;
+
+        let (sender, mut receiver) = match free_pipes[slot].take() {
+            Some(pipe) => pipe,
+            None => pipe::new()?,
+        };
+        // Discards the signals caught for a previous catcher of the slot.
+        let mut buffer = [0; 32];
+        while matches!(receiver.read(&mut buffer), Ok(size) if size > 0) {}
+
+        let source_id = match network.register_source(&mut receiver, Interest::READABLE) {
+            Ok(source_id) => source_id,
+            Err(err) => {
+                free_pipes[slot] = Some((sender, receiver));
+                return Err(err)
+            }
+        };
+        PIPES[slot].store(sender.as_raw_fd(), Ordering::SeqCst);
+        drop(free_pipes);
+
+        let mut catcher = OsSignalCatcher {
+            slot,
+            signals: Vec::new(),
+            source_id,
+            receiver: ManuallyDrop::new(receiver),
+            sender: ManuallyDrop::new(sender),
+            registered: true,
+        };
+
+        for &signal in signals {
+            if !catcher.signals.contains(&signal) {
+                if let Err(err) = install(signal.number()) {
+                    catcher.release(network);
+                    return Err(err)
+                }
+                catcher.signals.push(signal);
+            }
+        }
+
+        Ok(catcher)
+    }
+
+    pub fn source_id(&self) -> SourceId {
+        self.source_id
+    }
+
+    /// Stops catching signals, removing the pipe from the node poll.
+    pub fn release(mut self, network: &NetworkController) {
+        match network.deregister_source(&mut *self.receiver) {
+            Ok(()) => self.registered = false,
+            Err(err) => log::error!("Deregistering OS signal source: {}", err),
+        }
+    }
+
+    /// Reads all the caught signals.
+    /// Signals caught by other nodes but not requested by this one are discarded.
+    pub fn read(&mut self, mut callback: impl FnMut(OsSignal)) {
+        let mut buffer = [0; 32];
+        loop {
+            match self.receiver.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(size) => buffer[..size]
+                    .iter()
+                    .filter_map(|&number| OsSignal::from_number(number as libc::c_int))
+                    .filter(|signal| self.signals.contains(signal))
+                    .for_each(&mut callback),
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => break log::error!("Reading OS signals: {}", err),
+            }
+        }
+    }
+}
+
+impl Drop for OsSignalCatcher {
+    fn drop(&mut self) {
+        for signal in &self.signals {
+            uninstall(signal.number());
+        }
+        let mut free_pipes = FREE_PIPES.lock().expect(OTHER_THREAD_ERR);
+        PIPES[self.slot].store(-1, Ordering::SeqCst);
+
+        // A pipe still registered in a poll can not be reused, so it is just kept open.
+        if !self.registered {
+            let pipe = unsafe {
+                (ManuallyDrop::take(&mut self.sender), ManuallyDrop::take(&mut self.receiver))
+            };
+            free_pipes[self.slot] = Some(pipe);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use super::{StoredNodeEvent};
+
+use crate::util::thread::{OTHER_THREAD_ERR};
+
+use futures_core::stream::{Stream};
+
+use std::collections::{VecDeque};
+use std::pin::{Pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+struct Shared<S> {
+    events: VecDeque<StoredNodeEvent<S>>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// Creates the two sides of an [`EventStream`].
+pub(super) fn channel<S>() -> (EventStreamSender<S>, EventStream<S>) {
+    let shared = Arc::new(Mutex::new(Shared { events: VecDeque::new(), waker: None, closed: false }));
+    (EventStreamSender(shared.clone()), EventStream(shared))
+}
+
+/// Side of the stream used by the node to push the events.
+/// The stream is finished once this sender is dropped.
+pub(super) struct EventStreamSender<S>(Arc<Mutex<Shared<S>>>);
+
+impl<S> EventStreamSender<S> {
+    pub fn send(&self, event: StoredNodeEvent<S>) {
+        let mut shared = self.0.lock().expect(OTHER_THREAD_ERR);
+        shared.events.push_back(event);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<S> Drop for EventStreamSender<S> {
+    fn drop(&mut self) {
+        let mut shared = self.0.lock().expect(OTHER_THREAD_ERR);
+        shared.closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Stream of the events received by a node (available through the *async* feature).
+/// It is created by [`crate::node::NodeListener::into_stream()`].
+/// The stream finishes once the node is stopped and all the pending events have been consumed.
+///
+/// It does not depend on any async runtime, the task polling the stream is woken by the node
+/// threads.
+pub struct EventStream<S>(Arc<Mutex<Shared<S>>>);
+
+impl<S> Stream for EventStream<S> {
+    type Item = StoredNodeEvent<S>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.0.lock().expect(OTHER_THREAD_ERR);
+        if let Some(event) = shared.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
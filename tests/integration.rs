@@ -1,5 +1,5 @@
 use message_io::network::{NetEvent, Transport, SendStatus};
-use message_io::node::{self, NodeEvent};
+use message_io::node::{self, NodeEvent, StoredNetEvent};
 use message_io::util::thread::{NamespacedThread};
 use message_io::adapters::udp::{self};
 
@@ -81,6 +81,7 @@ fn start_echo_server(
         tx.send(server_addr).unwrap();
 
         listener.for_each(move |event| match event {
+            NodeEvent::Source(..) => unreachable!(),
             NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
             NodeEvent::Network(net_event) => match net_event {
                 NetEvent::Connected(..) => unreachable!(),
@@ -148,6 +149,7 @@ fn start_echo_client_manager(
         }
 
         listener.for_each(move |event| match event {
+            NodeEvent::Source(..) => unreachable!(),
             NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
             NodeEvent::Network(net_event) => match net_event {
                 NetEvent::Connected(server, status) => {
@@ -187,6 +189,7 @@ fn start_burst_receiver(
 
         let mut count = 0;
         listener.for_each(move |event| match event {
+            NodeEvent::Source(..) => unreachable!(),
             NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
             NodeEvent::Network(net_event) => match net_event {
                 NetEvent::Connected(..) => unreachable!(),
@@ -218,6 +221,7 @@ fn start_burst_sender(
 
         let mut count = 0;
         listener.for_each(move |event| match event {
+            NodeEvent::Source(..) => unreachable!(),
             NodeEvent::Signal(_) => {
                 if count < expected_count {
                     let message = format!("{}: {}", SMALL_MESSAGE, count);
@@ -277,6 +281,37 @@ fn burst(transport: Transport, messages_count: usize) {
     let _sender_thread = start_burst_sender(transport, server_addr, messages_count);
 }
 
+// Tcp: Does not apply: it's stream based
+// Udp: Does not apply: the backpressure can not be propagated to the sender
+#[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, 20000))]
+#[cfg_attr(feature = "websocket", test_case(Transport::Ws, 20000))]
+fn burst_enqueue_bounded(transport: Transport, messages_count: usize) {
+    //util::init_logger(LogThread::Enabled); // Enable it for better debugging
+
+    let (node, listener) = node::split::<()>();
+    let (_, receiver_addr) = node.network().listen(transport, LOCAL_ADDR).unwrap();
+    let _sender_thread = start_burst_sender(transport, receiver_addr, messages_count);
+
+    let (mut task, mut receiver) = listener.enqueue_bounded(16);
+
+    let mut count = 0;
+    while count < messages_count {
+        let event = receiver.receive_timeout(*TIMEOUT).expect(TIMEOUT_EVENT_RECV_ERR);
+        match event.network() {
+            StoredNetEvent::Message(_, data) => {
+                let expected_message = format!("{}: {}", SMALL_MESSAGE, count);
+                assert_eq!(expected_message, String::from_utf8_lossy(&data));
+                count += 1;
+            }
+            StoredNetEvent::Accepted(..) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    node.stop();
+    task.wait();
+}
+
 #[cfg_attr(feature = "tcp", test_case(Transport::Tcp, BIG_MESSAGE_SIZE))]
 #[cfg_attr(feature = "tcp", test_case(Transport::FramedTcp, BIG_MESSAGE_SIZE))]
 #[cfg_attr(feature = "udp", test_case(Transport::Udp, udp::MAX_LOCAL_PAYLOAD_LEN))]
@@ -299,6 +334,7 @@ fn message_size(transport: Transport, message_size: usize) {
     let mut received_message = Vec::new();
 
     listener.for_each(move |event| match event {
+        NodeEvent::Source(..) => unreachable!(),
         NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
         NodeEvent::Network(net_event) => match net_event {
             NetEvent::Connected(endpoint, status) => {
@@ -353,6 +389,7 @@ fn multicast_reuse_addr() {
 
     let mut received = 0;
     listener.for_each(move |event| match event {
+        NodeEvent::Source(..) => unreachable!(),
         NodeEvent::Signal(_) => panic!("{}", TIMEOUT_EVENT_RECV_ERR),
         NodeEvent::Network(net_event) => match net_event {
             NetEvent::Connected(endpoint, status) => {